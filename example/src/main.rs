@@ -9,7 +9,7 @@ use tracing_subscriber::{prelude::*, registry::Registry};
 
 fn main() {
     #[cfg(feature = "tracing")]
-    let (chrome_layer, _guard) = ChromeLayerBuilder::new().build();
+    let (chrome_layer, _guard) = ChromeLayerBuilder::new().include_args(true).build();
     #[cfg(feature = "tracing")]
     tracing_subscriber::registry().with(chrome_layer).init();
     let local = false;
@@ -21,7 +21,7 @@ fn main() {
     let arg = &args[1];
     let num_calls = arg
         .parse::<usize>()
-        .expect(&format!("Error parsing argument {arg} as usize"));
+        .unwrap_or_else(|_| panic!("Error parsing argument {arg} as usize"));
     if local {
         eprintln!("RUNNING LOCAL");
         run_local(num_calls);
@@ -13,7 +13,8 @@ use std::{
     time::Duration,
 };
 
-use rrmi::{create_registry, get_registry, remote::RemoteObject};
+use rrmi::metrics::{metrics, Side};
+use rrmi::{call_context, create_registry, get_registry, remote::RemoteObject};
 use rrmi_macros::remote_object;
 use thousands::Separable;
 static HASHMAP_LEN: usize = 100_000;
@@ -28,8 +29,6 @@ static NUM_CLIENTS_LOCAL: u8 = 2;
 #[cfg(feature = "tracing")]
 use tracing::{instrument, span, Level};
 #[cfg(feature = "tracing")]
-use tracing_chrome::ChromeLayerBuilder;
-#[cfg(feature = "tracing")]
 #[allow(unused)]
 use tracing_subscriber::{prelude::*, registry::Registry};
 
@@ -97,7 +96,7 @@ impl NumberServer {
 
     #[remote]
     #[cfg_attr(feature = "tracing", instrument)]
-    fn send_large_vec(&self, data: Vec<f64>) -> () {
+    fn send_large_vec(&self, data: Vec<f64>) {
         eprintln!(
             "Received large vector of size: {}x{}B",
             data.len(),
@@ -107,7 +106,7 @@ impl NumberServer {
 
     #[remote]
     #[cfg_attr(feature = "tracing", instrument)]
-    fn send_hashmap(&self, data: HashMap<String, String>) -> () {
+    fn send_hashmap(&self, data: HashMap<String, String>) {
         eprintln!("Received hashmap with {} elements", data.len())
     }
 
@@ -121,7 +120,7 @@ impl NumberServer {
 
     #[remote]
     #[cfg_attr(feature = "tracing", instrument)]
-    fn barrier_atomic(&self) -> () {
+    fn barrier_atomic(&self) {
         self.barrier_on.store(true, SeqCst);
         self.count.fetch_add(1, SeqCst);
         let mut inside = self.count.load(SeqCst);
@@ -132,7 +131,7 @@ impl NumberServer {
         while inside < self.total_clients {
             inside = self.count.load(SeqCst);
             let status = self.barrier_on.load(SeqCst);
-            if status == false {
+            if !status {
                 break;
             }
             thread::sleep(Duration::from_micros(10));
@@ -141,13 +140,13 @@ impl NumberServer {
 
     #[remote]
     #[cfg_attr(feature = "tracing", instrument)]
-    fn barrier_bar(&self) -> () {
+    fn barrier_bar(&self) {
         self.bar.wait();
     }
 
     #[remote]
     #[cfg_attr(feature = "tracing", instrument)]
-    fn barrier_mutex(&self) -> () {
+    fn barrier_mutex(&self) {
         let barrier_num = self.barrier_num.lock().unwrap();
         let current_num = *barrier_num;
         let mut count = self.count_mut.lock().unwrap();
@@ -168,15 +167,22 @@ impl NumberServer {
 
     #[remote]
     #[cfg_attr(feature = "tracing", instrument)]
-    fn set_done_num(&self, time: Duration) -> () {
+    fn set_done_num(&self, time: Duration) {
         let mut time_num = self.time_num.lock().expect("Could not get lock");
         *time_num += time;
         self.num_clients_done.fetch_add(1, SeqCst);
+        if let Some(client) = call_context() {
+            println!(
+                "Client {:?} done after {time:?} (connection {})",
+                client.peer_addr(),
+                client.connection_id
+            );
+        }
     }
 
     #[remote]
     #[cfg_attr(feature = "tracing", instrument)]
-    fn set_done_arr(&self, time: Duration) -> () {
+    fn set_done_arr(&self, time: Duration) {
         let mut time_arr = self.time_arr.lock().expect("Could not get lock");
         *time_arr += time;
         self.num_clients_done.fetch_add(1, SeqCst);
@@ -184,7 +190,7 @@ impl NumberServer {
 
     #[remote]
     #[cfg_attr(feature = "tracing", instrument)]
-    fn set_done_hash(&self, time: Duration, size: usize) -> () {
+    fn set_done_hash(&self, time: Duration, size: usize) {
         let mut time_hash = self.time_hash.lock().expect("Could not get lock");
         *time_hash += time;
         self.num_clients_done.fetch_add(1, SeqCst);
@@ -201,12 +207,12 @@ impl NumberServer {
 
     fn get_num_info(&self) -> Duration {
         let time = self.time_num.lock().expect("unable to acquire lock");
-        time.clone()
+        *time
     }
 
     fn get_arr_info(&self) -> Duration {
         let time = self.time_arr.lock().expect("unable to acquire lock");
-        time.clone()
+        *time
     }
 
     fn get_hashmap_info(&self) -> (Duration, usize) {
@@ -215,7 +221,7 @@ impl NumberServer {
             .hashmap_total_size
             .lock()
             .expect("Could not acquire lock");
-        (time.clone(), size.clone())
+        (*time, *size)
     }
 }
 
@@ -255,10 +261,10 @@ fn send_nums(stub: &NumberServerStub, times: usize) {
 }
 
 #[cfg_attr(feature = "tracing", instrument)]
-fn send_vecs(stub: &NumberServerStub, times: usize, vector: &Vec<f64>) {
+fn send_vecs(stub: &NumberServerStub, times: usize, vector: &[f64]) {
     let start = Instant::now();
     for _ in 0..times {
-        _ = stub.send_large_vec(vector.clone()).unwrap();
+        stub.send_large_vec(vector.to_vec()).unwrap();
     }
     let time = start.elapsed();
     _ = stub.set_done_arr(time);
@@ -280,7 +286,7 @@ fn send_hashmaps(
 }
 #[cfg_attr(feature = "tracing", instrument)]
 fn client(host: &str, nums: usize, vecs: usize, hashmaps: usize) {
-    let reg = get_registry(host, REG_PORT);
+    let reg = get_registry(host, REG_PORT).expect("registry lookup failed");
     let stub: NumberServerStub = reg
         .lookup("NumberServer")
         .expect("stub lookup failed")
@@ -316,7 +322,7 @@ fn run_clients_local(num_clients: u8, num_calls: usize) {
 #[cfg_attr(feature = "tracing", instrument)]
 fn run_clients_remote(num_clients: u8, num_calls: usize) {
     eprintln!("waiting for {num_clients} clients to finish {num_calls} of inc_num, {NUM_VECS} of send_large_vec and {NUM_HASH} send_hashmap");
-    let reg = get_registry("localhost", REG_PORT);
+    let reg = get_registry("localhost", REG_PORT).expect("registry lookup failed");
     let stub: NumberServerStub = reg
         .lookup("NumberServer")
         .expect("stub lookup failed")
@@ -356,7 +362,7 @@ pub fn server(experiment: fn(u8, usize), num_clients: u8, num_calls: usize) {
 
     // FINAL NUMBER
     eprintln!("Getting RegistryStub");
-    let reg = get_registry("localhost", port);
+    let reg = get_registry("localhost", port).expect("registry lookup failed");
     let stub: NumberServerStub = reg
         .lookup("NumberServer")
         .expect("stub lookup failed")
@@ -394,6 +400,26 @@ pub fn server(experiment: fn(u8, usize), num_clients: u8, num_calls: usize) {
     let hashmap_count = num_clients as usize * NUM_HASH;
     let hashmaps_avg_size = hashmaps_size / hashmap_count;
     print_statistics(time_hash, hashmap_count, hashmaps_avg_size);
+
+    eprintln!("================= METRICS =================");
+    print_metrics();
+}
+
+fn print_metrics() {
+    for (side, object, method, stats) in metrics().iter() {
+        if side != Side::Skeleton || object != "NumberServer" {
+            continue;
+        }
+        eprintln!(
+            "{method}: {} calls, {} errors, mean {:?}, p99 <= {:?}, {}B in, {}B out",
+            stats.calls,
+            stats.errors,
+            stats.latency.mean().unwrap_or_default(),
+            stats.latency.quantile(0.99),
+            stats.request_bytes.separate_with_underscores(),
+            stats.response_bytes.separate_with_underscores(),
+        );
+    }
 }
 
 fn print_statistics(total_time: Duration, total_count: usize, avegare_size: usize) {
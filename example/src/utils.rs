@@ -4,6 +4,7 @@ use gethostname::gethostname;
 #[derive(Debug)]
 pub struct Utils {
     pub my_hostname: String,
+    #[allow(unused)]
     pub slurm_nodes: Vec<String>,
     pub slurm_coordinator: String,
     pub liacs_nodes: Vec<String>,
@@ -85,7 +86,7 @@ fn get_slurm_nodelist() -> String {
 
 fn parse_hostnames(slurm_nodelist: &str) -> Vec<String> {
     // eprintln!("SLURM_NODELIST: {slurm_nodelist}");
-    if slurm_nodelist.len() == 0 {
+    if slurm_nodelist.is_empty() {
         return vec![get_my_hostname()];
     }
     let mut split = slurm_nodelist.split("[");
@@ -102,10 +103,10 @@ fn parse_hostnames(slurm_nodelist: &str) -> Vec<String> {
                 let mut split = e.split("-");
                 let start = split
                     .next()
-                    .expect(&format!("Error when reading start in {e}"));
+                    .unwrap_or_else(|| panic!("Error when reading start in {e}"));
                 let end = split
                     .next()
-                    .expect(&format!("Error when reading end in {e}"));
+                    .unwrap_or_else(|| panic!("Error when reading end in {e}"));
                 let s = start.parse::<u32>().unwrap();
                 let e = end.parse::<u32>().unwrap();
                 let width = start.chars().count();
@@ -1,7 +1,9 @@
 use std::time::Duration;
 
-use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use rrmi::remote::{MockRemoteObject, MockRemoteObjectStub};
 use rrmi::utils::{get_tcp_socket_linear, get_tcp_socket_os};
+use rrmi::{RemoteRef, Stub, create_registry};
 use std::hint::black_box; // adjust import
 
 fn bench_ports(c: &mut Criterion) {
@@ -33,5 +35,43 @@ fn bench_ports(c: &mut Criterion) {
 
     group.finish();
 }
-criterion_group!(benches, bench_ports);
+/// Round trips of a byte vector through a silent MockRemoteObject, per transport.
+fn bench_transports(c: &mut Criterion) {
+    let reg = create_registry(0);
+    reg.bind("echo", MockRemoteObject::silent());
+    let mut transports: Vec<(&str, RemoteRef)> = vec![(
+        "tcp",
+        reg.lookup_via("echo", "127.0.0.1".parse().expect("valid ip"))
+            .expect("echo is bound"),
+    )];
+    #[cfg(unix)]
+    transports.push(("unix", reg.lookup_unix("echo").expect("echo is bound")));
+    #[cfg(target_os = "linux")]
+    transports.push(("shm", reg.lookup_shm("echo").expect("echo is bound")));
+
+    let mut group = c.benchmark_group("round_trip");
+    group.sample_size(10);
+    group.warm_up_time(Duration::from_secs(1));
+    group.measurement_time(Duration::from_secs(5));
+
+    for (transport, remote) in transports {
+        let stub: MockRemoteObjectStub = Stub::new(remote).into();
+        for size in [1 << 10, 1 << 16, 1 << 20, 1 << 24] {
+            let args = vec![7u8; size];
+            group.throughput(Throughput::Bytes(2 * size as u64));
+            group.bench_with_input(BenchmarkId::new(transport, size), &args, |b, args| {
+                b.iter(|| {
+                    black_box(
+                        stub.run("bench", args.clone())
+                            .expect("echo returns the args"),
+                    )
+                })
+            });
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_ports, bench_transports);
 criterion_main!(benches);
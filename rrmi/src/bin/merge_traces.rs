@@ -0,0 +1,32 @@
+//! Merges the Chrome traces written on several hosts into one, lined up on the clock of the
+//! first. Each file becomes a process named after it.
+//!
+//! Usage: `rrmi-merge-traces <trace.json>... > merged.json`
+use std::path::Path;
+use std::process::exit;
+
+use rrmi::trace::{merge_chrome_traces, read_chrome_trace};
+
+fn main() {
+    let paths: Vec<String> = std::env::args().skip(1).collect();
+    if paths.is_empty() {
+        eprintln!("Usage: rrmi-merge-traces <trace.json>... > merged.json");
+        exit(1);
+    }
+    let mut traces = vec![];
+    for path in &paths {
+        let events = read_chrome_trace(path).unwrap_or_else(|e| {
+            eprintln!("{e}");
+            exit(1);
+        });
+        let name = Path::new(path)
+            .file_stem()
+            .map_or(path.clone(), |stem| stem.to_string_lossy().into_owned());
+        traces.push((name, events));
+    }
+    let merged = merge_chrome_traces(traces);
+    if let Err(e) = serde_json::to_writer(std::io::stdout().lock(), &merged) {
+        eprintln!("Could not write merged trace: {e}");
+        exit(1);
+    }
+}
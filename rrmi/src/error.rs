@@ -33,4 +33,19 @@ pub enum RMIError {
 
     #[error("IO error: {0}")]
     IoError(String),
+
+    #[error("Unable to resolve hostname: {0}")]
+    NameResolution(String),
+
+    #[error("No registry answering at {0}")]
+    RegistryUnreachable(String),
+
+    #[error("TLS error: {0}")]
+    Tls(String),
+
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+
+    #[error("Permission denied: {0}")]
+    PermissionDenied(String),
 }
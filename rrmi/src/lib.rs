@@ -1,7 +1,15 @@
+pub mod metrics;
 pub mod remote;
 mod stub;
+pub mod trace;
 use remote::RMI_ID;
-pub use remote::{create_registry, get_registry};
+pub use remote::{
+    create_registry, create_registry_in, get_registry, get_registry_in, get_registry_replicas,
+    try_create_registry,
+};
+pub use remote::{create_registry_psk, get_registry_psk, try_create_registry_psk};
+#[cfg(feature = "tls")]
+pub use remote::{create_registry_tls, get_registry_tls, try_create_registry_tls};
 
 mod error;
 mod transport;
@@ -9,6 +17,23 @@ pub use error::RMIError;
 
 // need for rrmi_macros
 extern crate self as rrmi;
-pub use remote::{RMIResult, RemoteRef};
-pub use stub::{Stub, marshal, unmarshal};
-pub use transport::{TcpClient, TcpStream, Transport, receive_data, send_data, utils};
+pub use metrics::metrics;
+pub use remote::{
+    Authorizer, CallContext, Naming, Principal, RMIResult, RemoteRef, RmiUrl, Roles, call_context,
+};
+pub use stub::{
+    AbortedCall, DynamicStub, RemoteStub, ShutdownReport, Skeleton, Stub, marshal, unmarshal,
+};
+// for the spans `#[remote_object]` enters when built with `tracing`
+#[doc(hidden)]
+pub use tracing as __tracing;
+#[cfg(target_os = "linux")]
+pub use transport::ShmTransport;
+#[cfg(unix)]
+pub use transport::UnixTransport;
+pub use transport::{
+    Client, Connection, Endpoint, Envelope, Headers, MemoryTransport, Message, PreSharedKey,
+    TIMEOUT_HEADER, TcpClient, TcpStream, Transport, receive_data, send_data, utils,
+};
+#[cfg(feature = "tls")]
+pub use transport::{PeerIdentity, TlsClientConfig, TlsServerConfig, TlsTransport, peer_identity};
@@ -0,0 +1,7 @@
+mod prometheus;
+mod recorder;
+mod tests;
+
+pub use prometheus::serve_metrics;
+pub(crate) use recorder::record;
+pub use recorder::{Histogram, LATENCY_BUCKETS, MethodMetrics, Metrics, Side, metrics};
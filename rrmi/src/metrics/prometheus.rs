@@ -0,0 +1,118 @@
+use std::fmt::Write as _;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, ToSocketAddrs};
+use std::thread;
+use std::time::Duration;
+
+use crate::error::RMIError;
+use crate::metrics::{MethodMetrics, Metrics, metrics};
+use crate::remote::RMIResult;
+
+// scrapes are served one at a time, a client that says nothing or too much is cut off
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+const MAX_REQUEST: u64 = 16 * 1024;
+
+/// Name, help text and value of a counter exported per method.
+type Counter = (&'static str, &'static str, fn(&MethodMetrics) -> u64);
+
+const COUNTERS: [Counter; 4] = [
+    ("rrmi_calls_total", "Calls made or served.", |m| m.calls),
+    (
+        "rrmi_errors_total",
+        "Calls refused or left without an answer.",
+        |m| m.errors,
+    ),
+    (
+        "rrmi_request_bytes_total",
+        "Bytes of marshalled requests.",
+        |m| m.request_bytes,
+    ),
+    (
+        "rrmi_response_bytes_total",
+        "Bytes of marshalled responses.",
+        |m| m.response_bytes,
+    ),
+];
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+impl Metrics {
+    /// The snapshot in the Prometheus text exposition format.
+    pub fn to_prometheus(&self) -> String {
+        let mut text = String::new();
+        for (name, help, value) in COUNTERS {
+            let _ = writeln!(text, "# HELP {name} {help}\n# TYPE {name} counter");
+            for (side, object, method, metrics) in self.iter() {
+                let _ = writeln!(
+                    text,
+                    "{name}{{side=\"{side}\",object=\"{}\",method=\"{}\"}} {}",
+                    escape(object),
+                    escape(method),
+                    value(metrics)
+                );
+            }
+        }
+        let name = "rrmi_call_duration_seconds";
+        let _ = writeln!(
+            text,
+            "# HELP {name} Time from sending a request to reading the response on stubs, \
+             from reading a request to sending the response on skeletons.\n# TYPE {name} histogram"
+        );
+        for (side, object, method, metrics) in self.iter() {
+            let labels = format!(
+                "side=\"{side}\",object=\"{}\",method=\"{}\"",
+                escape(object),
+                escape(method)
+            );
+            for (bound, count) in metrics.latency.buckets() {
+                let _ = writeln!(text, "{name}_bucket{{{labels},le=\"{bound}\"}} {count}");
+            }
+            let count = metrics.latency.count();
+            let sum = metrics.latency.sum().as_secs_f64();
+            let _ = writeln!(text, "{name}_bucket{{{labels},le=\"+Inf\"}} {count}");
+            let _ = writeln!(text, "{name}_sum{{{labels}}} {sum}");
+            let _ = writeln!(text, "{name}_count{{{labels}}} {count}");
+        }
+        text
+    }
+}
+
+/// Answers every HTTP request on `addr` with `metrics()` in the Prometheus text format, from
+/// a thread of its own. Returns the address it listens on, useful with port 0.
+pub fn serve_metrics(addr: impl ToSocketAddrs) -> RMIResult<SocketAddr> {
+    let listener = TcpListener::bind(addr).map_err(|e| RMIError::IoError(e.to_string()))?;
+    let local = listener
+        .local_addr()
+        .map_err(|e| RMIError::IoError(e.to_string()))?;
+    thread::Builder::new()
+        .name("Metrics".to_string())
+        .spawn(move || {
+            for stream in listener.incoming().flatten() {
+                if stream.set_read_timeout(Some(REQUEST_TIMEOUT)).is_err()
+                    || stream.set_write_timeout(Some(REQUEST_TIMEOUT)).is_err()
+                {
+                    continue;
+                }
+                let mut reader = BufReader::new((&stream).take(MAX_REQUEST));
+                // skip the request, there is only one page
+                let mut line = String::new();
+                while reader.read_line(&mut line).is_ok_and(|read| read > 2) {
+                    line.clear();
+                }
+                let body = metrics().to_prometheus();
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\n\
+                     Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                let _ = (&stream).write_all(response.as_bytes());
+            }
+        })
+        .map_err(|e| RMIError::IoError(e.to_string()))?;
+    Ok(local)
+}
@@ -0,0 +1,165 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+use crate::remote::{Call, Outcome};
+
+/// Upper bounds of the latency histogram buckets, in seconds.
+pub const LATENCY_BUCKETS: [f64; 16] = [
+    0.00005, 0.0001, 0.00025, 0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0,
+    2.5, 5.0,
+];
+
+static METRICS: LazyLock<Mutex<Metrics>> = LazyLock::new(Mutex::default);
+
+/// Which end of a call the numbers were taken at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Side {
+    /// calls made through stubs, latencies are round trips
+    Stub,
+    /// calls served by skeletons and registries, latencies leave out the network
+    Skeleton,
+}
+
+impl Side {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Side::Stub => "stub",
+            Side::Skeleton => "skeleton",
+        }
+    }
+}
+
+impl Display for Side {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Call latencies counted into the buckets of `LATENCY_BUCKETS`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Histogram {
+    /// one count per bucket and a last one for slower calls
+    counts: [u64; LATENCY_BUCKETS.len() + 1],
+    sum: Duration,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Histogram {
+            counts: [0; LATENCY_BUCKETS.len() + 1],
+            sum: Duration::ZERO,
+        }
+    }
+}
+
+impl Histogram {
+    pub fn observe(&mut self, latency: Duration) {
+        let secs = latency.as_secs_f64();
+        let bucket = LATENCY_BUCKETS.partition_point(|&bound| bound < secs);
+        self.counts[bucket] += 1;
+        self.sum += latency;
+    }
+
+    pub fn count(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    pub fn sum(&self) -> Duration {
+        self.sum
+    }
+
+    pub fn mean(&self) -> Option<Duration> {
+        match self.count() {
+            0 => None,
+            count => Some(self.sum.div_f64(count as f64)),
+        }
+    }
+
+    /// The upper bound of each bucket with the number of calls at or below it, like Prometheus
+    /// buckets. The slowest calls are only in `count`.
+    pub fn buckets(&self) -> impl Iterator<Item = (f64, u64)> + '_ {
+        LATENCY_BUCKETS
+            .iter()
+            .zip(self.counts.iter().scan(0, |total, count| {
+                *total += count;
+                Some(*total)
+            }))
+            .map(|(&bound, count)| (bound, count))
+    }
+
+    /// The bound of the first bucket holding at least `q` of the calls, `None` when there were
+    /// none or they are slower than the last bucket.
+    pub fn quantile(&self, q: f64) -> Option<Duration> {
+        let wanted = (q * self.count() as f64).ceil().max(1.0) as u64;
+        self.buckets()
+            .find(|&(_, count)| count >= wanted)
+            .map(|(bound, _)| Duration::from_secs_f64(bound))
+    }
+}
+
+/// What was recorded for one method of one object type on one side.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MethodMetrics {
+    pub calls: u64,
+    /// calls that were refused or did not get an answer, not methods returning `Err`
+    pub errors: u64,
+    pub latency: Histogram,
+    /// bytes of all marshalled requests, headers included
+    pub request_bytes: u64,
+    pub response_bytes: u64,
+}
+
+impl MethodMetrics {
+    fn record(&mut self, call: &Call, outcome: &Outcome) {
+        self.calls += 1;
+        if outcome.error.is_some() {
+            self.errors += 1;
+        }
+        self.latency.observe(outcome.elapsed);
+        self.request_bytes += call.request_size as u64;
+        self.response_bytes += outcome.response_size as u64;
+    }
+}
+
+/// A snapshot of every call this process made or served, see `metrics`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Metrics {
+    methods: BTreeMap<(Side, &'static str, &'static str), MethodMetrics>,
+}
+
+impl Metrics {
+    pub fn get(&self, side: Side, object: &str, method: &str) -> Option<&MethodMetrics> {
+        self.iter()
+            .find(|&(s, o, m, _)| s == side && o == object && m == method)
+            .map(|(.., metrics)| metrics)
+    }
+
+    /// Side, object type and method of everything recorded, sorted.
+    pub fn iter(&self) -> impl Iterator<Item = (Side, &'static str, &'static str, &MethodMetrics)> {
+        self.methods
+            .iter()
+            .map(|(&(side, object, method), metrics)| (side, object, method, metrics))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.methods.is_empty()
+    }
+}
+
+/// The calls made through stubs and served by skeletons in this process so far, per object
+/// type and method. Registries count as object type `"Registry"`.
+pub fn metrics() -> Metrics {
+    METRICS.lock().expect("Metrics: unable to get lock").clone()
+}
+
+pub(crate) fn record(side: Side, call: &Call, outcome: &Outcome) {
+    METRICS
+        .lock()
+        .expect("Metrics: unable to get lock")
+        .methods
+        .entry((side, call.object, call.method))
+        .or_default()
+        .record(call, outcome);
+}
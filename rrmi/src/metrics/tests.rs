@@ -0,0 +1,130 @@
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests {
+    use crate::metrics::{Histogram, Side, metrics, serve_metrics};
+    use crate::remote::{Call, Interceptor, RemoteObject};
+    use crate::{RMIError, RMIResult, create_registry_in, get_registry_in};
+    use rrmi_macros::remote_object;
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use std::time::Duration;
+
+    #[derive(Debug)]
+    struct Meter;
+
+    #[remote_object]
+    impl Meter {
+        #[remote]
+        fn tick(&self, data: Vec<u8>) -> usize {
+            data.len()
+        }
+    }
+
+    struct Refuse;
+
+    impl Interceptor for Refuse {
+        fn before_call(&self, call: &mut Call) -> RMIResult<()> {
+            Err(RMIError::PermissionDenied(call.method.to_string()))
+        }
+    }
+
+    #[test]
+    fn histogram() {
+        let mut latency = Histogram::default();
+        assert_eq!(latency.mean(), None);
+        assert_eq!(latency.quantile(0.5), None);
+        for micros in [30, 40, 700, 800, 900] {
+            latency.observe(Duration::from_micros(micros));
+        }
+        latency.observe(Duration::from_secs(60));
+        assert_eq!(latency.count(), 6);
+        assert_eq!(latency.sum(), Duration::from_micros(60_002_470));
+        let buckets: Vec<_> = latency.buckets().collect();
+        assert_eq!(buckets[0], (0.00005, 2));
+        assert_eq!(buckets[4], (0.001, 5));
+        assert_eq!(buckets.last(), Some(&(5.0, 5)));
+        assert_eq!(latency.quantile(0.3), Some(Duration::from_micros(50)));
+        assert_eq!(latency.quantile(0.5), Some(Duration::from_millis(1)));
+        // slower than every bucket
+        assert_eq!(latency.quantile(1.0), None);
+    }
+
+    #[test]
+    fn calls_are_counted() {
+        let reg = create_registry_in("metrics").expect("metrics is free");
+        reg.bind("meter", Meter);
+        let rmt_reg = get_registry_in("metrics").expect("registry is in this process");
+        let mut stub: MeterStub = rmt_reg.lookup("meter").expect("meter is in").into();
+        for len in [10, 1000, 100_000] {
+            assert_eq!(stub.tick(vec![1; len]), Ok(len));
+        }
+        stub.add_interceptor(Refuse);
+        assert!(stub.tick(vec![]).is_err());
+
+        let snapshot = metrics();
+        let client = snapshot
+            .get(Side::Stub, "Meter", "tick")
+            .expect("stub calls are counted");
+        assert_eq!((client.calls, client.errors), (4, 1));
+        assert_eq!(client.latency.count(), 4);
+        assert!(client.request_bytes > 101_010);
+        let server = snapshot
+            .get(Side::Skeleton, "Meter", "tick")
+            .expect("served calls are counted");
+        // the refused call never left the stub
+        assert_eq!((server.calls, server.errors), (3, 0));
+        assert_eq!(server.request_bytes, client.request_bytes);
+        assert_eq!(server.response_bytes, client.response_bytes);
+        assert!(snapshot.get(Side::Skeleton, "Registry", "lookup").is_some());
+
+        let text = snapshot.to_prometheus();
+        assert!(text.contains("# TYPE rrmi_calls_total counter\n"));
+        assert!(
+            text.contains("rrmi_errors_total{side=\"stub\",object=\"Meter\",method=\"tick\"} 1\n")
+        );
+        assert!(text.contains(
+            "rrmi_call_duration_seconds_count{side=\"skeleton\",object=\"Meter\",method=\"tick\"} 3\n"
+        ));
+    }
+
+    #[test]
+    fn prometheus_endpoint() {
+        let addr = serve_metrics("127.0.0.1:0").expect("a free port");
+        let reg = create_registry_in("scrape").expect("scrape is free");
+        reg.bind("meter", Meter);
+        let rmt_reg = get_registry_in("scrape").expect("registry is in this process");
+        let _: MeterStub = rmt_reg.lookup("meter").expect("meter is in").into();
+
+        let mut stream = TcpStream::connect(addr).expect("metrics are served");
+        stream
+            .write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .expect("request is sent");
+        let mut response = String::new();
+        stream.read_to_string(&mut response).expect("response");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("rrmi_calls_total{side=\"skeleton\",object=\"Registry\""));
+    }
+
+    #[test]
+    fn prometheus_stuck_clients() {
+        let addr = serve_metrics("127.0.0.1:0").expect("a free port");
+        let _silent = TcpStream::connect(addr).expect("metrics are served");
+        let mut endless = TcpStream::connect(addr).expect("metrics are served");
+        let _ = endless.write_all(&[b'a'; 64 * 1024]);
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let mut stream = TcpStream::connect(addr).expect("metrics are served");
+            stream
+                .write_all(b"GET /metrics HTTP/1.1\r\n\r\n")
+                .expect("request is sent");
+            let mut response = String::new();
+            let _ = tx.send(stream.read_to_string(&mut response).map(|_| response));
+        });
+        let response = rx
+            .recv_timeout(Duration::from_secs(20))
+            .expect("stuck clients should not block the next scrape")
+            .expect("response");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    }
+}
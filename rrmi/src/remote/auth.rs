@@ -0,0 +1,152 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+
+use crate::error::RMIError;
+use crate::remote::RMIResult;
+use crate::remote::context::caller;
+use crate::remote::interceptor::thread_hooks;
+use crate::transport::Connection;
+
+/// Role a caller needs to bind or unbind names in a registry from another host.
+pub const REGISTRY_WRITE_ROLE: &str = "registry-write";
+
+/// Who is calling, as far as the transport can tell.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Principal {
+    pub peer_addr: Option<SocketAddr>,
+    /// the call comes from this host, over loopback, a Unix socket, shared memory or in process
+    pub local: bool,
+    /// the common name of the certificate the caller presented over mutual TLS
+    pub name: Option<String>,
+}
+
+impl Principal {
+    pub fn of(stream: &dyn Connection) -> Self {
+        #[cfg(feature = "tls")]
+        let name = stream.peer_identity().and_then(|id| id.common_name);
+        #[cfg(not(feature = "tls"))]
+        let name = None;
+        Principal {
+            peer_addr: stream.peer_addr(),
+            local: stream.same_host(),
+            name,
+        }
+    }
+}
+
+/// Decides whether `caller` may call `method` of `object`.
+///
+/// `role` is what the method asked for with `#[remote(require = "role")]`. Closures taking the
+/// same arguments are authorizers too.
+pub trait Authorizer: Send + Sync {
+    fn authorize(&self, caller: &Principal, object: &str, method: &str, role: Option<&str>)
+    -> bool;
+}
+
+impl<F> Authorizer for F
+where
+    F: Fn(&Principal, &str, &str, Option<&str>) -> bool + Send + Sync,
+{
+    fn authorize(
+        &self,
+        caller: &Principal,
+        object: &str,
+        method: &str,
+        role: Option<&str>,
+    ) -> bool {
+        self(caller, object, method, role)
+    }
+}
+
+impl Debug for dyn Authorizer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Authorizer")
+    }
+}
+
+/// Grants roles to callers by the name they authenticated with. Methods without a
+/// required role are open to everyone.
+///
+/// ```
+/// use rrmi::remote::{Authorizer, Principal, Roles};
+/// let roles = Roles::new().grant("alice", "admin");
+/// let alice = Principal { peer_addr: None, local: false, name: Some("alice".to_string()) };
+/// assert!(roles.authorize(&alice, "Counter", "reset", Some("admin")));
+/// assert!(!roles.authorize(&alice, "Counter", "reset", Some("operator")));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Roles {
+    roles: HashMap<String, HashSet<String>>,
+}
+
+impl Roles {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn grant(mut self, name: &str, role: &str) -> Self {
+        self.roles
+            .entry(name.to_string())
+            .or_default()
+            .insert(role.to_string());
+        self
+    }
+}
+
+impl Authorizer for Roles {
+    fn authorize(
+        &self,
+        caller: &Principal,
+        _object: &str,
+        _method: &str,
+        role: Option<&str>,
+    ) -> bool {
+        let Some(role) = role else {
+            return true;
+        };
+        caller
+            .name
+            .as_ref()
+            .and_then(|name| self.roles.get(name))
+            .is_some_and(|roles| roles.contains(role))
+    }
+}
+
+/// The authorizer a registry shares with the skeletons of the objects bound to it, so it can
+/// be swapped after they started listening.
+pub(crate) type SharedAuthorizer = Arc<RwLock<Option<Arc<dyn Authorizer>>>>;
+
+/// Whether `caller` passes `authorizer`. Without an authorizer only methods that do not
+/// require a role can be called.
+pub(crate) fn check(
+    authorizer: &SharedAuthorizer,
+    caller: &Principal,
+    object: &str,
+    method: &str,
+    role: Option<&str>,
+) -> RMIResult<()> {
+    let authorizer = authorizer
+        .read()
+        .expect("Authorizer: unable to get lock")
+        .clone();
+    let allowed = match (authorizer, role) {
+        (Some(authorizer), _) => authorizer.authorize(caller, object, method, role),
+        (None, None) => true,
+        (None, Some(_)) => false,
+    };
+    match allowed {
+        true => Ok(()),
+        false => Err(RMIError::PermissionDenied(format!("{object}::{method}"))),
+    }
+}
+
+/// Called by the code `#[remote_object]` generates before running a method. Calls that do not
+/// come through a skeleton, like a direct call in the same program, are not checked.
+pub fn authorize(object: &str, method: &str, role: Option<&str>) -> RMIResult<()> {
+    match (caller(), thread_hooks()) {
+        (Some(caller), Some(hooks)) => check(&hooks.authorizer, &caller, object, method, role),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,99 @@
+use std::cell::RefCell;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::remote::Principal;
+use crate::trace::{TRACEPARENT_HEADER, TraceContext};
+use crate::transport::{Headers, TIMEOUT_HEADER};
+
+static NEXT_CONNECTION: AtomicU64 = AtomicU64::new(1);
+
+thread_local! {
+    // set by the skeleton for the connection it is serving on this thread
+    static CONTEXT: RefCell<Option<CallContext>> = const { RefCell::new(None) };
+}
+
+/// What a `#[remote]` method can know about the call it is running for, see `call_context`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallContext {
+    /// who made the call
+    pub caller: Principal,
+    /// unique within this process, the same for every call over one connection
+    pub connection_id: u64,
+    /// counts the calls over the connection, starting at 1
+    pub request_id: u64,
+    /// when the caller stops waiting for the answer, if it said
+    pub deadline: Option<Instant>,
+    /// the headers the caller sent along with the call
+    pub metadata: Headers,
+    /// the span serving the call, in the trace of the caller when it sent one
+    pub trace: Option<TraceContext>,
+}
+
+impl CallContext {
+    pub fn peer_addr(&self) -> Option<SocketAddr> {
+        self.caller.peer_addr
+    }
+
+    /// The name the caller authenticated with, the certificate common name over mutual TLS.
+    pub fn identity(&self) -> Option<&str> {
+        self.caller.name.as_deref()
+    }
+}
+
+/// The context of the call running on this thread, `None` outside of calls served by a
+/// skeleton. Meant to be called from inside `#[remote]` methods.
+pub fn call_context() -> Option<CallContext> {
+    CONTEXT.with(|context| context.borrow().clone())
+}
+
+/// Starts a fresh context for a connection from `caller` on this thread.
+pub(crate) fn enter_connection(caller: Principal) {
+    let context = CallContext {
+        caller,
+        connection_id: NEXT_CONNECTION.fetch_add(1, Ordering::Relaxed),
+        request_id: 0,
+        deadline: None,
+        metadata: Headers::new(),
+        trace: None,
+    };
+    CONTEXT.with(|current| *current.borrow_mut() = Some(context));
+}
+
+/// Moves the context of this thread on to the next call over the same connection.
+pub(crate) fn next_request() {
+    CONTEXT.with(|current| {
+        if let Some(context) = current.borrow_mut().as_mut() {
+            context.request_id += 1;
+            context.deadline = None;
+            context.metadata.clear();
+            context.trace = None;
+        }
+    });
+}
+
+/// The headers a request came with become the metadata of the call context,
+/// `TIMEOUT_HEADER` also sets the deadline and `TRACEPARENT_HEADER` the trace.
+pub(crate) fn enter_call(headers: Headers) {
+    CONTEXT.with(|current| {
+        if let Some(context) = current.borrow_mut().as_mut() {
+            context.deadline = headers
+                .get(TIMEOUT_HEADER)
+                .and_then(|ms| ms.parse().ok())
+                // too far out to represent is as good as none
+                .and_then(|ms| Instant::now().checked_add(Duration::from_millis(ms)));
+            context.trace = Some(
+                headers
+                    .get(TRACEPARENT_HEADER)
+                    .and_then(|parent| parent.parse::<TraceContext>().ok())
+                    .map_or_else(TraceContext::root, |parent| parent.child()),
+            );
+            context.metadata = headers;
+        }
+    });
+}
+
+pub(crate) fn caller() -> Option<Principal> {
+    CONTEXT.with(|context| context.borrow().as_ref().map(|c| c.caller.clone()))
+}
@@ -0,0 +1,262 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+
+use tracing::warn;
+
+use crate::error::RMIError;
+use crate::remote::replication::unreachable;
+use crate::remote::{RMI_ID, RMIResult, RemoteRef};
+use crate::stub::{RemoteStub, Stub};
+use crate::transport::Endpoint;
+
+/// How many calls in a row a member may fail before a `GroupStub` drops it.
+pub const DEFAULT_MAX_ERRORS: usize = 3;
+
+/// Whether `e` means the member is broken rather than that it refused the call: it could
+/// not be reached, or its answer did not parse, as when it closed the connection.
+fn failing(e: &RMIError) -> bool {
+    unreachable(e)
+        || matches!(
+            e,
+            RMIError::SerializationError(_) | RMIError::DeserializationError(_)
+        )
+}
+
+/// An object of a group, see `Registry::join`.
+#[derive(Debug, Clone)]
+pub(crate) struct Member {
+    pub(crate) id: RMI_ID,
+    /// where it lives when bound by reference, `None` for objects of the registry's process
+    pub(crate) remote: Option<RemoteRef>,
+}
+
+/// The groups of a registry by name, with whose turn it is for the next lookup.
+#[derive(Debug, Default)]
+pub(crate) struct Groups {
+    groups: HashMap<String, (Vec<Member>, usize)>,
+}
+
+impl Groups {
+    /// Adds `member` to group `name`, returns whether that made the group.
+    pub(crate) fn join(&mut self, name: &str, member: Member) -> bool {
+        let (members, _) = self.groups.entry(name.to_string()).or_default();
+        members.push(member);
+        members.len() == 1
+    }
+
+    /// Takes member `id` out of group `name`, returns it and whether it was the last one.
+    /// The group goes with its last member.
+    pub(crate) fn leave(&mut self, name: &str, id: RMI_ID) -> RMIResult<(Member, bool)> {
+        let (members, _) = self
+            .groups
+            .get_mut(name)
+            .ok_or_else(|| RMIError::NameNotFound(name.to_string()))?;
+        let index = members
+            .iter()
+            .position(|member| member.id == id)
+            .ok_or(RMIError::ObjectNotFound(id))?;
+        let member = members.remove(index);
+        let last = members.is_empty();
+        if last {
+            self.groups.remove(name);
+        }
+        Ok((member, last))
+    }
+
+    pub(crate) fn remove(&mut self, name: &str) -> Option<Vec<Member>> {
+        self.groups.remove(name).map(|(members, _)| members)
+    }
+
+    pub(crate) fn members(&self, name: &str) -> Option<Vec<Member>> {
+        self.groups.get(name).map(|(members, _)| members.clone())
+    }
+
+    /// The member of group `name` whose turn it is, round-robin.
+    pub(crate) fn next(&mut self, name: &str) -> Option<Member> {
+        let (members, turn) = self.groups.get_mut(name)?;
+        let member = members[*turn % members.len()].clone();
+        *turn = turn.wrapping_add(1);
+        Some(member)
+    }
+
+    pub(crate) fn names(&self) -> impl Iterator<Item = &String> {
+        self.groups.keys()
+    }
+}
+
+/// Which member of a group a `GroupStub` sends a call to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Balance {
+    /// each member in turn
+    #[default]
+    RoundRobin,
+    /// the member with the fewest calls under way, in turn when they are even
+    LeastOutstanding,
+    Random,
+}
+
+/// A member of a group as a `GroupStub` calls it.
+struct Connected<S> {
+    addr: Endpoint,
+    // a stub has one connection, calls on it go one at a time
+    stub: Mutex<S>,
+    outstanding: AtomicUsize,
+    // calls in a row it failed
+    errors: AtomicUsize,
+}
+
+/// Calls the members of a group, see `RegistryStub::lookup_group`, spreading them by a
+/// `Balance`. A member that failed `max_errors` calls in a row is dropped,
+/// calls fail once there are none left.
+///
+/// Calls are not sent again to another member when one fails, they may have run.
+pub struct GroupStub<S> {
+    name: String,
+    balance: Balance,
+    max_errors: usize,
+    members: RwLock<Vec<Arc<Connected<S>>>>,
+    turn: AtomicUsize,
+}
+
+impl<S: RemoteStub> GroupStub<S> {
+    /// Connects to the members of group `name`, leaving out the ones that cannot be reached.
+    pub(crate) fn connect(name: &str, stubs: Vec<Stub>) -> RMIResult<Self> {
+        let mut members = vec![];
+        let mut failed = None;
+        for stub in stubs {
+            let addr = stub.remote.addr.clone();
+            match S::connect(stub) {
+                Ok(stub) => members.push(Arc::new(Connected {
+                    addr,
+                    stub: Mutex::new(stub),
+                    outstanding: AtomicUsize::new(0),
+                    errors: AtomicUsize::new(0),
+                })),
+                Err(e) => {
+                    warn!(group = name, %addr, error = %e, "group member unreachable");
+                    failed = Some(e);
+                }
+            }
+        }
+        if let (true, Some(e)) = (members.is_empty(), failed) {
+            return Err(e);
+        }
+        Ok(GroupStub {
+            name: name.to_string(),
+            balance: Balance::default(),
+            max_errors: DEFAULT_MAX_ERRORS,
+            members: RwLock::new(members),
+            turn: AtomicUsize::new(0),
+        })
+    }
+
+    pub fn with_balance(self, balance: Balance) -> Self {
+        GroupStub { balance, ..self }
+    }
+
+    /// Drops members after they failed `max_errors` calls in a row, at least one.
+    pub fn with_max_errors(self, max_errors: usize) -> Self {
+        GroupStub {
+            max_errors: max_errors.max(1),
+            ..self
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn members(&self) -> std::sync::RwLockReadGuard<'_, Vec<Arc<Connected<S>>>> {
+        self.members
+            .read()
+            .expect("GroupStub: unable to get members lock")
+    }
+
+    /// Where the members still called are.
+    pub fn addrs(&self) -> Vec<Endpoint> {
+        self.members()
+            .iter()
+            .map(|member| member.addr.clone())
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.members().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members().is_empty()
+    }
+
+    /// Makes `call` on the stub of the member the balance picks:
+    /// `group.call(|counter| counter.increment())`.
+    pub fn call<T>(&self, call: impl FnOnce(&S) -> RMIResult<T>) -> RMIResult<T> {
+        let member = self.pick()?;
+        member.outstanding.fetch_add(1, Ordering::SeqCst);
+        let result = call(
+            &member
+                .stub
+                .lock()
+                .expect("GroupStub: unable to get member lock"),
+        );
+        member.outstanding.fetch_sub(1, Ordering::SeqCst);
+        match &result {
+            Err(e) if failing(e) => self.failed(&member, e),
+            _ => member.errors.store(0, Ordering::Relaxed),
+        }
+        result
+    }
+
+    fn pick(&self) -> RMIResult<Arc<Connected<S>>> {
+        let members = self.members();
+        if members.is_empty() {
+            return Err(RMIError::TransportError(format!(
+                "no members left in group {}",
+                self.name
+            )));
+        }
+        let turn = self.turn.fetch_add(1, Ordering::Relaxed);
+        let index = match self.balance {
+            Balance::RoundRobin => turn % members.len(),
+            Balance::LeastOutstanding => (0..members.len())
+                .map(|i| (turn + i) % members.len())
+                .min_by_key(|&i| members[i].outstanding.load(Ordering::SeqCst))
+                .unwrap_or(0),
+            Balance::Random => getrandom::u64().map_or(turn, |r| r as usize) % members.len(),
+        };
+        Ok(Arc::clone(&members[index]))
+    }
+
+    /// Counts a call `member` failed, dropping it when that makes too many.
+    fn failed(&self, member: &Arc<Connected<S>>, e: &RMIError) {
+        let errors = member.errors.fetch_add(1, Ordering::Relaxed) + 1;
+        if errors < self.max_errors {
+            return;
+        }
+        warn!(group = self.name, addr = %member.addr, errors, error = %e, "dropping group member");
+        self.members
+            .write()
+            .expect("GroupStub: unable to get members lock")
+            .retain(|m| !Arc::ptr_eq(m, member));
+    }
+}
+
+impl<S> Debug for GroupStub<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let members = self
+            .members
+            .read()
+            .expect("GroupStub: unable to get members lock");
+        f.debug_struct("GroupStub")
+            .field("name", &self.name)
+            .field("balance", &self.balance)
+            .field("max_errors", &self.max_errors)
+            .field(
+                "members",
+                &members.iter().map(|m| &m.addr).collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
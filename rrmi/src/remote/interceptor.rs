@@ -0,0 +1,290 @@
+use std::cell::RefCell;
+use std::fmt::Debug;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::error::RMIError;
+use crate::metrics::{Side, record};
+use crate::remote::RMIResult;
+use crate::remote::SharedAuthorizer;
+use crate::remote::call_context;
+use crate::remote::context::enter_call;
+use crate::stub::note_method;
+#[cfg(feature = "tracing")]
+use crate::trace::{CALL_SPAN, SERVE_SPAN};
+use crate::trace::{TRACEPARENT_HEADER, TraceContext};
+use crate::transport::{Envelope, Headers, Message, Transport};
+use crate::{marshal, unmarshal};
+
+#[cfg(feature = "tracing")]
+use tracing::Level;
+
+thread_local! {
+    // set by the skeleton for the connection it is serving on this thread
+    static HOOKS: RefCell<Option<Hooks>> = const { RefCell::new(None) };
+}
+
+/// A call to a remote object as interceptors see it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Call {
+    /// the type name of the object, what `RemoteObject::name` returns
+    pub object: &'static str,
+    pub method: &'static str,
+    /// stub interceptors can add headers in `before_call`, they are sent with the request
+    pub headers: Headers,
+    /// bytes of the marshalled request, not known yet in `before_call` on stubs
+    pub request_size: usize,
+}
+
+impl Call {
+    pub fn new(
+        object: &'static str,
+        method: &'static str,
+        headers: Headers,
+        request_size: usize,
+    ) -> Self {
+        Call {
+            object,
+            method,
+            headers,
+            request_size,
+        }
+    }
+}
+
+/// How a call went, given to `after_call`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Outcome {
+    /// bytes of the marshalled response, 0 when there was none
+    pub response_size: usize,
+    pub elapsed: Duration,
+    /// why the call failed, if it was refused or never got an answer
+    pub error: Option<RMIError>,
+}
+
+/// Hooks around every call going through a stub or a skeleton.
+///
+/// Interceptors run in the order they were added before the call and in reverse order after
+/// it. An error from `before_call` stops the call: the interceptors after it and the method
+/// do not run, the caller gets the error.
+pub trait Interceptor: Send + Sync {
+    fn before_call(&self, call: &mut Call) -> RMIResult<()> {
+        let _ = call;
+        Ok(())
+    }
+
+    /// Runs for every interceptor whose `before_call` ran, whatever the outcome.
+    fn after_call(&self, call: &Call, outcome: &Outcome) {
+        let _ = (call, outcome);
+    }
+}
+
+impl Debug for dyn Interceptor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Interceptor")
+    }
+}
+
+/// An ordered chain of interceptors.
+#[derive(Debug, Clone, Default)]
+pub struct Interceptors {
+    chain: Vec<Arc<dyn Interceptor>>,
+}
+
+impl Interceptors {
+    pub fn push(&mut self, interceptor: impl Interceptor + 'static) {
+        self.chain.push(Arc::new(interceptor));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chain.is_empty()
+    }
+
+    /// Runs `before_call` down the chain until one refuses the call.
+    pub fn start(&self, call: &mut Call) -> Started {
+        let mut ran = 0;
+        let mut refused = None;
+        for interceptor in &self.chain {
+            ran += 1;
+            if let Err(e) = interceptor.before_call(call) {
+                refused = Some(e);
+                break;
+            }
+        }
+        Started {
+            chain: self.chain[..ran].to_vec(),
+            at: Instant::now(),
+            refused,
+            side: None,
+            #[cfg(feature = "tracing")]
+            span: None,
+        }
+    }
+}
+
+/// A call the interceptors have seen the start of, `finish` it once it is done.
+#[derive(Debug)]
+#[must_use]
+pub struct Started {
+    chain: Vec<Arc<dyn Interceptor>>,
+    at: Instant,
+    refused: Option<RMIError>,
+    // where to count the call in `metrics()`, not counted when None
+    side: Option<Side>,
+    #[cfg(feature = "tracing")]
+    span: Option<tracing::span::EnteredSpan>,
+}
+
+impl Started {
+    /// The error an interceptor refused the call with, the call should not go ahead.
+    pub fn refused(&self) -> Option<RMIError> {
+        self.refused.clone()
+    }
+
+    pub fn finish(self, call: &Call, response_size: usize, error: Option<RMIError>) {
+        let outcome = Outcome {
+            response_size,
+            elapsed: self.at.elapsed(),
+            error,
+        };
+        for interceptor in self.chain.iter().rev() {
+            interceptor.after_call(call, &outcome);
+        }
+        if let Some(side) = self.side {
+            record(side, call, &outcome);
+        }
+    }
+}
+
+pub(crate) type SharedInterceptors = Arc<RwLock<Interceptors>>;
+
+/// What a registry shares with the skeletons of the objects bound to it, so it can be changed
+/// after they started listening.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Hooks {
+    pub(crate) authorizer: SharedAuthorizer,
+    pub(crate) interceptors: SharedInterceptors,
+}
+
+impl Hooks {
+    pub(crate) fn add_interceptor(&self, interceptor: impl Interceptor + 'static) {
+        self.interceptors
+            .write()
+            .expect("Interceptors: unable to get lock")
+            .push(interceptor);
+    }
+
+    /// Runs the interceptors' `before_call` for a call served on this side.
+    pub(crate) fn start(&self, call: &mut Call) -> Started {
+        note_method(call.method);
+        let interceptors = self
+            .interceptors
+            .read()
+            .expect("Interceptors: unable to get lock")
+            .clone();
+        Started {
+            side: Some(Side::Skeleton),
+            ..interceptors.start(call)
+        }
+    }
+}
+
+pub(crate) fn set_thread_hooks(hooks: Option<Hooks>) {
+    HOOKS.with(|current| *current.borrow_mut() = hooks);
+}
+
+pub(crate) fn thread_hooks() -> Option<Hooks> {
+    HOOKS.with(|current| current.borrow().clone())
+}
+
+/// Called by the code `#[remote_object]` generates once a request is decoded: its headers
+/// become the call context and the skeleton's interceptors see the call.
+pub fn before_call(call: &mut Call) -> Started {
+    enter_call(call.headers.clone());
+    #[cfg(feature = "tracing")]
+    let span = serve_span(call);
+    #[allow(unused_mut)]
+    let mut started = thread_hooks().unwrap_or_default().start(call);
+    #[cfg(feature = "tracing")]
+    {
+        started.span = Some(span);
+    }
+    started
+}
+
+/// The span a skeleton serves a call in, linked to the stub span through the trace ids.
+#[cfg(feature = "tracing")]
+fn serve_span(call: &Call) -> tracing::span::EnteredSpan {
+    let trace = call_context().and_then(|context| context.trace);
+    let parent = call
+        .headers
+        .get(TRACEPARENT_HEADER)
+        .and_then(|parent| parent.parse::<TraceContext>().ok());
+    tracing::span!(
+        Level::TRACE,
+        SERVE_SPAN,
+        object = call.object,
+        method = call.method,
+        trace_id = trace.map(|t| t.trace_id_hex()),
+        span_id = trace.map(|t| t.span_id_hex()),
+        parent_id = parent.map(|p| p.span_id_hex()),
+    )
+    .entered()
+}
+
+/// Sends `body` as `call` through `interceptors` and `transport`, `read` turns the response
+/// into what the stub method returns. Called by the stubs `#[remote_object]` generates.
+pub fn invoke<REQ, RES, T>(
+    transport: &impl Transport,
+    interceptors: &Interceptors,
+    mut call: Call,
+    body: REQ,
+    read: impl FnOnce(RES) -> RMIResult<T>,
+) -> RMIResult<T>
+where
+    REQ: Message,
+    RES: Message,
+{
+    // a call made while serving one continues its trace
+    let trace = call_context()
+        .and_then(|context| context.trace)
+        .map_or_else(TraceContext::root, |parent| parent.child());
+    call.headers
+        .entry(TRACEPARENT_HEADER.to_string())
+        .or_insert_with(|| trace.to_string());
+    #[cfg(feature = "tracing")]
+    let _span = tracing::span!(
+        Level::TRACE,
+        CALL_SPAN,
+        object = call.object,
+        method = call.method,
+        trace_id = trace.trace_id_hex(),
+        span_id = trace.span_id_hex(),
+    )
+    .entered();
+    let started = Started {
+        side: Some(Side::Stub),
+        ..interceptors.start(&mut call)
+    };
+    if let Some(e) = started.refused() {
+        started.finish(&call, 0, Some(e.clone()));
+        return Err(e);
+    }
+    let request = Envelope {
+        headers: call.headers.clone(),
+        body,
+    };
+    let mut response_size = 0;
+    let result = marshal(&request)
+        .and_then(|bytes| {
+            call.request_size = bytes.len();
+            transport.call(bytes)
+        })
+        .and_then(|bytes| {
+            response_size = bytes.len();
+            unmarshal(&bytes)
+        })
+        .and_then(read);
+    started.finish(&call, response_size, result.as_ref().err().cloned());
+    result
+}
@@ -0,0 +1,146 @@
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, ErrorKind, Read, Write};
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use tracing::warn;
+
+use crate::error::RMIError;
+use crate::remote::replication::Change;
+use crate::remote::{RMIResult, RemoteRef};
+use crate::stub::{marshal, unmarshal};
+use crate::transport::{Client, Endpoint};
+
+/// An append-only file of the remote binds of a registry, see `Registry::use_journal`.
+///
+/// Entries are framed like requests on the wire, a big-endian length then CBOR, each written
+/// in one go so a crash leaves at most a truncated last entry, which replaying skips.
+/// Replaying stops at an entry that does not decode, what follows it is not read.
+#[derive(Debug)]
+pub(crate) struct Journal {
+    path: PathBuf,
+    file: File,
+}
+
+fn io_error(path: &Path, e: std::io::Error) -> RMIError {
+    RMIError::IoError(format!("{}: {e}", path.display()))
+}
+
+impl Journal {
+    /// The bindings the journal at `path` ends up with, none when there is no file yet, and
+    /// whether it was read to the end rather than stopped at an entry that does not decode.
+    pub(crate) fn replay(path: &Path) -> RMIResult<(BTreeMap<String, RemoteRef>, bool)> {
+        let mut bindings = BTreeMap::new();
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok((bindings, true)),
+            Err(e) => return Err(io_error(path, e)),
+        };
+        let mut left = file.metadata().map_err(|e| io_error(path, e))?.len();
+        let mut reader = BufReader::new(file);
+        loop {
+            let mut len = [0u8; 4];
+            match reader.read_exact(&mut len) {
+                Ok(()) => (),
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof && left == 0 => break,
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => {
+                    warn!(journal = %path.display(), "skipping truncated journal entry");
+                    return Ok((bindings, false));
+                }
+                Err(e) => return Err(io_error(path, e)),
+            }
+            // a corrupt length could ask for gigabytes, an entry cannot be longer than the file
+            let len = u64::from(u32::from_be_bytes(len));
+            left = left.saturating_sub(4);
+            let entry = match len <= left {
+                true => {
+                    let mut bytes = vec![0u8; len as usize];
+                    left -= len;
+                    reader
+                        .read_exact(&mut bytes)
+                        .ok()
+                        .and_then(|_| unmarshal::<Change>(&bytes).ok())
+                }
+                false => None,
+            };
+            match entry {
+                Some(Change::Bind { name, remote }) => {
+                    bindings.insert(name, remote);
+                }
+                Some(Change::Unbind { name } | Change::Expire { name }) => {
+                    bindings.remove(&name);
+                }
+                None => {
+                    warn!(journal = %path.display(), "skipping journal entry that does not decode");
+                    return Ok((bindings, false));
+                }
+            }
+        }
+        Ok((bindings, true))
+    }
+
+    /// Replaces the journal at `path` with one holding only `bindings`, open for appending.
+    pub(crate) fn create(path: &Path, bindings: &BTreeMap<String, RemoteRef>) -> RMIResult<Self> {
+        let compacted = path.with_extension("compacting");
+        let mut file = File::create(&compacted).map_err(|e| io_error(&compacted, e))?;
+        for (name, remote) in bindings {
+            let entry = Change::Bind {
+                name: name.clone(),
+                remote: remote.clone(),
+            };
+            file.write_all(&frame(&entry)?)
+                .map_err(|e| io_error(&compacted, e))?;
+        }
+        file.sync_all().map_err(|e| io_error(&compacted, e))?;
+        std::fs::rename(&compacted, path).map_err(|e| io_error(path, e))?;
+        let file = OpenOptions::new()
+            .append(true)
+            .open(path)
+            .map_err(|e| io_error(path, e))?;
+        Ok(Journal {
+            path: path.to_path_buf(),
+            file,
+        })
+    }
+
+    pub(crate) fn bind(&mut self, name: &str, remote: &RemoteRef) -> RMIResult<()> {
+        self.append(&Change::Bind {
+            name: name.to_string(),
+            remote: remote.clone(),
+        })
+    }
+
+    pub(crate) fn unbind(&mut self, name: &str) -> RMIResult<()> {
+        self.append(&Change::Unbind {
+            name: name.to_string(),
+        })
+    }
+
+    fn append(&mut self, entry: &Change) -> RMIResult<()> {
+        let path = &self.path;
+        self.file
+            .write_all(&frame(entry)?)
+            .map_err(|e| io_error(path, e))?;
+        self.file.sync_data().map_err(|e| io_error(path, e))
+    }
+}
+
+fn frame(entry: &Change) -> RMIResult<Vec<u8>> {
+    let bytes = marshal(entry)?;
+    let mut frame = (bytes.len() as u32).to_be_bytes().to_vec();
+    frame.extend(bytes);
+    Ok(frame)
+}
+
+/// Whether something still listens where `remote` points, without making a call.
+pub(crate) fn reachable(remote: &RemoteRef, timeout: Duration) -> RMIResult<()> {
+    match &remote.addr {
+        // the handshake needs client credentials the registry does not have
+        Endpoint::Tls(addr) => TcpStream::connect_timeout(addr, timeout)
+            .map(drop)
+            .map_err(|e| RMIError::TransportError(format!("{addr}: {e}"))),
+        _ => Client::connect_timeout(remote, timeout).map(drop),
+    }
+}
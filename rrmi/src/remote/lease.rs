@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+use std::sync::mpsc::{RecvTimeoutError, Sender, channel};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use tracing::{debug, warn};
+
+use crate::error::RMIError;
+use crate::remote::registry::RegistryStub;
+use crate::remote::{RMIResult, RemoteRef};
+
+/// How often a registry looks for leases that ran out.
+pub(crate) const LEASE_POLL: Duration = Duration::from_millis(50);
+
+/// When the names bound with a lease expire, as a registry keeps them.
+#[derive(Debug, Default)]
+pub(crate) struct Leases {
+    deadlines: HashMap<String, Instant>,
+}
+
+impl Leases {
+    pub(crate) fn grant(&mut self, name: &str, ttl: Duration) {
+        self.deadlines
+            .insert(name.to_string(), Instant::now() + ttl);
+    }
+
+    /// Pushes the deadline of `name` back, fails when it holds no lease.
+    pub(crate) fn renew(&mut self, name: &str, ttl: Duration) -> RMIResult<()> {
+        let deadline = self
+            .deadlines
+            .get_mut(name)
+            .ok_or_else(|| RMIError::NameNotFound(name.to_string()))?;
+        *deadline = Instant::now() + ttl;
+        Ok(())
+    }
+
+    pub(crate) fn revoke(&mut self, name: &str) {
+        self.deadlines.remove(name);
+    }
+
+    /// Takes out the names whose lease ran out by `now`.
+    pub(crate) fn expired(&mut self, now: Instant) -> Vec<String> {
+        let expired: Vec<String> = self
+            .deadlines
+            .iter()
+            .filter(|(_, deadline)| **deadline <= now)
+            .map(|(name, _)| name.clone())
+            .collect();
+        for name in &expired {
+            self.deadlines.remove(name);
+        }
+        expired
+    }
+}
+
+/// The longest lease a registry grants, longer ones could not be added to an `Instant`.
+pub(crate) const MAX_LEASE: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+
+/// Checks a lease time makes sense before it is granted or renewed.
+pub(crate) fn check_ttl(ttl: Duration) -> RMIResult<()> {
+    match (LEASE_POLL..=MAX_LEASE).contains(&ttl) {
+        true => Ok(()),
+        false => Err(RMIError::BadArguments(format!(
+            "lease of {ttl:?}, between {LEASE_POLL:?} and {MAX_LEASE:?} needed"
+        ))),
+    }
+}
+
+/// A name bound with `RegistryStub::bind_with_lease`, renewed three times per lease time on
+/// a thread of its own. When the registry forgot the name, after a restart or a failover to
+/// another replica, it is bound again. Once the name is bound to another remote, it stops.
+///
+/// Dropping it stops renewing and unbinds the name, unless it was bound to another remote.
+#[derive(Debug)]
+pub struct Lease {
+    registry: RegistryStub,
+    name: String,
+    remote: RemoteRef,
+    // dropped to stop the renewing thread
+    stop: Option<Sender<()>>,
+    renewing: Option<JoinHandle<()>>,
+}
+
+/// What `name` is bound to, `None` when it is not bound at all.
+fn bound_to(registry: &RegistryStub, name: &str) -> RMIResult<Option<RemoteRef>> {
+    match registry.lookup(name) {
+        Ok(stub) => Ok(Some(stub.remote)),
+        Err(RMIError::NameNotFound(_)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+impl Lease {
+    pub(crate) fn start(
+        registry: RegistryStub,
+        name: &str,
+        remote: RemoteRef,
+        ttl: Duration,
+    ) -> RMIResult<Self> {
+        let (stop, stopped) = channel::<()>();
+        let renewer = registry.clone();
+        let leased = name.to_string();
+        let ours = remote.clone();
+        let renewing = std::thread::Builder::new()
+            .name(format!("Lease{name}"))
+            .spawn(move || {
+                while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(ttl / 3) {
+                    let renewed = match renewer.renew(&leased, ttl) {
+                        Err(RMIError::NameNotFound(_)) => match bound_to(&renewer, &leased) {
+                            Ok(Some(other)) if other != ours => {
+                                debug!(name = leased, "name bound elsewhere, lease given up");
+                                break;
+                            }
+                            Ok(_) => {
+                                debug!(name = leased, "lease lost, binding again");
+                                renewer.lease(&leased, ours.clone(), ttl)
+                            }
+                            Err(e) => Err(e),
+                        },
+                        renewed => renewed,
+                    };
+                    if let Err(e) = renewed {
+                        warn!(name = leased, error = %e, "could not renew lease");
+                    }
+                }
+            })
+            .map_err(|e| RMIError::IoError(e.to_string()))?;
+        Ok(Lease {
+            registry,
+            name: name.to_string(),
+            remote,
+            stop: Some(stop),
+            renewing: Some(renewing),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Drop for Lease {
+    fn drop(&mut self) {
+        drop(self.stop.take());
+        if let Some(renewing) = self.renewing.take() {
+            let _ = renewing.join();
+        }
+        match bound_to(&self.registry, &self.name) {
+            Ok(Some(remote)) if remote == self.remote => {
+                if let Err(e) = self.registry.unbind(&self.name) {
+                    debug!(name = self.name, error = %e, "could not unbind leased name");
+                }
+            }
+            Ok(Some(_)) => debug!(name = self.name, "leased name bound elsewhere, left alone"),
+            Ok(None) => (),
+            Err(e) => debug!(name = self.name, error = %e, "could not unbind leased name"),
+        }
+    }
+}
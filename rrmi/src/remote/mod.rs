@@ -1,7 +1,38 @@
+mod auth;
+pub use auth::{Authorizer, Principal, REGISTRY_WRITE_ROLE, Roles, authorize};
+pub(crate) use auth::{SharedAuthorizer, check};
+mod context;
+pub use context::{CallContext, call_context};
+mod group;
+mod interceptor;
+mod journal;
+mod lease;
+mod naming;
+mod replication;
+mod watch;
+pub(crate) use context::{enter_connection, next_request};
+pub use group::{Balance, DEFAULT_MAX_ERRORS, GroupStub};
+pub use interceptor::{Call, Interceptor, Interceptors, Outcome, Started, before_call, invoke};
+pub(crate) use interceptor::{Hooks, set_thread_hooks};
+pub use lease::Lease;
+pub use naming::{DEFAULT_REGISTRY_PORT, Naming, RmiUrl, check_name};
+pub use replication::{Change, Entry};
+pub use watch::{Watch, WatchEvent};
+
 pub mod registry;
-pub use registry::{RMI_ID, Registry, create_registry, get_registry};
+pub use registry::{
+    ObjectInfo, RMI_ID, Registry, create_registry, create_registry_in, get_registry,
+    get_registry_in, get_registry_replicas, try_create_registry,
+};
+pub use registry::{create_registry_psk, get_registry_psk, try_create_registry_psk};
+#[cfg(feature = "tls")]
+pub use registry::{create_registry_tls, get_registry_tls, try_create_registry_tls};
 
+#[allow(clippy::module_inception)]
 mod remote;
-pub use remote::{MockRemoteObject, MockRemoteObjectStub, RMIResult, RemoteObject, RemoteRef};
+pub use remote::{
+    MethodSignature, MockRemoteObject, MockRemoteObjectStub, RMIResult, RemoteObject, RemoteRef,
+    fingerprint,
+};
 
 mod tests;
@@ -0,0 +1,177 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::error::RMIError;
+use crate::remote::registry::{RegistryStub, get_registry};
+use crate::remote::{ObjectInfo, RMIResult, RemoteRef};
+use crate::stub::{DynamicStub, Stub};
+
+pub const DEFAULT_REGISTRY_PORT: u16 = 1099;
+
+/// Checks `name` can be bound: one or more `/` separated segments, none of them empty,
+/// like `counter` or `jobs/worker-3/counter`.
+pub fn check_name(name: &str) -> RMIResult<()> {
+    let invalid = |why: &str| RMIError::BadArguments(format!("invalid name {name:?}: {why}"));
+    if name.is_empty() {
+        return Err(invalid("empty"));
+    }
+    if name.split('/').any(str::is_empty) {
+        return Err(invalid("empty segment"));
+    }
+    if name.chars().any(char::is_control) {
+        return Err(invalid("control character"));
+    }
+    Ok(())
+}
+
+/// What is left of `name` below `prefix`, matching whole segments: `jobs/worker-3` is under
+/// `jobs` but not under `job`. Everything is under the empty prefix.
+pub(crate) fn under<'a>(name: &'a str, prefix: &str) -> Option<&'a str> {
+    if prefix.is_empty() {
+        return Some(name);
+    }
+    name.strip_prefix(prefix)?.strip_prefix('/')
+}
+
+/// Where a name lives: `rrmi://host:port/path/name`. The port defaults to 1099 and the
+/// scheme may be left out, `//host/name` like Java RMI URLs.
+///
+/// ```
+/// use rrmi::RmiUrl;
+/// let url: RmiUrl = "rrmi://node3/jobs/worker-3/counter".parse().expect("valid");
+/// assert_eq!(url.host, "node3");
+/// assert_eq!(url.port, 1099);
+/// assert_eq!(url.name, "jobs/worker-3/counter");
+/// assert_eq!(url.to_string(), "rrmi://node3:1099/jobs/worker-3/counter");
+/// let v6: RmiUrl = "//[::1]:2099/counter".parse().expect("valid");
+/// assert_eq!((v6.host.as_str(), v6.port), ("::1", 2099));
+/// assert!("rrmi://node3/jobs//counter".parse::<RmiUrl>().is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RmiUrl {
+    pub host: String,
+    pub port: u16,
+    /// empty for the URL of the registry itself
+    pub name: String,
+}
+
+impl RmiUrl {
+    pub fn new(host: &str, port: u16, name: &str) -> Self {
+        RmiUrl {
+            host: host.to_string(),
+            port,
+            name: name.to_string(),
+        }
+    }
+
+    /// The registry the URL points at.
+    pub fn registry(&self) -> RMIResult<RegistryStub> {
+        get_registry(&self.host, self.port)
+    }
+
+    /// The name, failing with `RMIError::BadArguments` for URLs of a registry.
+    fn bound_name(&self) -> RMIResult<&str> {
+        check_name(&self.name)?;
+        Ok(&self.name)
+    }
+}
+
+impl FromStr for RmiUrl {
+    type Err = RMIError;
+
+    fn from_str(url: &str) -> RMIResult<Self> {
+        let bad = |why: &str| RMIError::BadArguments(format!("{url}: {why}"));
+        let rest = url.strip_prefix("rrmi:").unwrap_or(url);
+        let rest = rest
+            .strip_prefix("//")
+            .ok_or_else(|| bad("expected rrmi://host[:port]/name"))?;
+        let (authority, name) = rest.split_once('/').unwrap_or((rest, ""));
+        let (host, port) = match authority.rsplit_once(':') {
+            // a bare IPv6 address has colons but no port
+            Some((host, port)) if !host.contains(':') || host.ends_with(']') => {
+                let port = port.parse().map_err(|_| bad("invalid port"))?;
+                (host, port)
+            }
+            _ => (authority, DEFAULT_REGISTRY_PORT),
+        };
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        if host.is_empty() {
+            return Err(bad("no host"));
+        }
+        if !name.is_empty() {
+            check_name(name)?;
+        }
+        Ok(RmiUrl::new(host, port, name))
+    }
+}
+
+impl Display for RmiUrl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.host.contains(':') {
+            true => write!(f, "rrmi://[{}]:{}/{}", self.host, self.port, self.name),
+            false => write!(f, "rrmi://{}:{}/{}", self.host, self.port, self.name),
+        }
+    }
+}
+
+/// Looks up, binds and lists names by URL, so clients do not have to get hold of the
+/// registry first, like `java.rmi.Naming`.
+///
+/// ```
+/// use rrmi::{create_registry, Naming};
+/// use rrmi::remote::MockRemoteObject;
+/// let reg = create_registry(1104);
+/// reg.bind("jobs/worker-3/counter", MockRemoteObject::silent());
+/// reg.bind("jobs/worker-4/counter", MockRemoteObject::silent());
+/// let found = Naming::list("rrmi://localhost:1104/jobs").expect("registry is listening");
+/// assert_eq!(found, vec![
+///     "rrmi://localhost:1104/jobs/worker-3/counter",
+///     "rrmi://localhost:1104/jobs/worker-4/counter",
+/// ]);
+/// assert!(Naming::lookup("rrmi://localhost:1104/jobs/worker-3/counter").is_ok());
+/// ```
+#[derive(Debug)]
+pub struct Naming;
+
+impl Naming {
+    pub fn lookup(url: &str) -> RMIResult<Stub> {
+        let url: RmiUrl = url.parse()?;
+        let name = url.bound_name()?;
+        url.registry()?.lookup(name)
+    }
+
+    pub fn lookup_dynamic(url: &str) -> RMIResult<DynamicStub> {
+        let url: RmiUrl = url.parse()?;
+        let name = url.bound_name()?;
+        url.registry()?.lookup_dynamic(name)
+    }
+
+    pub fn describe(url: &str) -> RMIResult<ObjectInfo> {
+        let url: RmiUrl = url.parse()?;
+        let name = url.bound_name()?;
+        url.registry()?.describe(name)
+    }
+
+    /// Binds the name in `url` to `remote`, an object this process exported.
+    pub fn bind(url: &str, remote: RemoteRef) -> RMIResult<()> {
+        let url: RmiUrl = url.parse()?;
+        let name = url.bound_name()?;
+        url.registry()?.bind(name, remote)
+    }
+
+    pub fn unbind(url: &str) -> RMIResult<()> {
+        let url: RmiUrl = url.parse()?;
+        let name = url.bound_name()?;
+        url.registry()?.unbind(name)
+    }
+
+    /// The URLs of the names under the path of `url`, of every name for a registry URL.
+    pub fn list(url: &str) -> RMIResult<Vec<String>> {
+        let url: RmiUrl = url.parse()?;
+        let names = url.registry()?.list_under(&url.name)?;
+        Ok(names
+            .iter()
+            .map(|name| RmiUrl::new(&url.host, url.port, name).to_string())
+            .collect())
+    }
+}
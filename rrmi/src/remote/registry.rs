@@ -1,19 +1,112 @@
 #[allow(non_camel_case_types)]
 pub type RMI_ID = usize;
-use super::{RemoteObject, RemoteRef};
+use super::group::{GroupStub, Groups, Member};
+use super::journal::{Journal, reachable};
+use super::lease::{LEASE_POLL, Lease, Leases, check_ttl};
+use super::naming::{check_name, under};
+use super::replication::{Change, Entry, REPLICATION_TIMEOUT, Replicas, unreachable};
+use super::watch::{Watch, WatchEvent, Watcher, watcher_info};
+use super::{
+    Authorizer, Call, Hooks, Interceptor, MethodSignature, Principal, REGISTRY_WRITE_ROLE,
+    RemoteObject, RemoteRef, check,
+};
 use crate::error::RMIError;
-use crate::stub::Skeleton;
-use crate::transport::SocketAddr;
-use crate::transport::utils::{get_addr, get_local_ips};
+use crate::stub::{
+    DROP_TIMEOUT, DynamicStub, RemoteStub, Serving, ShutdownReport, Skeleton, wake_tcp,
+};
+use crate::transport::utils::{
+    get_local_ips, get_tcp_listener, happy_eyeballs, is_local, is_same_host, resolve_addrs,
+};
+use crate::transport::{
+    Client, Endpoint, Headers, MemoryListener, MemoryStream, PreSharedKey, SocketAddr, TcpStream,
+};
+#[cfg(feature = "tls")]
+use crate::transport::{TlsClientConfig, TlsServerConfig, TlsStream};
 
 // use rrmi_macros::remote_object;
 use std::collections::HashMap;
+use std::fmt::Debug;
 use std::net::IpAddr;
-use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::{Arc, Mutex};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{Sender, channel};
+use std::sync::{Arc, LazyLock, Mutex, OnceLock, Weak};
+use std::time::{Duration, Instant, SystemTime};
 
 #[cfg(feature = "tracing")]
 use tracing::instrument;
+use tracing::{debug, error, info, warn};
+
+pub(crate) static PING_TIMEOUT: Duration = Duration::from_secs(2);
+// how many registries a lookup may be forwarded through, so peers pointing at each other
+// do not send it around forever
+const MAX_HOPS: u8 = 4;
+// registries created in this process by port, so get_registry can skip the sockets
+static LOCAL_REGISTRIES: LazyLock<Mutex<HashMap<u16, Weak<Registry>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+fn registry_namespace(namespace: &str) -> String {
+    format!("registry/{namespace}")
+}
+
+/// The in-process namespace of the registry on `port`, one `create_registry_in` cannot take.
+fn port_namespace(port: u16) -> String {
+    format!(":{port}")
+}
+
+fn check_namespace(namespace: &str) -> RMIResult<()> {
+    match namespace.is_empty() || namespace.contains(':') {
+        true => Err(RMIError::BadArguments(format!(
+            "invalid registry namespace {namespace:?}, it is empty or has a ':'"
+        ))),
+        false => Ok(()),
+    }
+}
+
+/// A client over TCP only has events sent to its own host, so it cannot have the registry
+/// connect to someone else.
+pub(crate) fn check_watcher(caller: &Principal, watcher: &RemoteRef) -> RMIResult<()> {
+    let Some(peer) = caller.peer_addr else {
+        return Ok(());
+    };
+    match &watcher.addr {
+        Endpoint::Tcp(addr) if addr.ip().to_canonical() == peer.ip().to_canonical() => Ok(()),
+        other => Err(RMIError::PermissionDenied(format!(
+            "watcher at {other} for a caller at {}",
+            peer.ip()
+        ))),
+    }
+}
+
+/// What `Registry::describe` tells about a bound name.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ObjectInfo {
+    pub name: String,
+    /// `RemoteObject::name` of the object, not known for objects bound by reference
+    pub type_name: Option<String>,
+    pub id: RMI_ID,
+    /// where the last lookup sent clients, `None` before the first one
+    pub addr: Option<Endpoint>,
+    /// see `RemoteObject::fingerprint`, not known for objects bound by reference
+    pub fingerprint: Option<String>,
+    pub bound_at: SystemTime,
+    pub methods: Vec<MethodSignature>,
+}
+
+/// Who is told about changes of the names under `prefix`, see `Registry::watch`.
+#[derive(Debug)]
+struct Watching {
+    id: u64,
+    prefix: String,
+    events: Sender<WatchEvent>,
+}
+
+/// When a name was bound and where lookups of it were last sent.
+#[derive(Debug, Clone)]
+struct Bound {
+    at: SystemTime,
+    addr: Option<Endpoint>,
+}
 
 #[derive(Debug)]
 pub struct Registry {
@@ -21,7 +114,34 @@ pub struct Registry {
     pub port: u16,
     objects: Arc<Mutex<HashMap<RMI_ID, Arc<Skeleton>>>>, // hashmap and objects should be thread safe
     names: Arc<Mutex<HashMap<String, RMI_ID>>>,
+    // objects living in other processes, bound by reference
+    remotes: Arc<Mutex<HashMap<String, RemoteRef>>>,
+    // names several objects are bound under, see `join`
+    groups: Mutex<Groups>,
+    bound: Arc<Mutex<HashMap<String, Bound>>>,
+    hooks: Hooks,
+    // the address lookups hand out instead of the one clients reached us on
+    advertised_ip: Mutex<Option<IpAddr>>,
     next_id: Arc<AtomicUsize>,
+    unix_sockets: AtomicBool,
+    shared_memory: AtomicBool,
+    in_process: AtomicBool,
+    #[cfg(feature = "tls")]
+    tls: Option<TlsServerConfig>,
+    psk: Option<PreSharedKey>,
+    // where remote binds are recorded, see `use_journal`
+    journal: Mutex<Option<Journal>>,
+    // registries unknown names are forwarded to, by the prefix they are mounted at
+    peers: Mutex<Vec<(String, Arc<RegistryStub>)>>,
+    // the other registries remote binds are kept in step with, see `use_replicas`
+    replicas: Mutex<Option<Arc<Replicas>>>,
+    watchers: Mutex<Vec<Watching>>,
+    leases: Mutex<Leases>,
+    // whether the thread expiring leases runs
+    reaping: AtomicBool,
+    // the registry as its threads hold it, set once it listens
+    this: OnceLock<Weak<Registry>>,
+    serving: Serving,
 }
 // #[remote_object]
 impl Registry {
@@ -30,43 +150,137 @@ impl Registry {
             port,
             objects: Arc::new(Mutex::new(HashMap::new())),
             names: Arc::new(Mutex::new(HashMap::new())),
+            remotes: Arc::new(Mutex::new(HashMap::new())),
+            groups: Mutex::new(Groups::default()),
+            bound: Arc::new(Mutex::new(HashMap::new())),
+            hooks: Hooks::default(),
+            advertised_ip: Mutex::new(None),
             next_id: Arc::new(AtomicUsize::new(1)), // keep 0 for itself
+            unix_sockets: AtomicBool::new(cfg!(unix)),
+            shared_memory: AtomicBool::new(cfg!(target_os = "linux")),
+            in_process: AtomicBool::new(true),
+            #[cfg(feature = "tls")]
+            tls: None,
+            psk: None,
+            journal: Mutex::new(None),
+            peers: Mutex::new(vec![]),
+            replicas: Mutex::new(None),
+            watchers: Mutex::new(vec![]),
+            leases: Mutex::new(Leases::default()),
+            reaping: AtomicBool::new(false),
+            this: OnceLock::new(),
+            serving: Serving::default(),
         }
     }
 
-    #[allow(dead_code)]
-    pub fn default() -> Registry {
-        Registry::new(1099)
+    /// A registry only reachable over TCP, for when every client has to go through a handshake.
+    fn sockets_only(port: u16) -> Self {
+        let reg = Registry::new(port);
+        reg.use_in_process(false);
+        reg.use_unix_sockets(false);
+        reg.use_shared_memory(false);
+        reg
+    }
+
+    /// Whether `get_registry` calls from this process reach the registry, and through it
+    /// its objects, over in-process channels instead of sockets. On by default.
+    pub fn use_in_process(&self, enabled: bool) {
+        self.in_process.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Whether lookups coming from this host are answered with a Unix domain socket
+    /// instead of a TCP port. On by default where Unix sockets exist.
+    pub fn use_unix_sockets(&self, enabled: bool) {
+        self.unix_sockets
+            .store(enabled && cfg!(unix), Ordering::Relaxed);
+    }
+
+    /// Whether same host lookups get a shared memory ring rather than a plain Unix socket.
+    /// On by default on Linux, has no effect when Unix sockets are off.
+    pub fn use_shared_memory(&self, enabled: bool) {
+        self.shared_memory
+            .store(enabled && cfg!(target_os = "linux"), Ordering::Relaxed);
+    }
+
+    /// Makes lookups from other hosts point at `ip`, for hosts behind NAT or with several
+    /// interfaces where the one clients come in on is not the one they should call.
+    pub fn set_advertised_ip(&self, ip: IpAddr) {
+        *self
+            .advertised_ip
+            .lock()
+            .expect("Registry: unable to get advertised ip lock") = Some(ip);
+    }
+
+    fn advertised_ip(&self) -> Option<IpAddr> {
+        *self
+            .advertised_ip
+            .lock()
+            .expect("Registry: unable to get advertised ip lock")
+    }
+
+    /// Decides who may call methods marked `#[remote(require = "role")]` on the objects bound
+    /// here, and which callers from other hosts may bind and unbind names.
+    ///
+    /// Without one, only callers on this host can change bindings, like Java's rmiregistry.
+    /// With one, other hosts need `REGISTRY_WRITE_ROLE` for the `bind` or `unbind` method
+    /// of `Registry`.
+    pub fn set_authorizer(&self, authorizer: impl Authorizer + 'static) {
+        *self
+            .hooks
+            .authorizer
+            .write()
+            .expect("Registry: unable to get authorizer lock") = Some(Arc::new(authorizer));
+    }
+
+    /// Adds `interceptor` at the end of the chain that calls to the objects bound here, and
+    /// to the registry itself, go through.
+    pub fn add_interceptor(&self, interceptor: impl Interceptor + 'static) {
+        self.hooks.add_interceptor(interceptor);
+    }
+
+    /// Whether `caller` may bind or unbind names, `method` being which of the two.
+    pub(crate) fn authorize_mutation(&self, caller: &Principal, method: &str) -> RMIResult<()> {
+        if caller.local {
+            return Ok(());
+        }
+        check(
+            &self.hooks.authorizer,
+            caller,
+            self.name(),
+            method,
+            Some(REGISTRY_WRITE_ROLE),
+        )
     }
 
     #[cfg_attr(feature = "tracing", instrument)]
-    pub fn get_ip(&self) -> Result<IpAddr, ()> {
-        let ips = get_local_ips().map_err(|e| eprintln!("Error getting local ip: {e:?}"));
-        match ips {
-            Ok(ips) => {
-                let ip = ips[0];
-                Ok(ip)
-            }
-            Err(_) => Err(()),
+    pub fn get_ip(&self) -> RMIResult<IpAddr> {
+        if let Some(ip) = self.advertised_ip() {
+            return Ok(ip);
         }
+        let ips = get_local_ips().inspect_err(|e| warn!(error = %e, "could not get local ip"))?;
+        ips.first()
+            .copied()
+            .ok_or(RMIError::TransportError("Cannot get local ip".to_string()))
     }
 
     #[cfg_attr(feature = "tracing", instrument)]
-    pub fn construct_addr(&self, port: u16) -> Result<SocketAddr, ()> {
+    pub fn construct_addr(&self, port: u16) -> RMIResult<SocketAddr> {
         // this will be slower than just saving it
-        let ips = get_local_ips().map_err(|e| eprintln!("Error getting local ip: {e:?}"));
         //TODO: handle multiple ips case
-        match ips {
-            Ok(ips) => {
-                let ip = ips[0];
-                Ok(SocketAddr::new(ip, port))
-            }
-            Err(_) => Err(()),
-        }
+        let ip = self.get_ip()?;
+        Ok(SocketAddr::new(ip, port))
     }
 
     #[cfg_attr(feature = "tracing", instrument)]
     pub fn remove(&self, name: &str) -> RMIResult<()> {
+        self.remove_object(name)?;
+        self.notify(WatchEvent::Unbound {
+            name: name.to_string(),
+        });
+        Ok(())
+    }
+
+    fn remove_object(&self, name: &str) -> RMIResult<()> {
         let mut names = self
             .names
             .lock()
@@ -80,12 +294,15 @@ impl Registry {
             .objects
             .lock()
             .expect("Registry: unable to get objects lock");
-        let _sk = objects.remove(&id).ok_or(RMIError::ObjectNotFound(id))?;
-        // todo!("make sure the object is also droped");
-        // let left = objects.keys().count();
-        // let strong = Arc::strong_count(&sk);
-        // let weak = Arc::strong_count(&sk);
-        // eprintln!("removed: now strong, weak = {strong},{weak} remaining: {left}");
+        let skeleton = objects.remove(&id).ok_or(RMIError::ObjectNotFound(id))?;
+        self.bound
+            .lock()
+            .expect("Registry: unable to get bound lock")
+            .remove(name);
+        // dropping the skeleton waits for its calls, which may need the registry
+        drop(objects);
+        drop(names);
+        drop(skeleton);
         Ok(())
     }
 
@@ -95,12 +312,12 @@ impl Registry {
             .names
             .lock()
             .expect("Registry: unable to get names lock");
-        let id = names
-            .get(name).copied()
-            .ok_or(RMIError::NameNotFound(name.to_string()));
-        id
+        names
+            .get(name)
+            .copied()
+            .ok_or(RMIError::NameNotFound(name.to_string()))
     }
-    
+
     #[cfg_attr(feature = "tracing", instrument)]
     pub fn get(&self, id: RMI_ID) -> RMIResult<Arc<Skeleton>> {
         //! RMI_ID -> Skeleton | for server
@@ -118,221 +335,2090 @@ impl Registry {
     #[cfg_attr(feature = "tracing", instrument)]
     pub fn lookup(&self, name: &str) -> RMIResult<RemoteRef> {
         //! name -> remote ref | for client
-        let id = self.get_id(name)?;
+        self.lookup_for(name, &Caller::Unknown)
+    }
+
+    /// Same as `lookup` but advertises `ip`, the address the client used to reach the registry.
+    /// Skeletons listen on every interface so a client that came in over `::1`
+    /// gets a `RemoteRef` it can reach over `::1` as well.
+    #[cfg_attr(feature = "tracing", instrument)]
+    pub fn lookup_via(&self, name: &str, ip: IpAddr) -> RMIResult<RemoteRef> {
+        let remote = self.reach_via(self.get_id(name)?, ip)?;
+        Ok(self.advertise(name, remote))
+    }
+
+    fn reach_via(&self, id: RMI_ID, ip: IpAddr) -> RMIResult<RemoteRef> {
         let skeleton = self.get(id)?;
-        let port = skeleton.listen()?;
-        let addr = self
-            .construct_addr(port)
-            .map_err(|_| RMIError::TransportError("Cannot get local ip".to_string()))?;
-        Ok(RemoteRef { addr, id })
+        #[cfg(feature = "tls")]
+        if let Some(tls) = &self.tls {
+            let port = skeleton.listen_tls(tls)?;
+            return Ok(RemoteRef::new(Endpoint::Tls(SocketAddr::new(ip, port)), id));
+        }
+        let port = match &self.psk {
+            Some(key) => skeleton.listen_psk(key)?,
+            None => skeleton.listen()?,
+        };
+        Ok(RemoteRef::new(SocketAddr::new(ip, port), id))
+    }
+
+    /// Same as `lookup` but over a Unix domain socket, only usable from this host.
+    #[cfg(unix)]
+    #[cfg_attr(feature = "tracing", instrument)]
+    pub fn lookup_unix(&self, name: &str) -> RMIResult<RemoteRef> {
+        let remote = self.reach_unix(self.get_id(name)?)?;
+        Ok(self.advertise(name, remote))
+    }
+
+    #[cfg(unix)]
+    fn reach_unix(&self, id: RMI_ID) -> RMIResult<RemoteRef> {
+        let path = self.get(id)?.listen_unix()?;
+        Ok(RemoteRef::new(path, id))
+    }
+
+    /// Same as `lookup_unix` but calls go through shared memory, only usable from this host.
+    #[cfg(target_os = "linux")]
+    #[cfg_attr(feature = "tracing", instrument)]
+    pub fn lookup_shm(&self, name: &str) -> RMIResult<RemoteRef> {
+        let remote = self.reach_shm(self.get_id(name)?)?;
+        Ok(self.advertise(name, remote))
+    }
+
+    #[cfg(target_os = "linux")]
+    fn reach_shm(&self, id: RMI_ID) -> RMIResult<RemoteRef> {
+        let path = self.get(id)?.listen_shm()?;
+        Ok(RemoteRef::new(Endpoint::Shm(path), id))
+    }
+
+    #[cfg(unix)]
+    fn reach_same_host(&self, id: RMI_ID) -> RMIResult<RemoteRef> {
+        #[cfg(target_os = "linux")]
+        if self.shared_memory.load(Ordering::Relaxed) {
+            return self.reach_shm(id);
+        }
+        self.reach_unix(id)
+    }
+
+    /// Same as `lookup` but over in-process channels, only usable from this process.
+    #[cfg_attr(feature = "tracing", instrument)]
+    pub fn lookup_memory(&self, name: &str) -> RMIResult<RemoteRef> {
+        let remote = self.reach_memory(self.get_id(name)?)?;
+        Ok(self.advertise(name, remote))
+    }
+
+    fn reach_memory(&self, id: RMI_ID) -> RMIResult<RemoteRef> {
+        let channel = self.get(id)?.listen_memory()?;
+        Ok(RemoteRef::new(Endpoint::Memory(channel), id))
+    }
+
+    /// Where `client` can call object `id` of this process.
+    fn reach(&self, id: RMI_ID, client: &Caller) -> RMIResult<RemoteRef> {
+        match client {
+            Caller::InProcess => self.reach_memory(id),
+            #[cfg(unix)]
+            Caller::SameHost => self.reach_same_host(id),
+            Caller::Reached(ip) => self.reach_via(id, self.advertised_ip().unwrap_or(*ip)),
+            Caller::Unknown => self.reach_via(id, self.get_ip()?),
+        }
+    }
+
+    fn reach_member(&self, member: Member, client: &Caller) -> RMIResult<RemoteRef> {
+        match member.remote {
+            Some(remote) => Ok(remote),
+            None => self.reach(member.id, client),
+        }
+    }
+
+    /// What a lookup of `name` by `client` answers, for a group the member whose turn it is.
+    fn lookup_for(&self, name: &str, client: &Caller) -> RMIResult<RemoteRef> {
+        if let Some(remote) = self.remote_binding(name) {
+            return Ok(remote);
+        }
+        let member = self.groups().next(name);
+        let remote = match member {
+            Some(member) => self.reach_member(member, client)?,
+            None => self.reach(self.get_id(name)?, client)?,
+        };
+        Ok(self.advertise(name, remote))
+    }
+
+    /// What a lookup of group `name` by `client` answers, every member. A name bound to a
+    /// single object is a group of one.
+    fn lookup_group_for(&self, name: &str, client: &Caller) -> RMIResult<Vec<RemoteRef>> {
+        let members = self.groups().members(name);
+        match members {
+            Some(members) => members
+                .into_iter()
+                .map(|member| self.reach_member(member, client))
+                .collect(),
+            None => self.lookup_for(name, client).map(|remote| vec![remote]),
+        }
     }
 
     // #[remote]
     #[cfg_attr(feature = "tracing", instrument)]
     pub fn list(&self) -> RMIResult<Vec<String>> {
-        let names: Vec<String> = self
+        let mut names: Vec<String> = self
             .names
             .lock()
             .expect("Registry: unable to get names lock")
             .keys()
             .cloned()
             .collect();
-        match names.len() {
-            0 => RMIResult::Err(RMIError::EmptyRegistry()),
-            _ => RMIResult::Ok(names),
+        names.extend(
+            self.remotes
+                .lock()
+                .expect("Registry: unable to get remotes lock")
+                .keys()
+                .cloned(),
+        );
+        names.extend(self.groups().names().cloned());
+        names.sort();
+        Ok(names)
+    }
+
+    /// The names below `prefix`, `jobs` lists `jobs/worker-3/counter` but not `jobs-old/counter`.
+    #[cfg_attr(feature = "tracing", instrument)]
+    pub fn list_under(&self, prefix: &str) -> RMIResult<Vec<String>> {
+        let prefix = prefix.trim_end_matches('/');
+        Ok(self
+            .list()?
+            .into_iter()
+            .filter(|name| under(name, prefix).is_some())
+            .collect())
+    }
+
+    /// Forwards lookups and describes of names under `prefix` that are not bound here to
+    /// `peer`, without the prefix: with a peer at `node3`, `node3/jobs/counter` is looked up
+    /// there as `jobs/counter`. With an empty prefix every unknown name is forwarded as is.
+    ///
+    /// Peers are asked longest prefix first. They answer with addresses for other hosts,
+    /// the client calls the object directly.
+    pub fn add_peer(&self, prefix: &str, peer: RegistryStub) {
+        let prefix = prefix.trim_matches('/').to_string();
+        info!(prefix, peer = %peer.remote.addr, "forwarding lookups");
+        self.peers
+            .lock()
+            .expect("Registry: unable to get peers lock")
+            .push((prefix, Arc::new(peer)));
+    }
+
+    /// `found`, unless the name is not bound here: then what the peers mounted over `name`
+    /// answer, the first one that has it.
+    fn or_forward<T>(
+        &self,
+        found: RMIResult<T>,
+        name: &str,
+        hops: u8,
+        ask: impl Fn(&RegistryStub, &str) -> RMIResult<T>,
+    ) -> RMIResult<T> {
+        if !matches!(found, Err(RMIError::NameNotFound(_))) || hops >= MAX_HOPS {
+            return found;
+        }
+        let mut peers: Vec<(String, Arc<RegistryStub>)> = self
+            .peers
+            .lock()
+            .expect("Registry: unable to get peers lock")
+            .iter()
+            .filter(|(prefix, _)| under(name, prefix).is_some())
+            .cloned()
+            .collect();
+        peers.sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
+        let mut failed = None;
+        for (prefix, peer) in peers {
+            let rest = under(name, &prefix).unwrap_or(name);
+            match ask(&peer, rest) {
+                Err(RMIError::NameNotFound(_)) => (),
+                Err(e) => {
+                    warn!(name, peer = %peer.remote.addr, error = %e, "peer failed");
+                    failed = Some(e);
+                }
+                Ok(found) => {
+                    debug!(name, peer = %peer.remote.addr, "forwarded");
+                    return Ok(found);
+                }
+            }
+        }
+        Err(failed.unwrap_or_else(|| RMIError::NameNotFound(name.to_string())))
+    }
+
+    /// What is bound to `name`: the object's type, id, address, fingerprint and methods.
+    #[cfg_attr(feature = "tracing", instrument)]
+    pub fn describe(&self, name: &str) -> RMIResult<ObjectInfo> {
+        let bound = self
+            .bound
+            .lock()
+            .expect("Registry: unable to get bound lock")
+            .get(name)
+            .cloned();
+        let bound_at = bound.as_ref().map_or(SystemTime::UNIX_EPOCH, |b| b.at);
+        // a group is described by its first member
+        let member = self
+            .groups()
+            .members(name)
+            .and_then(|members| members.into_iter().next());
+        let remote = match &member {
+            Some(member) => member.remote.clone(),
+            None => self.remote_binding(name),
+        };
+        if let Some(remote) = remote {
+            return Ok(ObjectInfo {
+                name: name.to_string(),
+                type_name: None,
+                id: remote.id,
+                addr: Some(remote.addr),
+                fingerprint: None,
+                bound_at,
+                methods: vec![],
+            });
         }
+        let id = match member {
+            Some(member) => member.id,
+            None => self.get_id(name)?,
+        };
+        let skeleton = self.get(id)?;
+        let object = skeleton.object();
+        Ok(ObjectInfo {
+            name: name.to_string(),
+            type_name: Some(object.name().to_string()),
+            id,
+            addr: bound.and_then(|b| b.addr),
+            fingerprint: Some(object.fingerprint()),
+            bound_at,
+            methods: object.methods(),
+        })
     }
 
     pub fn bind<Obj: RemoteObject + 'static>(&self, name: &str, object: Obj) -> (Arc<Obj>, RMI_ID) {
         // bind a skelton to the registry
         let object_ref = Arc::new(object);
         let arc_object = Arc::clone(&object_ref);
-        let skeleton = Arc::new(Skeleton::new(object_ref));
+        let skeleton = Arc::new(Skeleton::with_hooks(object_ref, self.hooks.clone()));
         let id = self.next_id.fetch_add(1, Ordering::Relaxed);
         self.objects
             .lock()
             .expect("Registry: unable to get objects lock")
             .insert(id, skeleton);
-        self.names
+        let replaced = self
+            .names
             .lock()
             .expect("Registry: unable to get names lock")
-            .insert(name.to_string(), id);
-        eprintln!("Registered {id}: {name}");
+            .insert(name.to_string(), id)
+            .is_some();
+        let replaced = self.remote_binding_remove(name).is_some() || replaced;
+        let replaced = self.remove_group(name) || replaced;
+        self.leases().revoke(name);
+        self.bound
+            .lock()
+            .expect("Registry: unable to get bound lock")
+            .insert(
+                name.to_string(),
+                Bound {
+                    at: SystemTime::now(),
+                    addr: None,
+                },
+            );
+        debug!(id, name, "registered");
+        self.notify_bound(name, replaced);
         (arc_object, id)
     }
 
-    pub fn unbind(&self) {
-        todo!()
-        // switch variable to unbind
+    /// Binds `name` to an object served by another process, lookups return `remote` as is.
+    /// Replaces whatever was bound to `name`. Fails when the replicas of the registry do not
+    /// take the bind, see `use_replicas`.
+    #[cfg_attr(feature = "tracing", instrument)]
+    pub fn bind_remote(&self, name: &str, remote: RemoteRef) -> RMIResult<()> {
+        self.replicated(Change::Bind {
+            name: name.to_string(),
+            remote,
+        })
     }
-}
-/// Creates and exports a Registry instance on the local host that accepts requests on the specified port.
-///
-/// Parameters:
-/// port: `u16` the port on which the registry accepts requests
-///
-/// Returns:
-/// the registry
-///
-/// ```
-/// // create registry
-/// use rrmi::{get_registry,create_registry};
-/// let port: u16 = 1099;
-/// let local = "localhost";
-/// let reg = create_registry(port);
-/// // check that is has the right port
-/// assert_eq!(port,reg.port);
-///
-/// ```
-#[cfg_attr(feature = "tracing", instrument)]
-pub fn create_registry(port: u16) -> Arc<Registry> {
-    let reg = Arc::new(Registry::new(port));
-    let port = reg.listen().expect("Registry: unable to start listening");
-    eprintln!("RMI Registry listening on {}", port);
-    reg
-}
-
-/// Returns a reference to the remote object Registry on the specified host and port
-///
-/// Parameters:
-///
-/// host: `&str` of host for the remote registry
-///
-/// port: `u16` port on which the registry accepts requests
-///
-/// Returns:
-///
-/// reference (a stub) to the remote object registry
-/// ```
-/// // create registry
-/// use rrmi::{get_registry,create_registry};
-/// let port: u16 = 1100;
-/// let local = "localhost";
-/// let reg = create_registry(port);
-/// let ip = reg.get_ip().expect("should have address");
-/// // access the registry
-/// let reg_local = get_registry(local,1099);
-/// let reg_remote = get_registry(&ip.to_string(),1099);
-///
-/// ```
-//TODO: should i check connection and throw error?
-#[cfg_attr(feature = "tracing", instrument)]
-pub fn get_registry(host: &str, port: u16) -> RegistryStub {
-    let addr = get_addr(&host, port);
-    let remote = RemoteRef::new(addr, 0);
-    RegistryStub { remote }
-}
-
-use ::rrmi::RMIResult;
-use ::rrmi::transport::TcpListener;
 
-impl Registry {
+    /// Removes `name`, whether it was bound to a local object, by reference or to a group.
     #[cfg_attr(feature = "tracing", instrument)]
-    pub fn listen(self: &Arc<Self>) -> RMIResult<u16> {
-        // takes an arc reference to self Arc<Registry>
-        // clone and move to a listening thread
-        let listener = TcpListener::bind(("0.0.0.0", self.port)).map_err(|e| {
-            eprintln!("Registry Error: cannot bind port {e}");
-            RMIError::TransportError(e.to_string())
-        })?;
-        let self_clone = Arc::clone(&self);
-        let addr = listener
-            .local_addr()
-            .expect("Registry: does not have an address");
-        let _handle_registry = std::thread::Builder::new()
-            .name("Registry".to_string())
-            .spawn(move || {
-                for stream in listener.incoming() {
-                    match stream {
-                        Ok(mut stream) => {
-                            eprintln!("Registry received connection from {:?}", stream.peer_addr());
-                            if let Err(e) = self_clone.run(&mut stream) {
-                                eprintln!("Error: {e} when handling connection");
-                            }
-                        }
-                        Err(e) => eprintln!("Transport error: {e}"),
-                    };
-                }
-            })
-            .expect("Registry thread failed");
-        Ok(addr.port())
+    pub fn unbind(&self, name: &str) -> RMIResult<()> {
+        if self.get_id(name).is_ok() {
+            return self.remove(name);
+        }
+        if self.remove_group(name) {
+            self.notify(WatchEvent::Unbound {
+                name: name.to_string(),
+            });
+            return Ok(());
+        }
+        self.replicated(Change::Unbind {
+            name: name.to_string(),
+        })
     }
-}
 
-// Remote object code
-// this could also be generated from the macro
-use ::rrmi::stub::{Deserialize, Serialize, Stub};
-use ::rrmi::transport::{TcpClient, TcpStream, Transport};
-use rrmi::{marshal, receive_data, send_data, unmarshal};
+    fn groups(&self) -> std::sync::MutexGuard<'_, Groups> {
+        self.groups
+            .lock()
+            .expect("Registry: unable to get groups lock")
+    }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub enum RegistryRequest {
-    Lookup { name: String },
-    List,
-}
+    /// Binds `object` under `name` next to the objects already there, making them a group:
+    /// lookups return the members in turn and `RegistryStub::lookup_group` all of them.
+    /// Returns the object and its id, for `leave`.
+    ///
+    /// Watchers see `WatchEvent::Bound` for the first member, `WatchEvent::Rebound` when
+    /// the members change and `WatchEvent::Unbound` when the last one leaves. Fails with
+    /// `RMIError::BadArguments` when `name` is bound to a single object.
+    pub fn join<Obj: RemoteObject + 'static>(
+        &self,
+        name: &str,
+        object: Obj,
+    ) -> RMIResult<(Arc<Obj>, RMI_ID)> {
+        self.check_group(name)?;
+        let object_ref = Arc::new(object);
+        let arc_object = Arc::clone(&object_ref);
+        let skeleton = Arc::new(Skeleton::with_hooks(object_ref, self.hooks.clone()));
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.objects
+            .lock()
+            .expect("Registry: unable to get objects lock")
+            .insert(id, skeleton);
+        self.add_member(name, Member { id, remote: None });
+        Ok((arc_object, id))
+    }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub enum RegistryResponse {
-    Lookup(RMIResult<RemoteRef>),
-    List(RMIResult<Vec<String>>),
-}
+    /// Like `join` for an object served by another process. Group members are neither
+    /// journaled nor replicated.
+    #[cfg_attr(feature = "tracing", instrument)]
+    pub fn join_remote(&self, name: &str, remote: RemoteRef) -> RMIResult<RMI_ID> {
+        self.check_group(name)?;
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.add_member(
+            name,
+            Member {
+                id,
+                remote: Some(remote),
+            },
+        );
+        Ok(id)
+    }
 
-impl RemoteObject for Registry {
-    fn run(&self, stream: &mut TcpStream) -> RMIResult<()> {
-        self.handle_connection(stream)
+    /// Takes member `id` out of group `name`.
+    #[cfg_attr(feature = "tracing", instrument)]
+    pub fn leave(&self, name: &str, id: RMI_ID) -> RMIResult<()> {
+        let (member, last) = self.groups().leave(name, id)?;
+        debug!(id, name, "left group");
+        self.drop_member(member);
+        let name = name.to_string();
+        if last {
+            self.bound
+                .lock()
+                .expect("Registry: unable to get bound lock")
+                .remove(&name);
+            self.notify(WatchEvent::Unbound { name });
+        } else {
+            self.notify(WatchEvent::Rebound { name });
+        }
+        Ok(())
     }
-    fn name(&self) -> &'static str {
-        "Registry"
+
+    fn check_group(&self, name: &str) -> RMIResult<()> {
+        match self.get_id(name).is_ok() || self.remote_binding(name).is_some() {
+            true => Err(RMIError::BadArguments(format!(
+                "{name} is bound to a single object"
+            ))),
+            false => Ok(()),
+        }
     }
-}
-#[allow(dead_code)]
-impl Registry {
-    #[cfg_attr(feature = "tracing", instrument)]
-    fn handle_connection(&self, stream: &mut TcpStream) -> RMIResult<()> {
-        stream.set_nodelay(true).expect("Could not set NO_DELAY");
-        let request_bytes = receive_data(stream);
-        let request: RegistryRequest = unmarshal(&request_bytes)?;
-        let response: RegistryResponse = self.handle_request(request);
-        let response_bytes = marshal(&response)?;
-        send_data(response_bytes, stream)
+
+    fn add_member(&self, name: &str, member: Member) {
+        let id = member.id;
+        let first = self.groups().join(name, member);
+        if first {
+            self.bound
+                .lock()
+                .expect("Registry: unable to get bound lock")
+                .insert(
+                    name.to_string(),
+                    Bound {
+                        at: SystemTime::now(),
+                        addr: None,
+                    },
+                );
+        }
+        debug!(id, name, "joined group");
+        self.notify_bound(name, !first);
     }
-    #[cfg_attr(feature = "tracing", instrument)]
-    fn handle_request(&self, req: RegistryRequest) -> RegistryResponse {
-        match req {
-            RegistryRequest::Lookup { name } => RegistryResponse::Lookup(self.lookup(&name)),
-            RegistryRequest::List => RegistryResponse::List(self.list()),
+
+    /// Removes group `name` and its members, returns whether there was one.
+    fn remove_group(&self, name: &str) -> bool {
+        let Some(members) = self.groups().remove(name) else {
+            return false;
+        };
+        for member in members {
+            self.drop_member(member);
         }
+        self.bound
+            .lock()
+            .expect("Registry: unable to get bound lock")
+            .remove(name);
+        true
     }
-}
 
-#[derive(Debug)]
-pub struct RegistryStub {
-    remote: RemoteRef,
-}
-impl RegistryStub {
-    pub fn new(remote: RemoteRef) -> Self {
-        RegistryStub { remote }
+    fn drop_member(&self, member: Member) {
+        if member.remote.is_some() {
+            return;
+        }
+        let skeleton = self
+            .objects
+            .lock()
+            .expect("Registry: unable to get objects lock")
+            .remove(&member.id);
+        // dropping the skeleton waits for its calls, which may need the registry
+        drop(skeleton);
     }
 
-    #[cfg_attr(feature = "tracing", instrument)]
-    pub fn lookup(&self, name: &str) -> RMIResult<Stub> {
-        let transport = TcpClient::new(self.remote.addr);
-        let req = RegistryRequest::Lookup {
-            name: name.to_string(),
+    /// Makes `change` here, or through the leader when the registry is replicated.
+    fn replicated(&self, change: Change) -> RMIResult<()> {
+        let Some(replicas) = self.replicas() else {
+            return self.apply(&change);
         };
-        let resp: RegistryResponse = transport.send(req)?;
-        match resp {
-            RegistryResponse::Lookup(Ok(res)) => Ok(Stub::new(res)),
-            _ => Err(RMIError::TransportError("Wrong response".to_string())),
+        for (rank, leader) in replicas.before_me() {
+            match leader.propose(change.clone(), replicas.len()) {
+                Ok((from, entries)) => {
+                    let sent = from + entries.len() as u64;
+                    let have = replicas.receive(from, entries, |change| self.apply(change));
+                    if have < sent {
+                        // this replica diverged, the whole log shows where
+                        match leader.sync(have) {
+                            Ok((from, entries)) => {
+                                replicas.receive(from, entries, |change| self.apply(change));
+                            }
+                            Err(e) => debug!(rank, error = %e, "replica did not catch up"),
+                        }
+                    }
+                    return Ok(());
+                }
+                Err(e) if unreachable(&e) => debug!(rank, error = %e, "replica is down"),
+                Err(e) => return Err(e),
+            }
         }
+        replicas.lead(change, |change| self.apply(change))
     }
-    #[cfg_attr(feature = "tracing", instrument)]
-    pub fn list(&self) -> RMIResult<Vec<String>> {
-        let transport = TcpClient::new(self.remote.addr);
+
+    /// Makes `change` here only.
+    fn apply(&self, change: &Change) -> RMIResult<()> {
+        match change {
+            Change::Bind { name, remote } => {
+                self.leases().revoke(name);
+                self.bind_remote_here(name, remote.clone());
+                Ok(())
+            }
+            Change::Unbind { name } => match self.remote_binding_remove(name) {
+                Some(_) => {
+                    self.leases().revoke(name);
+                    self.bound
+                        .lock()
+                        .expect("Registry: unable to get bound lock")
+                        .remove(name);
+                    self.notify(WatchEvent::Unbound { name: name.clone() });
+                    Ok(())
+                }
+                None => self.remove(name),
+            },
+            Change::Expire { name } => {
+                self.remote_binding_remove(name)
+                    .ok_or_else(|| RMIError::NameNotFound(name.clone()))?;
+                self.bound
+                    .lock()
+                    .expect("Registry: unable to get bound lock")
+                    .remove(name);
+                self.notify(WatchEvent::Expired { name: name.clone() });
+                Ok(())
+            }
+        }
+    }
+
+    fn leases(&self) -> std::sync::MutexGuard<'_, Leases> {
+        self.leases
+            .lock()
+            .expect("Registry: unable to get leases lock")
+    }
+
+    /// Like `bind_remote`, but the name is unbound unless `renew` is called within `ttl`,
+    /// again and again, so names of processes that died do not stay around. Watchers are
+    /// told with `WatchEvent::Expired`.
+    ///
+    /// The lease is kept by this registry only: after a restart, or on the other replicas,
+    /// the name is bound without one until the binder renews.
+    #[cfg_attr(feature = "tracing", instrument)]
+    pub fn bind_remote_with_lease(
+        &self,
+        name: &str,
+        remote: RemoteRef,
+        ttl: Duration,
+    ) -> RMIResult<()> {
+        check_ttl(ttl)?;
+        self.reap()?;
+        self.replicated(Change::Bind {
+            name: name.to_string(),
+            remote,
+        })?;
+        self.leases().grant(name, ttl);
+        Ok(())
+    }
+
+    /// Extends the lease of `name` to `ttl` from now. Fails with `RMIError::NameNotFound`
+    /// when the name holds no lease, it ran out or was bound without one.
+    #[cfg_attr(feature = "tracing", instrument)]
+    pub fn renew(&self, name: &str, ttl: Duration) -> RMIResult<()> {
+        check_ttl(ttl)?;
+        self.leases().renew(name, ttl)
+    }
+
+    /// Starts the thread expiring leases, unless it runs already.
+    fn reap(&self) -> RMIResult<()> {
+        let Some(registry) = self.this.get().cloned() else {
+            return Err(RMIError::ServerError(
+                "leases need a listening registry".to_string(),
+            ));
+        };
+        if self.reaping.swap(true, Ordering::SeqCst) {
+            return Ok(());
+        }
+        let serving = self.serving.clone();
+        self.serving.spawn("Leases".to_string(), move || {
+            loop {
+                std::thread::sleep(LEASE_POLL);
+                if serving.is_stopping() {
+                    break;
+                }
+                let Some(registry) = registry.upgrade() else {
+                    break;
+                };
+                registry.expire_leases();
+            }
+        })
+    }
+
+    fn expire_leases(&self) {
+        let expired = self.leases().expired(Instant::now());
+        for name in expired {
+            info!(name, "lease expired");
+            if let Err(e) = self.replicated(Change::Expire { name: name.clone() }) {
+                warn!(name, error = %e, "could not expire lease");
+            }
+        }
+    }
+
+    fn bind_remote_here(&self, name: &str, remote: RemoteRef) {
+        let replaced = self.remove_object(name).is_ok();
+        let replaced = self.remove_group(name) || replaced;
+        debug!(name, addr = %remote.addr, "registered remote");
+        self.bound
+            .lock()
+            .expect("Registry: unable to get bound lock")
+            .insert(
+                name.to_string(),
+                Bound {
+                    at: SystemTime::now(),
+                    addr: Some(remote.addr.clone()),
+                },
+            );
+        self.record(|journal| journal.bind(name, &remote));
+        let replaced = self
+            .remotes
+            .lock()
+            .expect("Registry: unable to get remotes lock")
+            .insert(name.to_string(), remote)
+            .is_some()
+            || replaced;
+        self.notify_bound(name, replaced);
+    }
+
+    fn notify_bound(&self, name: &str, replaced: bool) {
+        let name = name.to_string();
+        self.notify(match replaced {
+            true => WatchEvent::Rebound { name },
+            false => WatchEvent::Bound { name },
+        });
+    }
+
+    /// Tells the watchers of `event`'s name, forgetting the ones that went away.
+    fn notify(&self, event: WatchEvent) {
+        self.watchers
+            .lock()
+            .expect("Registry: unable to get watchers lock")
+            .retain(|watching| {
+                under(event.name(), &watching.prefix).is_none()
+                    || watching.events.send(event.clone()).is_ok()
+            });
+    }
+
+    /// Calls `on_event` with the changes of the names under `prefix`, in order and on a
+    /// thread of its own, until `unwatch` or until it fails. Returns the id of the watch,
+    /// random so that other clients cannot guess it to unwatch.
+    ///
+    /// Other processes watch with `RegistryStub::watch`.
+    #[cfg_attr(feature = "tracing", instrument(skip(on_event)))]
+    pub fn watch(
+        &self,
+        prefix: &str,
+        mut on_event: impl FnMut(WatchEvent) -> RMIResult<()> + Send + 'static,
+    ) -> RMIResult<u64> {
+        let id = getrandom::u64().map_err(|e| RMIError::IoError(e.to_string()))?;
+        let (events, received) = channel();
+        self.serving.spawn(format!("Watch{id}"), move || {
+            for event in received {
+                if let Err(e) = on_event(event) {
+                    warn!(id, error = %e, "watcher failed, not watching anymore");
+                    break;
+                }
+            }
+        })?;
+        let prefix = prefix.trim_end_matches('/').to_string();
+        debug!(id, prefix, "watching");
+        self.watchers
+            .lock()
+            .expect("Registry: unable to get watchers lock")
+            .push(Watching { id, prefix, events });
+        Ok(id)
+    }
+
+    /// Stops the watch `id`, events already sent are still delivered.
+    #[cfg_attr(feature = "tracing", instrument)]
+    pub fn unwatch(&self, id: u64) -> RMIResult<()> {
+        let mut watchers = self
+            .watchers
+            .lock()
+            .expect("Registry: unable to get watchers lock");
+        let before = watchers.len();
+        watchers.retain(|watching| watching.id != id);
+        match watchers.len() < before {
+            true => Ok(()),
+            false => Err(RMIError::BadArguments(format!("no watch {id}"))),
+        }
+    }
+
+    /// Watches for a client that exported a `Watcher` at `watcher`.
+    fn watch_for(&self, prefix: &str, watcher: RemoteRef) -> RMIResult<u64> {
+        let psk = self.psk.clone();
+        let mut connected = None;
+        self.watch(prefix, move |event| {
+            // on the watch's thread, the one serving requests does not wait on the client
+            let stub = match &mut connected {
+                Some(stub) => stub,
+                None => connected.insert(DynamicStub::new(
+                    Stub::with_psk(watcher.clone(), psk.clone()),
+                    watcher_info(),
+                )?),
+            };
+            let event = serde_cbor::value::to_value(event)
+                .map_err(|e| RMIError::SerializationError(e.to_string()))?;
+            stub.call("notify", vec![event]).map(drop)
+        })
+    }
+
+    /// Remembers where a lookup of `name` sent the client, for `describe`.
+    fn advertise(&self, name: &str, remote: RemoteRef) -> RemoteRef {
+        if let Some(bound) = self
+            .bound
+            .lock()
+            .expect("Registry: unable to get bound lock")
+            .get_mut(name)
+        {
+            bound.addr = Some(remote.addr.clone());
+        }
+        remote
+    }
+
+    fn remote_binding(&self, name: &str) -> Option<RemoteRef> {
+        self.remotes
+            .lock()
+            .expect("Registry: unable to get remotes lock")
+            .get(name)
+            .cloned()
+    }
+
+    fn remote_binding_remove(&self, name: &str) -> Option<RemoteRef> {
+        let removed = self
+            .remotes
+            .lock()
+            .expect("Registry: unable to get remotes lock")
+            .remove(name);
+        if removed.is_some() {
+            self.record(|journal| journal.unbind(name));
+        }
+        removed
+    }
+
+    /// Keeps the remote binds in the journal at `path`, so they survive a restart of the
+    /// process. Binds recorded there by an earlier run are restored first, as long as their
+    /// object still answers a connection and the name was not bound in this run already.
+    /// The others are forgotten. Returns the names restored.
+    ///
+    /// The journal is then rewritten with the remote binds of this run. One that did not
+    /// replay to the end, past a corrupt entry, is first moved to the same path with `.bak`
+    /// added.
+    ///
+    /// Objects bound with `bind` live in this process and cannot be restored, they are not
+    /// recorded.
+    #[cfg_attr(feature = "tracing", instrument)]
+    pub fn use_journal(&self, path: impl AsRef<Path> + Debug) -> RMIResult<Vec<String>> {
+        let path = path.as_ref();
+        let (recorded, complete) = Journal::replay(path)?;
+        let checked: Vec<(String, RemoteRef, RMIResult<()>)> = std::thread::scope(|scope| {
+            let checks: Vec<_> = recorded
+                .into_iter()
+                .filter(|(name, _)| {
+                    self.get_id(name).is_err() && self.remote_binding(name).is_none()
+                })
+                .map(|(name, remote)| {
+                    scope.spawn(move || {
+                        let reachable = reachable(&remote, PING_TIMEOUT);
+                        (name, remote, reachable)
+                    })
+                })
+                .collect();
+            checks
+                .into_iter()
+                .filter_map(|check| check.join().ok())
+                .collect()
+        });
+        let mut restored = vec![];
+        for (name, remote, reachable) in checked {
+            match reachable {
+                Ok(()) => {
+                    debug!(name, addr = %remote.addr, "restored from journal");
+                    self.bind_remote(&name, remote)?;
+                    restored.push(name);
+                }
+                Err(e) => {
+                    warn!(name, addr = %remote.addr, error = %e, "not restoring, unreachable")
+                }
+            }
+        }
+        let bindings = self
+            .remotes
+            .lock()
+            .expect("Registry: unable to get remotes lock")
+            .iter()
+            .map(|(name, remote)| (name.clone(), remote.clone()))
+            .collect();
+        // compacting drops what could not be read, keep it around for a human to look at
+        if !complete {
+            let mut backup = path.as_os_str().to_owned();
+            backup.push(".bak");
+            std::fs::rename(path, &backup)
+                .map_err(|e| RMIError::IoError(format!("{}: {e}", path.display())))?;
+            warn!(journal = %path.display(), backup = ?backup, "journal did not replay to the end");
+        }
+        *self
+            .journal
+            .lock()
+            .expect("Registry: unable to get journal lock") =
+            Some(Journal::create(path, &bindings)?);
+        info!(journal = %path.display(), restored = restored.len(), "recording remote binds");
+        Ok(restored)
+    }
+
+    /// Writes a change of the remote binds to the journal, if there is one.
+    fn record(&self, change: impl FnOnce(&mut Journal) -> RMIResult<()>) {
+        let mut journal = self
+            .journal
+            .lock()
+            .expect("Registry: unable to get journal lock");
+        if let Some(journal) = journal.as_mut()
+            && let Err(e) = change(journal)
+        {
+            error!(error = %e, "could not write to journal");
+        }
+    }
+
+    /// Keeps the remote binds of this registry in step with the other registries in
+    /// `replicas`, this one being `replicas[me]`, so any of them can answer lookups when
+    /// another goes down. Every replica has to be given the same list, in the same order.
+    ///
+    /// Binds and unbinds go to the leader, the first replica in the list that answers, which
+    /// passes them on to the others. The registry first catches up with the replicas that
+    /// are already running. Objects bound with `bind` live in this process and are not
+    /// replicated.
+    ///
+    /// Replicas on other hosts need `REGISTRY_WRITE_ROLE` for the `replicate` method of
+    /// `Registry`, see `set_authorizer`.
+    #[cfg_attr(feature = "tracing", instrument)]
+    pub fn use_replicas(&self, replicas: Vec<RegistryStub>, me: usize) -> RMIResult<()> {
+        if me >= replicas.len() {
+            return Err(RMIError::BadArguments(format!(
+                "replica {me} of {}",
+                replicas.len()
+            )));
+        }
+        // the first replica by rank that answers leads, its log is the one the others take
+        let mut log = vec![];
+        for (rank, replica) in replicas.iter().enumerate() {
+            if rank == me {
+                continue;
+            }
+            match replica.sync(0) {
+                Ok((_, entries)) => {
+                    log = entries;
+                    break;
+                }
+                Err(e) => debug!(rank, error = %e, "not catching up with replica"),
+            }
+        }
+        for entry in &log {
+            if let Err(e) = self.apply(&entry.change) {
+                debug!(change = ?entry.change, error = %e, "replicated change did not apply");
+            }
+        }
+        info!(
+            me,
+            replicas = replicas.len(),
+            changes = log.len(),
+            "replicating"
+        );
+        *self
+            .replicas
+            .lock()
+            .expect("Registry: unable to get replicas lock") =
+            Some(Arc::new(Replicas::new(replicas, me, log)));
+        Ok(())
+    }
+
+    fn replicas(&self) -> Option<Arc<Replicas>> {
+        self.replicas
+            .lock()
+            .expect("Registry: unable to get replicas lock")
+            .clone()
+    }
+
+    /// The replicas, failing when there are none or, with `leading`, when this is not the
+    /// leader.
+    fn replicas_for(&self, leading: bool) -> RMIResult<Arc<Replicas>> {
+        let replicas = self
+            .replicas()
+            .ok_or_else(|| RMIError::ServerError("registry is not replicated".to_string()))?;
+        if leading && !replicas.leads() {
+            return Err(RMIError::ServerError(
+                "registry is not the leader of its replicas".to_string(),
+            ));
+        }
+        Ok(replicas)
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Registry {
+        Registry::new(1099)
+    }
+}
+/// Creates and exports a Registry instance on the local host that accepts requests on the specified port.
+///
+/// Parameters:
+/// port: `u16` the port on which the registry accepts requests
+///
+/// Returns:
+/// the registry
+///
+/// ```
+/// // create registry
+/// use rrmi::{get_registry,create_registry};
+/// let port: u16 = 1099;
+/// let local = "localhost";
+/// let reg = create_registry(port);
+/// // check that is has the right port
+/// assert_eq!(port,reg.port);
+///
+/// ```
+#[cfg_attr(feature = "tracing", instrument)]
+pub fn create_registry(port: u16) -> Arc<Registry> {
+    try_create_registry(port).expect("Registry: unable to start listening")
+}
+
+/// Like `create_registry`, but fails instead of panicking when the port cannot be listened on.
+#[cfg_attr(feature = "tracing", instrument)]
+pub fn try_create_registry(port: u16) -> RMIResult<Arc<Registry>> {
+    let reg = Arc::new(Registry::new(port));
+    let port = reg.listen()?;
+    reg.serve_memory(&port_namespace(port))?;
+    LOCAL_REGISTRIES
+        .lock()
+        .expect("Registry: unable to get local registries lock")
+        .insert(port, Arc::downgrade(&reg));
+    info!(port, "registry listening");
+    Ok(reg)
+}
+
+/// Creates a Registry whose clients, and the clients of the objects bound to it, have to
+/// connect over TLS with `tls`. Clients reach it with `get_registry_tls`.
+///
+/// Unix sockets, shared memory and in-process shortcuts are off, they would bypass TLS.
+#[cfg(feature = "tls")]
+#[cfg_attr(feature = "tracing", instrument)]
+pub fn create_registry_tls(port: u16, tls: TlsServerConfig) -> Arc<Registry> {
+    try_create_registry_tls(port, tls).expect("Registry: unable to start listening")
+}
+
+/// Like `create_registry_tls`, but fails instead of panicking when the port cannot be
+/// listened on.
+#[cfg(feature = "tls")]
+#[cfg_attr(feature = "tracing", instrument)]
+pub fn try_create_registry_tls(port: u16, tls: TlsServerConfig) -> RMIResult<Arc<Registry>> {
+    let mut reg = Registry::sockets_only(port);
+    reg.tls = Some(tls);
+    let reg = Arc::new(reg);
+    let port = reg.listen()?;
+    info!(port, "registry listening with TLS");
+    Ok(reg)
+}
+
+/// Creates a Registry that only answers clients proving they know `key`, and whose objects
+/// only serve such clients. Clients reach it with `get_registry_psk`.
+///
+/// Connections that fail the handshake are dropped before anything they sent is decoded.
+/// Unix sockets, shared memory and in-process shortcuts are off, they would skip the handshake.
+///
+/// ```
+/// use rrmi::{create_registry_psk,get_registry_psk,PreSharedKey,RMIError};
+/// let reg = create_registry_psk(1102, PreSharedKey::new("open sesame"));
+/// let reg_stub = get_registry_psk("localhost", 1102, PreSharedKey::new("open sesame"));
+/// assert!(reg_stub.is_ok());
+/// let wrong = get_registry_psk("localhost", 1102, PreSharedKey::new("open barley"));
+/// assert!(matches!(wrong, Err(RMIError::Unauthorized(_))));
+/// ```
+#[cfg_attr(feature = "tracing", instrument)]
+pub fn create_registry_psk(port: u16, key: PreSharedKey) -> Arc<Registry> {
+    try_create_registry_psk(port, key).expect("Registry: unable to start listening")
+}
+
+/// Like `create_registry_psk`, but fails instead of panicking when the port cannot be
+/// listened on.
+#[cfg_attr(feature = "tracing", instrument)]
+pub fn try_create_registry_psk(port: u16, key: PreSharedKey) -> RMIResult<Arc<Registry>> {
+    let mut reg = Registry::sockets_only(port);
+    reg.psk = Some(key);
+    let reg = Arc::new(reg);
+    let port = reg.listen()?;
+    info!(port, "registry listening with pre-shared key");
+    Ok(reg)
+}
+
+/// Creates a Registry that is only reachable from this process, under `namespace`.
+///
+/// Stubs looked up through it call their skeletons over in-process channels:
+/// requests and responses are still marshalled but no socket is ever opened.
+///
+/// Fails with `RMIError::BadArguments` for an empty namespace or one with a `:`, and with
+/// `RMIError::TransportError` when a registry of this process has it already.
+///
+/// ```
+/// use rrmi::{create_registry_in,get_registry_in};
+/// use rrmi::remote::MockRemoteObject;
+/// let reg = create_registry_in("docs").expect("docs is free");
+/// reg.bind("mock", MockRemoteObject::silent());
+/// let reg_stub = get_registry_in("docs").expect("registry is in this process");
+/// assert_eq!(reg_stub.list(), Ok(vec!["mock".to_string()]));
+/// ```
+#[cfg_attr(feature = "tracing", instrument)]
+pub fn create_registry_in(namespace: &str) -> RMIResult<Arc<Registry>> {
+    let reg = Arc::new(Registry::new(0));
+    reg.listen_memory(namespace)?;
+    info!(namespace, "registry listening in process");
+    Ok(reg)
+}
+
+/// Returns a reference to the remote object Registry on the specified host and port
+///
+/// Parameters:
+///
+/// host: `&str` of host for the remote registry
+///
+/// port: `u16` port on which the registry accepts requests
+///
+/// Returns:
+///
+/// reference (a stub) to the remote object registry
+///
+/// Every address `host` resolves to is tried until one answers a registry ping.
+/// Fails with `RMIError::NameResolution` when `host` does not resolve and with
+/// `RMIError::RegistryUnreachable` when none of its addresses has a registry on `port`.
+/// ```
+/// // create registry
+/// use rrmi::{get_registry,create_registry,RMIError};
+/// let port: u16 = 1100;
+/// let local = "localhost";
+/// let reg = create_registry(port);
+/// let ip = reg.get_ip().expect("should have address");
+/// // access the registry
+/// let reg_local = get_registry(local,port).expect("registry is listening");
+/// let reg_remote = get_registry(&ip.to_string(),port).expect("registry is listening");
+/// // nothing listens here
+/// let missing = get_registry(local,1101);
+/// assert!(matches!(missing, Err(RMIError::RegistryUnreachable(_))));
+///
+/// ```
+#[cfg_attr(feature = "tracing", instrument)]
+pub fn get_registry(host: &str, port: u16) -> RMIResult<RegistryStub> {
+    let addrs = resolve_addrs(host, port)?;
+    if let Some(stub) = colocated_registry(&addrs, port) {
+        return Ok(stub);
+    }
+    let ping = |addr| RegistryStub::new(RemoteRef::new(addr, 0)).ping_timeout(PING_TIMEOUT);
+    match happy_eyeballs(&addrs, ping) {
+        Ok((addr, ())) => {
+            debug!(%addr, "registry answered");
+            Ok(RegistryStub::new(RemoteRef::new(addr, 0)))
+        }
+        Err(errors) => {
+            for (addr, e) in errors {
+                debug!(%addr, error = %e, "registry not reachable");
+            }
+            Err(RMIError::RegistryUnreachable(format!("{host}:{port}")))
+        }
+    }
+}
+
+/// Like `get_registry` for a registry replicated with `Registry::use_replicas`, given where
+/// each replica listens. The stub talks to the first replica that answers and fails over to
+/// the others when it goes down.
+///
+/// ```
+/// use rrmi::{create_registry, get_registry_replicas};
+/// let reg = create_registry(1105);
+/// // the second replica is not up
+/// let stub = get_registry_replicas(&[("localhost", 1106), ("localhost", 1105)])
+///     .expect("one replica answers");
+/// assert_eq!(stub.list(), Ok(vec![]));
+/// ```
+#[cfg_attr(feature = "tracing", instrument)]
+pub fn get_registry_replicas(replicas: &[(&str, u16)]) -> RMIResult<RegistryStub> {
+    let mut remotes = vec![];
+    let mut answered = None;
+    for (index, &(host, port)) in replicas.iter().enumerate() {
+        match get_registry(host, port) {
+            Ok(stub) => {
+                answered.get_or_insert(index);
+                remotes.push(stub.remote);
+            }
+            Err(RMIError::RegistryUnreachable(_)) => {
+                let addr = resolve_addrs(host, port)?[0];
+                remotes.push(RemoteRef::new(addr, 0));
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    let Some(answered) = answered else {
+        let replicas: Vec<String> = replicas.iter().map(|(h, p)| format!("{h}:{p}")).collect();
+        return Err(RMIError::RegistryUnreachable(replicas.join(", ")));
+    };
+    let mut remotes = remotes.into_iter();
+    let first = remotes.next().expect("a replica answered");
+    let stub = RegistryStub::new(first).with_replicas(remotes.collect());
+    stub.current.store(answered, Ordering::Relaxed);
+    Ok(stub)
+}
+
+/// Like `get_registry` for registries created with `create_registry_tls`.
+///
+/// Server certificates are checked against `host` unless `tls` names another server.
+#[cfg(feature = "tls")]
+#[cfg_attr(feature = "tracing", instrument)]
+pub fn get_registry_tls(host: &str, port: u16, tls: TlsClientConfig) -> RMIResult<RegistryStub> {
+    let addrs = resolve_addrs(host, port)?;
+    let tls = tls.server_name_or(host);
+    let stub = |addr, tls| RegistryStub::with_tls(RemoteRef::new(Endpoint::Tls(addr), 0), tls);
+    let ping_tls = tls.clone();
+    let ping = move |addr| stub(addr, ping_tls.clone()).ping_timeout(PING_TIMEOUT);
+    match happy_eyeballs(&addrs, ping) {
+        Ok((addr, ())) => {
+            debug!(%addr, "registry answered over TLS");
+            Ok(stub(addr, tls))
+        }
+        Err(errors) => {
+            for (addr, e) in errors {
+                debug!(%addr, error = %e, "registry not reachable over TLS");
+            }
+            Err(RMIError::RegistryUnreachable(format!("{host}:{port}")))
+        }
+    }
+}
+
+/// Like `get_registry` for registries created with `create_registry_psk`.
+///
+/// Fails with `RMIError::Unauthorized` when the registry answers but does not accept `key`.
+#[cfg_attr(feature = "tracing", instrument)]
+pub fn get_registry_psk(host: &str, port: u16, key: PreSharedKey) -> RMIResult<RegistryStub> {
+    let addrs = resolve_addrs(host, port)?;
+    let stub = |addr, key| RegistryStub::with_psk(RemoteRef::new(addr, 0), key);
+    let ping_key = key.clone();
+    let ping = move |addr| stub(addr, ping_key.clone()).ping_timeout(PING_TIMEOUT);
+    match happy_eyeballs(&addrs, ping) {
+        Ok((addr, ())) => {
+            debug!(%addr, "registry accepted our key");
+            Ok(stub(addr, key))
+        }
+        Err(errors) => {
+            let mut unauthorized = None;
+            for (addr, e) in errors {
+                debug!(%addr, error = %e, "registry not reachable");
+                if let RMIError::Unauthorized(_) = e {
+                    unauthorized = Some(e);
+                }
+            }
+            Err(unauthorized
+                .unwrap_or_else(|| RMIError::RegistryUnreachable(format!("{host}:{port}"))))
+        }
+    }
+}
+
+/// Returns a reference to the Registry created with `create_registry_in(namespace)` in this process.
+#[cfg_attr(feature = "tracing", instrument)]
+pub fn get_registry_in(namespace: &str) -> RMIResult<RegistryStub> {
+    check_namespace(namespace)?;
+    let remote = RemoteRef::new(Endpoint::Memory(registry_namespace(namespace)), 0);
+    let stub = RegistryStub::new(remote);
+    stub.ping()
+        .map_err(|_| RMIError::RegistryUnreachable(namespace.to_string()))?;
+    Ok(stub)
+}
+
+/// The registry on `port` if this process created it and `addrs` point at this host.
+fn colocated_registry(addrs: &[SocketAddr], port: u16) -> Option<RegistryStub> {
+    let registry = LOCAL_REGISTRIES.lock().ok()?.get(&port)?.upgrade()?;
+    if !registry.in_process.load(Ordering::Relaxed) {
+        return None;
+    }
+    let local_ips = get_local_ips().unwrap_or_default();
+    let is_this_host = |addr: &SocketAddr| {
+        let ip = addr.ip().to_canonical();
+        is_local(&ip) || local_ips.contains(&ip)
+    };
+    if !addrs.iter().any(is_this_host) {
+        return None;
+    }
+    let namespace = registry_namespace(&port_namespace(port));
+    let stub = RegistryStub::new(RemoteRef::new(Endpoint::Memory(namespace), 0));
+    stub.ping().ok()?;
+    debug!(port, "registry is in this process, skipping sockets");
+    Some(stub)
+}
+
+use ::rrmi::RMIResult;
+
+impl Registry {
+    #[cfg_attr(feature = "tracing", instrument)]
+    pub fn listen(self: &Arc<Self>) -> RMIResult<u16> {
+        // the thread only holds on to the registry while serving, dropping it shuts it down
+        let listener = get_tcp_listener(self.port).inspect_err(|e| {
+            error!(port = self.port, error = %e, "registry cannot bind port");
+        })?;
+        let _ = self.this.set(Arc::downgrade(self));
+        let registry = Arc::downgrade(self);
+        let serving = self.serving.clone();
+        let addr = listener
+            .local_addr()
+            .expect("Registry: does not have an address");
+        let port = addr.port();
+        let waker = self.serving.add_waker(move || wake_tcp(port));
+        self.serving
+            .spawn("Registry".to_string(), move || {
+                for stream in listener.incoming() {
+                    if serving.is_stopping() {
+                        break;
+                    }
+                    let Some(registry) = registry.upgrade() else {
+                        break;
+                    };
+                    match stream {
+                        Ok(stream) => {
+                            debug!(peer = ?stream.peer_addr(), "registry connection");
+                            // handshakes and replication wait on peers, each connection
+                            // gets a thread so they do not hold up the others
+                            let connection = move || registry.accept(stream);
+                            if let Err(e) = serving.spawn("Registry".to_string(), connection) {
+                                warn!(error = %e, "registry could not serve connection");
+                            }
+                        }
+                        Err(e) => warn!(error = %e, "registry could not accept"),
+                    };
+                }
+            })
+            .inspect_err(|_| self.serving.remove_waker(waker))?;
+        Ok(port)
+    }
+
+    /// Serves registry requests from this process under `namespace`, see `create_registry_in`.
+    #[cfg_attr(feature = "tracing", instrument)]
+    pub fn listen_memory(self: &Arc<Self>, namespace: &str) -> RMIResult<()> {
+        check_namespace(namespace)?;
+        self.serve_memory(namespace)
+    }
+
+    fn serve_memory(self: &Arc<Self>, namespace: &str) -> RMIResult<()> {
+        let name = registry_namespace(namespace);
+        let listener = MemoryListener::bind(&name)?;
+        let _ = self.this.set(Arc::downgrade(self));
+        let registry = Arc::downgrade(self);
+        let serving = self.serving.clone();
+        let waker = self
+            .serving
+            .add_waker(move || drop(MemoryStream::connect(&name)));
+        self.serving
+            .spawn(format!("Registry{namespace}"), move || {
+                while let Ok(mut stream) = listener.accept() {
+                    if serving.is_stopping() {
+                        break;
+                    }
+                    let Some(registry) = registry.upgrade() else {
+                        break;
+                    };
+                    registry.serve(&mut stream);
+                }
+            })
+            .inspect_err(|_| self.serving.remove_waker(waker))
+    }
+
+    /// Authenticates a connection accepted by `listen` and serves it.
+    fn accept(&self, mut stream: TcpStream) {
+        if let Err(e) = stream.set_nodelay(true) {
+            warn!(error = %e, "could not set NO_DELAY");
+        }
+        if let Some(key) = &self.psk
+            && let Err(e) = key.accept(&mut stream)
+        {
+            warn!(peer = ?stream.peer_addr(), error = %e, "client failed to authenticate");
+            return;
+        }
+        #[cfg(feature = "tls")]
+        if let Some(tls) = &self.tls {
+            match TlsStream::accept(stream, tls) {
+                Ok(mut stream) => self.serve(&mut stream),
+                Err(e) => warn!(error = %e, "TLS handshake failed"),
+            }
+            return;
+        }
+        self.serve(&mut stream);
+    }
+
+    /// Answers the one request a connection to the registry carries.
+    fn serve(&self, stream: &mut dyn Connection) {
+        let Some(connection) = self.serving.connect(self.name(), stream) else {
+            return;
+        };
+        if connection.begin() {
+            if let Err(e) = self.run(stream) {
+                warn!(error = %e, "registry connection failed");
+            }
+            connection.end();
+        }
+    }
+
+    /// Stops listening and shuts down the skeletons of the objects bound here, waiting up to
+    /// `timeout` in all for the calls they are serving. The port is free again afterwards,
+    /// so the registry can be created anew.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use rrmi::create_registry;
+    /// let reg = create_registry(1103);
+    /// let report = reg.shutdown(Duration::from_secs(1));
+    /// assert!(report.is_clean());
+    /// let again = create_registry(1103);
+    /// assert_eq!(again.port, 1103);
+    /// ```
+    #[cfg_attr(feature = "tracing", instrument)]
+    pub fn shutdown(&self, timeout: Duration) -> ShutdownReport {
+        let deadline = Instant::now() + timeout;
+        // ends the threads delivering events, they would hold up the shutdown
+        self.watchers
+            .lock()
+            .expect("Registry: unable to get watchers lock")
+            .clear();
+        let mut report = self.serving.shutdown(timeout);
+        if let Ok(mut registries) = LOCAL_REGISTRIES.lock()
+            && registries
+                .get(&self.port)
+                .is_some_and(|registry| std::ptr::eq(registry.as_ptr(), self))
+        {
+            registries.remove(&self.port);
+        }
+        let skeletons: Vec<Arc<Skeleton>> = self
+            .objects
+            .lock()
+            .expect("Registry: unable to get objects lock")
+            .values()
+            .cloned()
+            .collect();
+        for skeleton in skeletons {
+            let left = deadline.saturating_duration_since(Instant::now());
+            report.merge(skeleton.shutdown(left));
+        }
+        info!(port = self.port, ?report, "registry shut down");
+        report
+    }
+}
+
+impl Drop for Registry {
+    fn drop(&mut self) {
+        let report = self.shutdown(DROP_TIMEOUT);
+        for call in &report.aborted {
+            warn!(%call, "call aborted, registry dropped while it was running");
+        }
+    }
+}
+
+// Remote object code
+// this could also be generated from the macro
+use ::rrmi::stub::{Deserialize, Serialize, Stub};
+use ::rrmi::transport::{Connection, Transport};
+use rrmi::{marshal, receive_data, send_data, unmarshal};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum RegistryRequest {
+    Lookup {
+        name: String,
+    },
+    List,
+    Ping,
+    Bind {
+        name: String,
+        remote: RemoteRef,
+    },
+    Unbind {
+        name: String,
+    },
+    Describe {
+        name: String,
+    },
+    ListUnder {
+        prefix: String,
+    },
+    /// sent by a registry forwarding `request` to a peer, after `hops` registries
+    Forwarded {
+        hops: u8,
+        request: Box<RegistryRequest>,
+    },
+    /// sent by a replica to the leader, which answers with its log from `have` on
+    Propose {
+        change: Change,
+        have: u64,
+    },
+    /// sent by the leader to a replica, its log from `from` on
+    Replicate {
+        from: u64,
+        entries: Vec<Entry>,
+    },
+    /// sent by a starting replica to the others
+    Sync {
+        from: u64,
+    },
+    BindLease {
+        name: String,
+        remote: RemoteRef,
+        ttl: Duration,
+    },
+    Renew {
+        name: String,
+        ttl: Duration,
+    },
+    /// `watcher` is a `Watcher` the client exported, with an unspecified address when the
+    /// registry should call back on the one the request came from
+    Watch {
+        prefix: String,
+        watcher: RemoteRef,
+    },
+    Unwatch {
+        id: u64,
+    },
+    Join {
+        name: String,
+        remote: RemoteRef,
+    },
+    Leave {
+        name: String,
+        id: RMI_ID,
+    },
+    LookupGroup {
+        name: String,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub enum RegistryResponse {
+    Lookup(RMIResult<RemoteRef>),
+    List(RMIResult<Vec<String>>),
+    Ping,
+    Bind(RMIResult<()>),
+    Unbind(RMIResult<()>),
+    Describe(RMIResult<ObjectInfo>),
+    /// a replica log from the position given on
+    Log(RMIResult<(u64, Vec<Entry>)>),
+    /// how much of the log a replica has
+    Replicated(RMIResult<u64>),
+    Watch(RMIResult<u64>),
+    Unwatch(RMIResult<()>),
+    Renew(RMIResult<()>),
+    /// the id of a new group member
+    Join(RMIResult<RMI_ID>),
+    Group(RMIResult<Vec<RemoteRef>>),
+    /// an interceptor did not let the request through
+    Refused(RMIError),
+}
+
+impl RegistryRequest {
+    pub fn method_name(&self) -> &'static str {
+        match self {
+            RegistryRequest::Lookup { .. } => "lookup",
+            RegistryRequest::List => "list",
+            RegistryRequest::Ping => "ping",
+            RegistryRequest::Bind { .. } => "bind",
+            RegistryRequest::Unbind { .. } => "unbind",
+            RegistryRequest::Describe { .. } => "describe",
+            RegistryRequest::ListUnder { .. } => "list",
+            RegistryRequest::Forwarded { request, .. } => request.method_name(),
+            RegistryRequest::Propose { .. } | RegistryRequest::Replicate { .. } => "replicate",
+            RegistryRequest::Sync { .. } => "sync",
+            RegistryRequest::BindLease { .. } => "bind",
+            RegistryRequest::Renew { .. } => "renew",
+            RegistryRequest::Watch { .. } => "watch",
+            RegistryRequest::Unwatch { .. } => "unwatch",
+            RegistryRequest::Join { .. } => "bind",
+            RegistryRequest::Leave { .. } => "unbind",
+            RegistryRequest::LookupGroup { .. } => "lookup",
+        }
+    }
+}
+
+impl RegistryResponse {
+    fn error(&self) -> Option<RMIError> {
+        match self {
+            RegistryResponse::Lookup(Err(e))
+            | RegistryResponse::List(Err(e))
+            | RegistryResponse::Bind(Err(e))
+            | RegistryResponse::Unbind(Err(e))
+            | RegistryResponse::Describe(Err(e))
+            | RegistryResponse::Log(Err(e))
+            | RegistryResponse::Replicated(Err(e))
+            | RegistryResponse::Watch(Err(e))
+            | RegistryResponse::Unwatch(Err(e))
+            | RegistryResponse::Renew(Err(e))
+            | RegistryResponse::Join(Err(e))
+            | RegistryResponse::Group(Err(e))
+            | RegistryResponse::Refused(e) => Some(e.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl RemoteObject for Registry {
+    fn run(&self, stream: &mut dyn Connection) -> RMIResult<()> {
+        self.handle_connection(stream)
+    }
+    fn name(&self) -> &'static str {
+        "Registry"
+    }
+}
+#[allow(dead_code)]
+impl Registry {
+    #[cfg_attr(feature = "tracing", instrument)]
+    fn handle_connection(&self, stream: &mut dyn Connection) -> RMIResult<()> {
+        let request_bytes = receive_data(stream);
+        let request: RegistryRequest = unmarshal(&request_bytes)?;
+        let mut call = Call::new(
+            self.name(),
+            request.method_name(),
+            Headers::new(),
+            request_bytes.len(),
+        );
+        let started = self.hooks.start(&mut call);
+        let client = match (stream.local_addr(), stream.peer_addr()) {
+            _ if stream.in_process() => Caller::InProcess,
+            #[cfg(unix)]
+            (Some(local), Some(peer))
+                if is_same_host(&local, &peer) && self.unix_sockets.load(Ordering::Relaxed) =>
+            {
+                Caller::SameHost
+            }
+            // v4 clients of the dual-stack listener show up as ::ffff:a.b.c.d
+            (Some(local), _) if !local.ip().is_unspecified() => {
+                Caller::Reached(local.ip().to_canonical())
+            }
+            _ => Caller::Unknown,
+        };
+        let principal = Principal::of(stream);
+        let response: RegistryResponse = match started.refused() {
+            Some(e) => RegistryResponse::Refused(e),
+            None => self.handle_request(request, client, &principal, 0),
+        };
+        let response_bytes = marshal(&response)?;
+        started.finish(&call, response_bytes.len(), response.error());
+        send_data(response_bytes, stream)
+    }
+    #[cfg_attr(feature = "tracing", instrument)]
+    fn handle_request(
+        &self,
+        req: RegistryRequest,
+        client: Caller,
+        principal: &Principal,
+        hops: u8,
+    ) -> RegistryResponse {
+        match req {
+            RegistryRequest::Lookup { name } => {
+                let found = self.lookup_for(&name, &client);
+                RegistryResponse::Lookup(self.or_forward(found, &name, hops, |peer, rest| {
+                    peer.forwarded_lookup(rest, hops + 1)
+                }))
+            }
+            RegistryRequest::List => RegistryResponse::List(self.list()),
+            RegistryRequest::ListUnder { prefix } => {
+                RegistryResponse::List(self.list_under(&prefix))
+            }
+            RegistryRequest::Ping => RegistryResponse::Ping,
+            RegistryRequest::Bind { name, remote } => RegistryResponse::Bind(
+                self.authorize_mutation(principal, "bind")
+                    .and_then(|_| check_name(&name))
+                    .and_then(|_| self.replicated(Change::Bind { name, remote })),
+            ),
+            RegistryRequest::Unbind { name } => RegistryResponse::Unbind(
+                self.authorize_mutation(principal, "unbind")
+                    .and_then(|_| self.unbind(&name)),
+            ),
+            RegistryRequest::Describe { name } => {
+                let found = self.describe(&name);
+                let found = self.or_forward(found, &name, hops, |peer, rest| {
+                    peer.forwarded_describe(rest, hops + 1)
+                        .map(|info| ObjectInfo {
+                            name: name.clone(),
+                            ..info
+                        })
+                });
+                RegistryResponse::Describe(found)
+            }
+            RegistryRequest::Forwarded { hops, request } => {
+                // the answer goes to a client on another host, not to the forwarding registry
+                let client = match client {
+                    Caller::Reached(ip) if !ip.is_loopback() => Caller::Reached(ip),
+                    _ => Caller::Unknown,
+                };
+                self.handle_request(*request, client, principal, hops)
+            }
+            RegistryRequest::Propose { change, have } => RegistryResponse::Log(
+                self.authorize_mutation(principal, "replicate")
+                    .and_then(|_| self.replicas_for(true))
+                    .and_then(|replicas| {
+                        replicas.lead(change, |change| self.apply(change))?;
+                        Ok(replicas.since(have))
+                    }),
+            ),
+            RegistryRequest::Replicate { from, entries } => RegistryResponse::Replicated(
+                self.authorize_mutation(principal, "replicate")
+                    .and_then(|_| self.replicas_for(false))
+                    .map(|replicas| replicas.receive(from, entries, |change| self.apply(change))),
+            ),
+            RegistryRequest::Sync { from } => {
+                RegistryResponse::Log(self.replicas_for(false).map(|r| r.since(from)))
+            }
+            RegistryRequest::BindLease { name, remote, ttl } => RegistryResponse::Bind(
+                self.authorize_mutation(principal, "bind")
+                    .and_then(|_| check_name(&name))
+                    .and_then(|_| self.bind_remote_with_lease(&name, remote, ttl)),
+            ),
+            RegistryRequest::Renew { name, ttl } => RegistryResponse::Renew(
+                self.authorize_mutation(principal, "renew")
+                    .and_then(|_| self.renew(&name, ttl)),
+            ),
+            RegistryRequest::Watch {
+                prefix,
+                mut watcher,
+            } => {
+                if let Endpoint::Tcp(addr) = &mut watcher.addr
+                    && addr.ip().is_unspecified()
+                {
+                    let ip = principal.peer_addr.map(|peer| peer.ip().to_canonical());
+                    addr.set_ip(ip.unwrap_or(IpAddr::from([127, 0, 0, 1])));
+                }
+                RegistryResponse::Watch(
+                    self.authorize_mutation(principal, "watch")
+                        .and_then(|_| check_watcher(principal, &watcher))
+                        .and_then(|_| self.watch_for(&prefix, watcher)),
+                )
+            }
+            RegistryRequest::Unwatch { id } => RegistryResponse::Unwatch(
+                self.authorize_mutation(principal, "unwatch")
+                    .and_then(|_| self.unwatch(id)),
+            ),
+            RegistryRequest::Join { name, remote } => RegistryResponse::Join(
+                self.authorize_mutation(principal, "bind")
+                    .and_then(|_| check_name(&name))
+                    .and_then(|_| self.join_remote(&name, remote)),
+            ),
+            RegistryRequest::Leave { name, id } => RegistryResponse::Unbind(
+                self.authorize_mutation(principal, "unbind")
+                    .and_then(|_| self.leave(&name, id)),
+            ),
+            RegistryRequest::LookupGroup { name } => {
+                let found = self.lookup_group_for(&name, &client);
+                RegistryResponse::Group(self.or_forward(found, &name, hops, |peer, rest| {
+                    peer.forwarded_group(rest, hops + 1)
+                }))
+            }
+        }
+    }
+}
+
+/// Where a registry request came from, decides what kind of `RemoteRef` a lookup returns.
+#[derive(Debug)]
+enum Caller {
+    InProcess,
+    #[cfg(unix)]
+    SameHost,
+    /// another host, that reached us on this address
+    Reached(IpAddr),
+    Unknown,
+}
+
+#[derive(Debug, Clone)]
+pub struct RegistryStub {
+    remote: RemoteRef,
+    #[cfg(feature = "tls")]
+    tls: Option<TlsClientConfig>,
+    psk: Option<PreSharedKey>,
+    // replicas of the registry requests fail over to, see `with_replicas`
+    replicas: Vec<RemoteRef>,
+    // which of `remote` and `replicas` answered last, shared by the clones
+    current: Arc<AtomicUsize>,
+}
+impl RegistryStub {
+    pub fn new(remote: RemoteRef) -> Self {
+        RegistryStub {
+            remote,
+            #[cfg(feature = "tls")]
+            tls: None,
+            psk: None,
+            replicas: vec![],
+            current: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    #[cfg(feature = "tls")]
+    pub fn with_tls(remote: RemoteRef, tls: TlsClientConfig) -> Self {
+        RegistryStub {
+            tls: Some(tls),
+            ..RegistryStub::new(remote)
+        }
+    }
+
+    pub fn with_psk(remote: RemoteRef, key: PreSharedKey) -> Self {
+        RegistryStub {
+            psk: Some(key),
+            ..RegistryStub::new(remote)
+        }
+    }
+
+    /// Fails over to `replicas` of the registry, reached with the same TLS config or key,
+    /// when the one it talks to cannot be reached. It sticks with the replica that answered.
+    ///
+    /// A bind or unbind may be sent again to another replica when the connection broke
+    /// before the answer came.
+    pub fn with_replicas(self, replicas: Vec<RemoteRef>) -> Self {
+        RegistryStub { replicas, ..self }
+    }
+
+    fn replica(&self, index: usize) -> &RemoteRef {
+        match index {
+            0 => &self.remote,
+            _ => &self.replicas[index - 1],
+        }
+    }
+
+    fn connect(&self, remote: &RemoteRef, timeout: Option<Duration>) -> RMIResult<Client> {
+        #[cfg(feature = "tls")]
+        if let Some(tls) = &self.tls {
+            return Client::connect_tls(remote, tls, timeout);
+        }
+        if let Some(key) = &self.psk {
+            return Client::connect_psk(remote, key, timeout);
+        }
+        match timeout {
+            Some(timeout) => Client::connect_timeout(remote, timeout),
+            None => Client::connect(remote),
+        }
+    }
+
+    /// Sends `req` to the replica that answered last, or the next ones when it is down.
+    fn send(&self, req: RegistryRequest, timeout: Option<Duration>) -> RMIResult<RegistryResponse> {
+        let count = 1 + self.replicas.len();
+        let first = self.current.load(Ordering::Relaxed);
+        let mut failed = None;
+        for tried in 0..count {
+            let index = (first + tried) % count;
+            let remote = self.replica(index);
+            let sent = self
+                .connect(remote, timeout)
+                .and_then(|transport| transport.send(req.clone()));
+            match sent {
+                Err(e) if count > 1 && unreachable(&e) => {
+                    warn!(addr = %remote.addr, error = %e, "registry replica unreachable");
+                    failed = Some(e);
+                }
+                sent => {
+                    if tried > 0 {
+                        info!(addr = %remote.addr, "failed over to registry replica");
+                        self.current.store(index, Ordering::Relaxed);
+                    }
+                    return sent;
+                }
+            }
+        }
+        Err(failed.expect("a registry stub has at least one address"))
+    }
+    /// Wraps what a lookup returned, objects are reached with this stub's TLS config or key.
+    fn stub(&self, remote: RemoteRef) -> Stub {
+        let stub = Stub::with_psk(remote, self.psk.clone());
+        #[cfg(feature = "tls")]
+        let stub = Stub {
+            tls: self.tls.clone(),
+            ..stub
+        };
+        stub
+    }
+
+    #[cfg_attr(feature = "tracing", instrument)]
+    pub fn lookup(&self, name: &str) -> RMIResult<Stub> {
+        let req = RegistryRequest::Lookup {
+            name: name.to_string(),
+        };
+        let resp = self.send(req, None)?;
+        match resp {
+            RegistryResponse::Lookup(res) => res.map(|res| self.stub(res)),
+            RegistryResponse::Refused(e) => Err(e),
+            _ => Err(RMIError::TransportError("Wrong response".to_string())),
+        }
+    }
+    /// Looks up `name` for calls by method name, see `DynamicStub`.
+    #[cfg_attr(feature = "tracing", instrument)]
+    pub fn lookup_dynamic(&self, name: &str) -> RMIResult<DynamicStub> {
+        let info = self.describe(name)?;
+        DynamicStub::new(self.lookup(name)?, info)
+    }
+
+    #[cfg_attr(feature = "tracing", instrument)]
+    pub fn list(&self) -> RMIResult<Vec<String>> {
         let req = RegistryRequest::List {};
-        let resp: RegistryResponse = transport.send(req)?;
+        let resp = self.send(req, None)?;
+        match resp {
+            RegistryResponse::List(res) => res,
+            RegistryResponse::Refused(e) => Err(e),
+            _ => Err(RMIError::TransportError("Wrong response".to_string())),
+        }
+    }
+    #[cfg_attr(feature = "tracing", instrument)]
+    pub fn ping(&self) -> RMIResult<()> {
+        self.send_ping(None)
+    }
+
+    /// Pings without hanging on hosts that drop packets or on ports where something else listens.
+    #[cfg_attr(feature = "tracing", instrument)]
+    pub fn ping_timeout(&self, timeout: Duration) -> RMIResult<()> {
+        self.send_ping(Some(timeout))
+    }
+
+    /// Binds `name` to `remote`, an object this process exported, see `Skeleton::export`.
+    /// Fails with `RMIError::PermissionDenied` unless the registry lets this caller bind.
+    #[cfg_attr(feature = "tracing", instrument)]
+    pub fn bind(&self, name: &str, remote: RemoteRef) -> RMIResult<()> {
+        let req = RegistryRequest::Bind {
+            name: name.to_string(),
+            remote,
+        };
+        let resp = self.send(req, None)?;
+        match resp {
+            RegistryResponse::Bind(res) => res,
+            RegistryResponse::Refused(e) => Err(e),
+            _ => Err(RMIError::TransportError("Wrong response".to_string())),
+        }
+    }
+
+    #[cfg_attr(feature = "tracing", instrument)]
+    pub fn unbind(&self, name: &str) -> RMIResult<()> {
+        let req = RegistryRequest::Unbind {
+            name: name.to_string(),
+        };
+        let resp = self.send(req, None)?;
+        match resp {
+            RegistryResponse::Unbind(res) => res,
+            RegistryResponse::Refused(e) => Err(e),
+            _ => Err(RMIError::TransportError("Wrong response".to_string())),
+        }
+    }
+
+    #[cfg_attr(feature = "tracing", instrument)]
+    pub fn describe(&self, name: &str) -> RMIResult<ObjectInfo> {
+        let req = RegistryRequest::Describe {
+            name: name.to_string(),
+        };
+        let resp = self.send(req, None)?;
+        match resp {
+            RegistryResponse::Describe(res) => res,
+            RegistryResponse::Refused(e) => Err(e),
+            _ => Err(RMIError::TransportError("Wrong response".to_string())),
+        }
+    }
+
+    /// The names below `prefix`, see `Registry::list_under`.
+    #[cfg_attr(feature = "tracing", instrument)]
+    pub fn list_under(&self, prefix: &str) -> RMIResult<Vec<String>> {
+        let req = RegistryRequest::ListUnder {
+            prefix: prefix.to_string(),
+        };
+        let resp = self.send(req, None)?;
         match resp {
             RegistryResponse::List(res) => res,
+            RegistryResponse::Refused(e) => Err(e),
+            _ => Err(RMIError::TransportError("Wrong response".to_string())),
+        }
+    }
+
+    fn forward(&self, request: RegistryRequest, hops: u8) -> RMIResult<RegistryResponse> {
+        let req = RegistryRequest::Forwarded {
+            hops,
+            request: Box::new(request),
+        };
+        match self.send(req, Some(PING_TIMEOUT))? {
+            RegistryResponse::Refused(e) => Err(e),
+            resp => Ok(resp),
+        }
+    }
+
+    fn forwarded_lookup(&self, name: &str, hops: u8) -> RMIResult<RemoteRef> {
+        let req = RegistryRequest::Lookup {
+            name: name.to_string(),
+        };
+        match self.forward(req, hops)? {
+            RegistryResponse::Lookup(res) => res,
+            _ => Err(RMIError::TransportError("Wrong response".to_string())),
+        }
+    }
+
+    fn forwarded_group(&self, name: &str, hops: u8) -> RMIResult<Vec<RemoteRef>> {
+        let req = RegistryRequest::LookupGroup {
+            name: name.to_string(),
+        };
+        match self.forward(req, hops)? {
+            RegistryResponse::Group(res) => res,
+            _ => Err(RMIError::TransportError("Wrong response".to_string())),
+        }
+    }
+
+    fn forwarded_describe(&self, name: &str, hops: u8) -> RMIResult<ObjectInfo> {
+        let req = RegistryRequest::Describe {
+            name: name.to_string(),
+        };
+        match self.forward(req, hops)? {
+            RegistryResponse::Describe(res) => res,
+            _ => Err(RMIError::TransportError("Wrong response".to_string())),
+        }
+    }
+
+    /// Adds `remote`, an object this process exported, to the group `name`, see
+    /// `Registry::join`. Returns its id in the group, for `leave`.
+    #[cfg_attr(feature = "tracing", instrument)]
+    pub fn join(&self, name: &str, remote: RemoteRef) -> RMIResult<RMI_ID> {
+        let req = RegistryRequest::Join {
+            name: name.to_string(),
+            remote,
+        };
+        match self.send(req, None)? {
+            RegistryResponse::Join(res) => res,
+            RegistryResponse::Refused(e) => Err(e),
+            _ => Err(RMIError::TransportError("Wrong response".to_string())),
+        }
+    }
+
+    #[cfg_attr(feature = "tracing", instrument)]
+    pub fn leave(&self, name: &str, id: RMI_ID) -> RMIResult<()> {
+        let req = RegistryRequest::Leave {
+            name: name.to_string(),
+            id,
+        };
+        match self.send(req, None)? {
+            RegistryResponse::Unbind(res) => res,
+            RegistryResponse::Refused(e) => Err(e),
+            _ => Err(RMIError::TransportError("Wrong response".to_string())),
+        }
+    }
+
+    /// Looks up every member of the group `name` and connects to them, a name bound to a
+    /// single object makes a group of one. Members that cannot be reached are left out.
+    ///
+    /// ```
+    /// use rrmi::remote::{Balance, GroupStub, MockRemoteObject, MockRemoteObjectStub};
+    /// use rrmi::{create_registry_in, get_registry_in};
+    /// let reg = create_registry_in("grouped").expect("grouped is free");
+    /// for _ in 0..3 {
+    ///     reg.join("workers", MockRemoteObject::silent()).expect("workers is a group");
+    /// }
+    /// let stub = get_registry_in("grouped").expect("registry is in this process");
+    /// let workers: GroupStub<MockRemoteObjectStub> = stub
+    ///     .lookup_group("workers")
+    ///     .expect("workers are bound")
+    ///     .with_balance(Balance::LeastOutstanding);
+    /// assert_eq!(workers.len(), 3);
+    /// assert_eq!(workers.call(|worker| worker.run("job", vec![1])), Ok(vec![1]));
+    /// ```
+    #[cfg_attr(feature = "tracing", instrument)]
+    pub fn lookup_group<S: RemoteStub>(&self, name: &str) -> RMIResult<GroupStub<S>> {
+        let req = RegistryRequest::LookupGroup {
+            name: name.to_string(),
+        };
+        let members = match self.send(req, None)? {
+            RegistryResponse::Group(res) => res,
+            RegistryResponse::Refused(e) => Err(e),
+            _ => Err(RMIError::TransportError("Wrong response".to_string())),
+        }?;
+        let stubs = members
+            .into_iter()
+            .map(|remote| self.stub(remote))
+            .collect();
+        GroupStub::connect(name, stubs)
+    }
+
+    /// Binds `name` to `remote` for as long as the returned `Lease` is kept, renewing it in
+    /// the background. If this process dies the registry unbinds the name once `ttl` passed
+    /// without a renewal, see `Registry::bind_remote_with_lease`.
+    #[cfg_attr(feature = "tracing", instrument)]
+    pub fn bind_with_lease(
+        &self,
+        name: &str,
+        remote: RemoteRef,
+        ttl: Duration,
+    ) -> RMIResult<Lease> {
+        self.lease(name, remote.clone(), ttl)?;
+        Lease::start(self.clone(), name, remote, ttl)
+    }
+
+    pub(crate) fn lease(&self, name: &str, remote: RemoteRef, ttl: Duration) -> RMIResult<()> {
+        let req = RegistryRequest::BindLease {
+            name: name.to_string(),
+            remote,
+            ttl,
+        };
+        match self.send(req, None)? {
+            RegistryResponse::Bind(res) => res,
+            RegistryResponse::Refused(e) => Err(e),
+            _ => Err(RMIError::TransportError("Wrong response".to_string())),
+        }
+    }
+
+    /// Extends the lease of `name`, see `Registry::renew`. `Lease` does it on its own.
+    #[cfg_attr(feature = "tracing", instrument)]
+    pub fn renew(&self, name: &str, ttl: Duration) -> RMIResult<()> {
+        let req = RegistryRequest::Renew {
+            name: name.to_string(),
+            ttl,
+        };
+        match self.send(req, None)? {
+            RegistryResponse::Renew(res) => res,
+            RegistryResponse::Refused(e) => Err(e),
+            _ => Err(RMIError::TransportError("Wrong response".to_string())),
+        }
+    }
+
+    /// Calls `on_event` with the changes of the names under `prefix` until the returned
+    /// `Watch` is dropped, the empty prefix watches every name.
+    ///
+    /// The registry calls back on an object this process exports for the watch, so it has to
+    /// be able to connect to this host. Not available over TLS.
+    ///
+    /// ```
+    /// use std::sync::mpsc::channel;
+    /// use std::time::Duration;
+    /// use rrmi::remote::{MockRemoteObject, WatchEvent};
+    /// use rrmi::{create_registry_in, get_registry_in};
+    /// let reg = create_registry_in("watched").expect("watched is free");
+    /// let (events, received) = channel();
+    /// let stub = get_registry_in("watched").expect("registry is in this process");
+    /// let _watch = stub
+    ///     .watch("jobs", move |event| drop(events.send(event)))
+    ///     .expect("registry can call back");
+    /// reg.bind("jobs/worker-3", MockRemoteObject::silent());
+    /// let event = received.recv_timeout(Duration::from_secs(1));
+    /// assert_eq!(event, Ok(WatchEvent::Bound { name: "jobs/worker-3".to_string() }));
+    /// ```
+    #[cfg_attr(feature = "tracing", instrument(skip(on_event)))]
+    pub fn watch(
+        &self,
+        prefix: &str,
+        on_event: impl Fn(WatchEvent) + Send + Sync + 'static,
+    ) -> RMIResult<Watch> {
+        #[cfg(feature = "tls")]
+        if self.tls.is_some() {
+            return Err(RMIError::Tls(
+                "watching is not available over TLS".to_string(),
+            ));
+        }
+        let skeleton = Skeleton::new(Arc::new(Watcher::new(on_event)));
+        let current = self.replica(self.current.load(Ordering::Relaxed));
+        let watcher = match (&current.addr, &self.psk) {
+            (Endpoint::Memory(_), _) => Endpoint::Memory(skeleton.listen_memory()?),
+            (_, Some(key)) => Endpoint::Tcp(SocketAddr::new(
+                IpAddr::from([0; 16]),
+                skeleton.listen_psk(key)?,
+            )),
+            (_, None) => Endpoint::Tcp(SocketAddr::new(IpAddr::from([0; 16]), skeleton.listen()?)),
+        };
+        let req = RegistryRequest::Watch {
+            prefix: prefix.to_string(),
+            watcher: RemoteRef::new(watcher, 0),
+        };
+        let id = match self.send(req, None)? {
+            RegistryResponse::Watch(res) => res,
+            RegistryResponse::Refused(e) => Err(e),
+            _ => Err(RMIError::TransportError("Wrong response".to_string())),
+        }?;
+        Ok(Watch {
+            registry: self.clone(),
+            id,
+            _watcher: skeleton,
+        })
+    }
+
+    pub(crate) fn unwatch(&self, id: u64) -> RMIResult<()> {
+        match self.send(RegistryRequest::Unwatch { id }, None)? {
+            RegistryResponse::Unwatch(res) => res,
+            RegistryResponse::Refused(e) => Err(e),
+            _ => Err(RMIError::TransportError("Wrong response".to_string())),
+        }
+    }
+
+    /// Asks the leader of the replicas of the registry to make `change`, see `Replicas`.
+    pub(crate) fn propose(&self, change: Change, have: u64) -> RMIResult<(u64, Vec<Entry>)> {
+        let req = RegistryRequest::Propose { change, have };
+        match self.send(req, Some(REPLICATION_TIMEOUT))? {
+            RegistryResponse::Log(res) => res,
+            RegistryResponse::Refused(e) => Err(e),
+            _ => Err(RMIError::TransportError("Wrong response".to_string())),
+        }
+    }
+
+    pub(crate) fn replicate(&self, from: u64, entries: Vec<Entry>) -> RMIResult<u64> {
+        let req = RegistryRequest::Replicate { from, entries };
+        match self.send(req, Some(REPLICATION_TIMEOUT))? {
+            RegistryResponse::Replicated(res) => res,
+            RegistryResponse::Refused(e) => Err(e),
+            _ => Err(RMIError::TransportError("Wrong response".to_string())),
+        }
+    }
+
+    pub(crate) fn sync(&self, from: u64) -> RMIResult<(u64, Vec<Entry>)> {
+        let req = RegistryRequest::Sync { from };
+        match self.send(req, Some(REPLICATION_TIMEOUT))? {
+            RegistryResponse::Log(res) => res,
+            RegistryResponse::Refused(e) => Err(e),
+            _ => Err(RMIError::TransportError("Wrong response".to_string())),
+        }
+    }
+
+    fn send_ping(&self, timeout: Option<Duration>) -> RMIResult<()> {
+        match self.send(RegistryRequest::Ping, timeout)? {
+            RegistryResponse::Ping => Ok(()),
+            RegistryResponse::Refused(e) => Err(e),
             _ => Err(RMIError::TransportError("Wrong response".to_string())),
         }
     }
@@ -1,34 +1,78 @@
 use crate::RMI_ID;
-use crate::TcpStream;
 use crate::error::RMIError;
 use crate::stub::{Deserialize, Serialize};
-use crate::transport::{IpAddr, SocketAddr};
+use crate::transport::{Connection, Endpoint, IpAddr, SocketAddr};
 use rrmi_macros::remote_object;
+use sha2::{Digest, Sha256};
+use std::fmt::Display;
+use tracing::info;
 
 pub type RMIResult<T> = Result<T, RMIError>;
 
-#[derive(Clone, Serialize, Deserialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
 pub struct RemoteRef {
     //should point to RemoteObject on the server side
-    pub addr: SocketAddr, // 127.0.0.1:8080 for example
-    pub id: RMI_ID,       // just a num for identity
+    pub addr: Endpoint, // tcp://127.0.0.1:8080 or unix:///tmp/rrmi-1-0.sock for example
+    pub id: RMI_ID,     // just a num for identity
 }
 
 impl RemoteRef {
-    pub fn new(addr: SocketAddr, id: RMI_ID) -> Self {
-        RemoteRef { addr, id }
+    pub fn new(addr: impl Into<Endpoint>, id: RMI_ID) -> Self {
+        RemoteRef {
+            addr: addr.into(),
+            id,
+        }
     }
     pub fn example() -> Self {
         let addr = SocketAddr::new(IpAddr::from([127, 0, 0, 1]), 1099);
-        RemoteRef { addr, id: 1 }
+        RemoteRef::new(addr, 1)
+    }
+}
+
+/// A `#[remote]` method as it is written, captured by `#[remote_object]`.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct MethodSignature {
+    pub name: String,
+    /// names and types of the parameters, `&str` parameters are sent as `String`
+    pub params: Vec<(String, String)>,
+    pub returns: String,
+    /// the role set with `#[remote(require = "role")]`
+    pub require: Option<String>,
+}
+
+impl Display for MethodSignature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let params: Vec<String> = self
+            .params
+            .iter()
+            .map(|(name, ty)| format!("{name}: {ty}"))
+            .collect();
+        write!(
+            f,
+            "fn {}({}) -> {}",
+            self.name,
+            params.join(", "),
+            self.returns
+        )
     }
 }
 
 pub trait RemoteObject: Send + Sync {
-    fn run(&self, stream: &mut TcpStream) -> RMIResult<()>;
+    fn run(&self, stream: &mut dyn Connection) -> RMIResult<()>;
 
     fn name(&self) -> &'static str;
 
+    /// The `#[remote]` methods of the object, in the order they are declared.
+    fn methods(&self) -> Vec<MethodSignature> {
+        vec![]
+    }
+
+    /// Changes whenever the type name or a method signature does, so clients can tell whether
+    /// the stub they were built with still matches the object.
+    fn fingerprint(&self) -> String {
+        fingerprint(self.name(), &self.methods())
+    }
+
     // fn listen(self: &Arc<Self>) -> RMIResult<u16>;
     // CANNOT USE AS DYNAMIC WITH &Arc ref
 
@@ -36,6 +80,23 @@ pub trait RemoteObject: Send + Sync {
     //CANNOT USE AS DYNAMIC WITH generic types
 }
 
+/// Hash of a type name and its method signatures, 16 hex digits.
+pub fn fingerprint(type_name: &str, methods: &[MethodSignature]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(type_name.as_bytes());
+    for method in methods {
+        hasher.update(b"\n");
+        hasher.update(method.to_string().as_bytes());
+        if let Some(role) = &method.require {
+            hasher.update(role.as_bytes());
+        }
+    }
+    hasher.finalize()[..8]
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
 #[allow(dead_code)]
 #[derive(Debug)]
 pub struct MockRemoteObject {
@@ -59,8 +120,14 @@ impl MockRemoteObject {
     #[remote]
     fn run(&self, method_name: &str, args: Vec<u8>) -> Vec<u8> {
         if self.verbose {
-            eprintln!("Remote got {method_name} and vec: {args:?}");
+            info!(method_name, ?args, "mock remote object called");
         }
         args
     }
 }
+
+impl Default for MockRemoteObject {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,239 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tracing::{debug, warn};
+
+use crate::error::RMIError;
+use crate::remote::registry::{PING_TIMEOUT, RegistryStub};
+use crate::remote::{RMIResult, RemoteRef};
+use crate::stub::{Deserialize, Serialize};
+
+/// How long a replica waits on another one, a busy leader should answer well within it.
+pub(crate) const REPLICATION_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A change of the remote binds of a registry, as journals record it and replicas ship it.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum Change {
+    Bind {
+        name: String,
+        remote: RemoteRef,
+    },
+    Unbind {
+        name: String,
+    },
+    /// an unbind because the lease of the name ran out
+    Expire {
+        name: String,
+    },
+}
+
+/// A change in the log of the replicas, with an id that tells it apart from a change
+/// another leader made at the same position.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Entry {
+    pub id: u64,
+    pub change: Change,
+}
+
+impl Entry {
+    fn new(change: Change) -> RMIResult<Self> {
+        let id = getrandom::u64().map_err(|e| RMIError::IoError(e.to_string()))?;
+        Ok(Entry { id, change })
+    }
+
+    fn name(&self) -> &str {
+        match &self.change {
+            Change::Bind { name, .. } | Change::Unbind { name } | Change::Expire { name } => name,
+        }
+    }
+}
+
+/// Whether `e` means the registry could not be reached at all, so another replica may be.
+pub(crate) fn unreachable(e: &RMIError) -> bool {
+    matches!(
+        e,
+        RMIError::TransportError(_) | RMIError::IoError(_) | RMIError::RegistryUnreachable(_)
+    )
+}
+
+/// The replicas a registry is one of, see `Registry::use_replicas`.
+///
+/// The leader is the first replica by rank that answers a ping. Every change goes through it:
+/// it applies the change, appends it to its log and ships the log to the others in the
+/// background. The replica that proposed the change gets the missing part of the log in the
+/// answer, so it has the change before its client hears back.
+///
+/// Logs are sent with the entry before the part the replica misses, so it can check it still
+/// agrees with the leader. A leader cut off from the others keeps leading its side, when they
+/// meet again the replicas take the log of the one ranked first and drop what the other side
+/// changed meanwhile.
+#[derive(Debug)]
+pub(crate) struct Replicas {
+    members: Vec<RegistryStub>,
+    me: usize,
+    // every change applied here, in the order of the leader
+    log: Mutex<Vec<Entry>>,
+    // how much of the log each replica had when last heard from, by rank
+    acked: Mutex<Vec<u64>>,
+}
+
+impl Replicas {
+    pub(crate) fn new(members: Vec<RegistryStub>, me: usize, log: Vec<Entry>) -> Self {
+        let acked = vec![log.len() as u64; members.len()];
+        Replicas {
+            members,
+            me,
+            log: Mutex::new(log),
+            acked: Mutex::new(acked),
+        }
+    }
+
+    fn log(&self) -> std::sync::MutexGuard<'_, Vec<Entry>> {
+        self.log.lock().expect("Replicas: unable to get log lock")
+    }
+
+    pub(crate) fn len(&self) -> u64 {
+        self.log().len() as u64
+    }
+
+    /// The log from position `from` on, and the entry before it to check against.
+    pub(crate) fn since(&self, from: u64) -> (u64, Vec<Entry>) {
+        let log = self.log();
+        let from = from.min(log.len() as u64).saturating_sub(1);
+        (from, log[from as usize..].to_vec())
+    }
+
+    /// The replicas ranked before this one, the ones that lead when they are up.
+    pub(crate) fn before_me(&self) -> impl Iterator<Item = (usize, &RegistryStub)> {
+        self.members[..self.me].iter().enumerate()
+    }
+
+    /// Whether this replica leads: none of the ones ranked before it answers.
+    pub(crate) fn leads(&self) -> bool {
+        self.before_me()
+            .all(|(_, member)| member.ping_timeout(PING_TIMEOUT).is_err())
+    }
+
+    /// Applies `change` as the leader and ships it to the other replicas.
+    pub(crate) fn lead(
+        self: &Arc<Self>,
+        change: Change,
+        apply: impl FnOnce(&Change) -> RMIResult<()>,
+    ) -> RMIResult<()> {
+        let entry = Entry::new(change)?;
+        let mut log = self.log();
+        apply(&entry.change)?;
+        log.push(entry);
+        drop(log);
+        self.ship();
+        Ok(())
+    }
+
+    /// Applies what this replica misses of `entries`, the leader's log from position `from`
+    /// on. Returns how much of the log it has, less than the leader's when there is a gap or
+    /// the replica needs the whole log to find where it stopped agreeing.
+    pub(crate) fn receive(
+        &self,
+        from: u64,
+        entries: Vec<Entry>,
+        apply: impl Fn(&Change) -> RMIResult<()>,
+    ) -> u64 {
+        let mut log = self.log();
+        let have = log.len() as u64;
+        if from > have {
+            return have;
+        }
+        let mut at = from as usize;
+        let mut entries = entries.into_iter().peekable();
+        while at < log.len() && entries.peek().is_some_and(|entry| entry.id == log[at].id) {
+            at += 1;
+            entries.next();
+        }
+        if at < log.len() && entries.peek().is_some() {
+            if at == from as usize && from > 0 {
+                // the logs differ before this part too
+                return 0;
+            }
+            let dropped = log.split_off(at);
+            warn!(
+                at,
+                dropped = dropped.len(),
+                "replica diverged from the leader, dropping its changes"
+            );
+            undo(&log, &dropped, &apply);
+        }
+        for entry in entries {
+            // the leader applied it, a failure here only means this replica already agrees
+            if let Err(e) = apply(&entry.change) {
+                debug!(change = ?entry.change, error = %e, "replicated change did not apply");
+            }
+            log.push(entry);
+        }
+        log.len() as u64
+    }
+
+    /// Sends the other replicas what they miss of the log, on a thread of its own.
+    fn ship(self: &Arc<Self>) {
+        let replicas = Arc::clone(self);
+        let shipping = std::thread::Builder::new()
+            .name("Replicas".to_string())
+            .spawn(move || {
+                for (rank, member) in replicas.members.iter().enumerate() {
+                    if rank == replicas.me {
+                        continue;
+                    }
+                    if let Err(e) = replicas.push(rank, member) {
+                        debug!(rank, error = %e, "replica missed changes");
+                    }
+                }
+            });
+        if let Err(e) = shipping {
+            warn!(error = %e, "could not ship changes to replicas");
+        }
+    }
+
+    fn push(&self, rank: usize, member: &RegistryStub) -> RMIResult<()> {
+        let acked = |have: Option<u64>| {
+            let mut acked = self
+                .acked
+                .lock()
+                .expect("Replicas: unable to get acked lock");
+            if let Some(have) = have {
+                acked[rank] = have;
+            }
+            acked[rank]
+        };
+        // more rounds fill in a gap the replica reported, or send it the whole log
+        for _ in 0..3 {
+            let (from, entries) = self.since(acked(None));
+            let sent = from + entries.len() as u64;
+            if acked(Some(member.replicate(from, entries)?)) >= sent {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Takes back the `dropped` entries that followed `log`, each name they touched goes back to
+/// what `log` left it at.
+fn undo(log: &[Entry], dropped: &[Entry], apply: &impl Fn(&Change) -> RMIResult<()>) {
+    let mut names: Vec<&str> = dropped.iter().map(Entry::name).collect();
+    names.sort_unstable();
+    names.dedup();
+    for name in names {
+        let last = log.iter().rev().find(|entry| entry.name() == name);
+        let change = match last.map(|entry| &entry.change) {
+            Some(Change::Bind { remote, .. }) => Change::Bind {
+                name: name.to_string(),
+                remote: remote.clone(),
+            },
+            _ => Change::Unbind {
+                name: name.to_string(),
+            },
+        };
+        if let Err(e) = apply(&change) {
+            debug!(?change, error = %e, "dropped change did not undo");
+        }
+    }
+}
@@ -1,25 +1,62 @@
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests {
-    use crate::remote::Registry;
-    use crate::remote::registry::get_registry;
-    use crate::transport::{SocketAddr, TcpListener, TcpStream};
+    use crate::remote::journal::Journal;
+    use crate::remote::registry::{
+        RegistryStub, check_watcher, get_registry, get_registry_replicas,
+    };
+    use crate::remote::{Balance, GroupStub, WatchEvent, check_name};
+    use crate::remote::{
+        Call, Interceptor, Outcome, Principal, REGISTRY_WRITE_ROLE, Registry, RemoteObject, Roles,
+    };
+    use crate::trace::{TRACEPARENT_HEADER, TraceContext};
+    use crate::transport::{IpAddr, SocketAddr, TcpStream};
     use crate::utils::get_local_ips;
-    use crate::{RMIError, create_registry};
+    use crate::{AbortedCall, ShutdownReport};
+    use crate::{Endpoint, PreSharedKey, RemoteRef, create_registry_psk, get_registry_psk};
+    use crate::{Headers, RMIResult, TIMEOUT_HEADER};
+    use crate::{Naming, RmiUrl};
+    use crate::{RMIError, call_context, create_registry, create_registry_in, get_registry_in};
     use crate::{
         receive_data,
         remote::{MockRemoteObject, MockRemoteObjectStub},
         send_data,
-        stub::{Stub, marshal, unmarshal},
+        stub::{Skeleton, Stub, marshal, unmarshal},
     };
-    use core::{panic, time};
+    use core::panic;
+    use rrmi_macros::remote_object;
+    use std::collections::BTreeMap;
+    use std::net::Ipv6Addr;
+    use std::net::TcpListener;
+    use std::sync::atomic::{AtomicBool, Ordering};
     use std::sync::{Arc, Mutex};
     #[allow(unused_imports)]
     use std::{io::Read, thread, time::Duration};
     use threadpool::ThreadPool;
 
+    static UNREACHABLE_PORT: u16 = 10993;
+    static PING_PORT: u16 = 10994;
+    static IPV6_PORT: u16 = 10995;
+    static UNIX_PORT: u16 = 10992;
     static POPUL_PORT: u16 = 10996;
     static BIND_PORT: u16 = 10997;
     static LOCAL_PORT: u16 = 10998;
+    static IN_PROCESS_PORT: u16 = 10991;
+    static SHM_PORT: u16 = 10990;
+    static PSK_PORT: u16 = 10987;
+    static AUTH_PORT: u16 = 10986;
+    static SHUTDOWN_PORT: u16 = 10981;
+    static FRONT_PORT: u16 = 10980;
+    static PEER_PORT: u16 = 10979;
+    static REPLICA_PORTS: [u16; 3] = [10978, 10977, 10976];
+    static WATCH_PORT: u16 = 10975;
+    static LEASE_PORT: u16 = 10974;
+    static GROUP_PORT: u16 = 10973;
+    static NAMESPACE_PORT: u16 = 10972;
+    static SILENT_PORT: u16 = 10966;
+    static SILENT_PSK_PORT: u16 = 10965;
+    static SLOW_PEER_PORTS: [u16; 2] = [10964, 10963];
+    static PARTITION_PORTS: [u16; 4] = [10970, 10969, 10968, 10967];
     static REMOTE_TEST_PORT: u16 = 12345;
     static REMOTE_TEST_SYNC_PORT: u16 = 54321;
     static REMOTE_HOST: &str = "0065074.student.liacs.nl";
@@ -35,6 +72,600 @@ mod tests {
         assert_eq!(addr, SocketAddr::new(ip, port))
     }
 
+    #[test]
+    fn advertised_ip() {
+        let reg = Registry::default();
+        let advertised = IpAddr::from([192, 0, 2, 7]);
+        reg.set_advertised_ip(advertised);
+        assert_eq!(reg.get_ip(), Ok(advertised));
+        assert_eq!(
+            reg.construct_addr(2000),
+            Ok(SocketAddr::new(advertised, 2000))
+        );
+    }
+
+    #[test]
+    fn remote_ref_ipv6() {
+        let ip = IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1));
+        let remote = RemoteRef::new(SocketAddr::new(ip, 1099), 7);
+        let bytes = marshal(&remote).expect("RemoteRef is serializable");
+        let back: RemoteRef = unmarshal(&bytes).expect("bytes from RemoteRef are deserializable");
+        assert_eq!(back.addr, remote.addr);
+        assert_eq!(back.id, remote.id);
+    }
+
+    #[test]
+    fn ipv6_loopback() {
+        let reg = create_registry(IPV6_PORT);
+        reg.use_unix_sockets(false);
+        reg.use_in_process(false);
+        reg.bind("v6", MockRemoteObject::silent());
+        let rmt_reg = get_registry("::1", IPV6_PORT).expect("registry is listening");
+        let stb = rmt_reg.lookup("v6").expect("v6 should be in");
+        // the skeleton is advertised on the address we reached the registry with
+        match stb.remote.addr {
+            Endpoint::Tcp(addr) => assert_eq!(addr.ip(), IpAddr::V6(Ipv6Addr::LOCALHOST)),
+            other => panic!("expected a tcp endpoint, got {other}"),
+        }
+        let stub: MockRemoteObjectStub = stb.into();
+        let res = stub
+            .run("over ipv6", vec![6; 3])
+            .expect("MockObject returns the args");
+        assert_eq!(res, vec![6; 3]);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn unix_socket_same_host() {
+        let reg = create_registry(UNIX_PORT);
+        reg.use_in_process(false);
+        reg.use_shared_memory(false);
+        reg.bind("local", MockRemoteObject::silent());
+        let rmt_reg = get_registry("localhost", UNIX_PORT).expect("registry is listening");
+        let stb = rmt_reg.lookup("local").expect("local should be in");
+        let Endpoint::Unix(path) = stb.remote.addr.clone() else {
+            panic!(
+                "same host lookups should use unix sockets, got {}",
+                stb.remote.addr
+            );
+        };
+        let stub: MockRemoteObjectStub = stb.into();
+        for i in 0..3 {
+            let res = stub
+                .run("over uds", vec![i; 4])
+                .expect("MockObject returns the args");
+            assert_eq!(res, vec![i; 4]);
+        }
+        // the socket file is gone once the stub is connected
+        assert!(!path.exists());
+
+        reg.use_unix_sockets(false);
+        let stb = rmt_reg.lookup("local").expect("local should be in");
+        assert!(matches!(stb.remote.addr, Endpoint::Tcp(_)));
+    }
+
+    #[test]
+    fn in_process_registry() {
+        let reg = create_registry_in("tests").expect("tests is free");
+        reg.bind("mem", MockRemoteObject::silent());
+        let rmt_reg = get_registry_in("tests").expect("registry is in this process");
+        assert_eq!(rmt_reg.list(), Ok(vec!["mem".to_string()]));
+        let stb = rmt_reg.lookup("mem").expect("mem should be in");
+        assert!(matches!(stb.remote.addr, Endpoint::Memory(_)));
+        let stub: MockRemoteObjectStub = stb.into();
+        for i in 0..3 {
+            let res = stub
+                .run("in process", vec![i; 5])
+                .expect("MockObject returns the args");
+            assert_eq!(res, vec![i; 5]);
+        }
+        match get_registry_in("no-such-namespace") {
+            Err(RMIError::RegistryUnreachable(_)) => (),
+            other => panic!("expected RegistryUnreachable, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn namespaces_apart_from_ports() {
+        let by_port = create_registry(NAMESPACE_PORT);
+        by_port.bind("socket", MockRemoteObject::silent());
+        let by_name = create_registry_in(&NAMESPACE_PORT.to_string()).expect("not a port's");
+        by_name.bind("memory", MockRemoteObject::silent());
+        let rmt_reg =
+            get_registry_in(&NAMESPACE_PORT.to_string()).expect("registry is in this process");
+        assert_eq!(rmt_reg.list(), Ok(vec!["memory".to_string()]));
+        let rmt_reg = get_registry("localhost", NAMESPACE_PORT).expect("registry is listening");
+        assert_eq!(rmt_reg.list(), Ok(vec!["socket".to_string()]));
+
+        let taken = format!(":{NAMESPACE_PORT}");
+        assert!(matches!(
+            create_registry_in(&taken),
+            Err(RMIError::BadArguments(_))
+        ));
+        assert!(matches!(
+            create_registry_in(""),
+            Err(RMIError::BadArguments(_))
+        ));
+        assert!(matches!(
+            create_registry_in(&NAMESPACE_PORT.to_string()),
+            Err(RMIError::TransportError(_))
+        ));
+    }
+
+    #[test]
+    fn colocated_registry_skips_sockets() {
+        let reg = create_registry(IN_PROCESS_PORT);
+        reg.bind("near", MockRemoteObject::silent());
+        let rmt_reg = get_registry("localhost", IN_PROCESS_PORT).expect("registry is listening");
+        let stb = rmt_reg.lookup("near").expect("near should be in");
+        assert!(matches!(stb.remote.addr, Endpoint::Memory(_)));
+        let stub: MockRemoteObjectStub = stb.into();
+        let res = stub
+            .run("no sockets", vec![1, 2])
+            .expect("MockObject returns the args");
+        assert_eq!(res, vec![1, 2]);
+
+        reg.use_in_process(false);
+        let rmt_reg = get_registry("localhost", IN_PROCESS_PORT).expect("registry is listening");
+        let stb = rmt_reg.lookup("near").expect("near should be in");
+        assert!(!matches!(stb.remote.addr, Endpoint::Memory(_)));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn shared_memory_same_host() {
+        let reg = create_registry(SHM_PORT);
+        reg.use_in_process(false);
+        reg.bind("shm", MockRemoteObject::silent());
+        let rmt_reg = get_registry("localhost", SHM_PORT).expect("registry is listening");
+        let stb = rmt_reg.lookup("shm").expect("shm should be in");
+        assert!(matches!(stb.remote.addr, Endpoint::Shm(_)));
+        let stub: MockRemoteObjectStub = stb.into();
+        for size in [1, 1 << 16, 5 << 20] {
+            let res = stub
+                .run("over shm", vec![3; size])
+                .expect("MockObject returns the args");
+            assert_eq!(res, vec![3; size]);
+        }
+    }
+
+    #[test]
+    fn pre_shared_key() {
+        let key = PreSharedKey::new("swordfish");
+        let reg = create_registry_psk(PSK_PORT, key.clone());
+        reg.bind("secret", MockRemoteObject::silent());
+
+        let rmt_reg = get_registry_psk("localhost", PSK_PORT, key).expect("key is right");
+        assert_eq!(rmt_reg.list(), Ok(vec!["secret".to_string()]));
+        let stb = rmt_reg.lookup("secret").expect("secret should be in");
+        let Endpoint::Tcp(addr) = stb.remote.addr else {
+            panic!("expected a tcp endpoint, got {}", stb.remote.addr);
+        };
+        // someone without the key gets to the skeleton first
+        let mut stranger = TcpStream::connect(addr).expect("skeleton is listening");
+        send_data(marshal(&"let me in").expect("serializable"), &mut stranger)
+            .expect("the skeleton reads it");
+        assert!(receive_data(&mut stranger).is_empty());
+        let stub: MockRemoteObjectStub = stb.into();
+        let res = stub
+            .run("authenticated", vec![4; 2])
+            .expect("MockObject returns the args");
+        assert_eq!(res, vec![4; 2]);
+
+        let wrong = PreSharedKey::new("swordfisk");
+        match get_registry_psk("localhost", PSK_PORT, wrong) {
+            Err(RMIError::Unauthorized(_)) => (),
+            other => panic!("expected Unauthorized, got {other:?}"),
+        }
+        match get_registry("localhost", PSK_PORT) {
+            Err(RMIError::RegistryUnreachable(_)) => (),
+            other => panic!("clients without a key should not get in, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn silent_psk_client() {
+        let key = PreSharedKey::new("swordfish");
+        let reg = create_registry_psk(SILENT_PSK_PORT, key.clone());
+        reg.use_in_process(false);
+        // connects and never answers the challenge
+        let _silent =
+            TcpStream::connect(("127.0.0.1", SILENT_PSK_PORT)).expect("registry is listening");
+        let rmt_reg =
+            get_registry_psk("localhost", SILENT_PSK_PORT, key).expect("registry is listening");
+        rmt_reg.ping().expect("registry answers pings");
+    }
+
+    #[derive(Debug)]
+    struct Vault;
+
+    #[remote_object]
+    impl Vault {
+        #[remote]
+        fn peek(&self) -> u32 {
+            42
+        }
+        #[remote(require = "admin")]
+        fn open(&self) -> u32 {
+            7
+        }
+    }
+
+    #[derive(Debug)]
+    struct Catalog;
+
+    #[remote_object]
+    impl Catalog {
+        #[remote]
+        fn find(&self, name: &str, limit: Option<u32>) -> Vec<(String, u64)> {
+            let _ = limit;
+            vec![(name.to_string(), 1)]
+        }
+        #[remote(require = "admin")]
+        fn clear(&self) {}
+    }
+
+    #[test]
+    fn describe() {
+        let reg = create_registry_in("describe").expect("describe is free");
+        reg.bind("catalog", Catalog);
+        reg.bind_remote("elsewhere", RemoteRef::example())
+            .expect("no replicas to refuse it");
+        let rmt_reg = get_registry_in("describe").expect("registry is in this process");
+        assert_eq!(
+            rmt_reg.list(),
+            Ok(vec!["catalog".to_string(), "elsewhere".to_string()])
+        );
+
+        let info = rmt_reg.describe("catalog").expect("catalog is in");
+        assert_eq!(info.type_name.as_deref(), Some("Catalog"));
+        assert_eq!(info.addr, None);
+        assert_eq!(info.fingerprint, Some(Catalog.fingerprint()));
+        let methods: Vec<String> = info.methods.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            methods,
+            vec![
+                "fn find(name: &str, limit: Option<u32>) -> Vec<(String, u64)>",
+                "fn clear() -> ()",
+            ]
+        );
+        assert_eq!(info.methods[1].require.as_deref(), Some("admin"));
+        // lookups are what give local objects an address
+        let stub = rmt_reg.lookup("catalog").expect("catalog is in");
+        let info = rmt_reg.describe("catalog").expect("catalog is in");
+        assert_eq!(info.addr, Some(stub.remote.addr));
+        assert_eq!(info.id, stub.remote.id);
+
+        let remote = rmt_reg.describe("elsewhere").expect("bound by reference");
+        assert_eq!(remote.type_name, None);
+        assert_eq!(remote.addr, Some(RemoteRef::example().addr));
+        assert!(remote.bound_at >= info.bound_at);
+        assert_eq!(
+            rmt_reg.describe("nothing"),
+            Err(RMIError::NameNotFound("nothing".to_string()))
+        );
+        assert_ne!(Catalog.fingerprint(), Vault.fingerprint());
+    }
+
+    #[test]
+    fn dynamic_calls() {
+        use serde_cbor::Value;
+        let reg = create_registry_in("dynamic").expect("dynamic is free");
+        reg.bind("catalog", Catalog);
+        let rmt_reg = get_registry_in("dynamic").expect("registry is in this process");
+        let stub = rmt_reg.lookup_dynamic("catalog").expect("catalog is in");
+
+        let found = stub.call("find", vec![Value::Text("rrmi".to_string()), Value::Null]);
+        let row = Value::Array(vec![Value::Text("rrmi".to_string()), Value::Integer(1)]);
+        assert_eq!(found, Ok(Value::Array(vec![row])));
+        // errors from the object come back as errors
+        assert_eq!(
+            stub.call("clear", vec![]),
+            Err(RMIError::PermissionDenied("Catalog::clear".to_string()))
+        );
+        assert!(matches!(
+            stub.call("find", vec![]),
+            Err(RMIError::BadArguments(_))
+        ));
+        assert_eq!(
+            stub.call("drop", vec![]),
+            Err(RMIError::MethodNotFound("drop".to_string()))
+        );
+        #[cfg(feature = "json")]
+        {
+            use serde_json::json;
+            let by_name = stub.call_json("find", json!({"name": "rrmi", "limit": 3}));
+            assert_eq!(by_name, Ok(json!([["rrmi", 1]])));
+            let in_order = stub.call_json("find", json!(["rrmi", null]));
+            assert_eq!(in_order, Ok(json!([["rrmi", 1]])));
+            assert!(matches!(
+                stub.call_json("find", json!({"name": "rrmi", "offset": 3})),
+                Err(RMIError::BadArguments(_))
+            ));
+        }
+        // the object cannot decode a request with arguments of the wrong type
+        assert!(
+            stub.call("find", vec![Value::Integer(1), Value::Null])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn per_method_roles() {
+        let reg = create_registry_in("roles").expect("roles is free");
+        reg.bind("vault", Vault);
+        let rmt_reg = get_registry_in("roles").expect("registry is in this process");
+        let stub: VaultStub = rmt_reg.lookup("vault").expect("vault should be in").into();
+        assert_eq!(stub.peek(), Ok(42));
+        // nobody has a role without an authorizer
+        match stub.open() {
+            Err(RMIError::PermissionDenied(method)) => assert_eq!(method, "Vault::open"),
+            other => panic!("expected PermissionDenied, got {other:?}"),
+        }
+        // swapping the authorizer reaches skeletons already serving
+        reg.set_authorizer(|caller: &Principal, _: &str, _: &str, role: Option<&str>| {
+            role.is_none() || caller.local
+        });
+        assert_eq!(stub.open(), Ok(7));
+        // called directly there is no caller to check
+        assert_eq!(Vault.open(), 7);
+    }
+
+    #[derive(Debug)]
+    struct Caller;
+
+    #[remote_object]
+    impl Caller {
+        #[remote]
+        fn context(&self) -> Option<(u64, u64, bool)> {
+            call_context().map(|c| (c.connection_id, c.request_id, c.caller.local))
+        }
+        #[remote]
+        fn trace(&self) -> Option<String> {
+            call_context().and_then(|c| c.trace).map(|t| t.to_string())
+        }
+        #[remote]
+        fn headers(&self) -> (Headers, bool) {
+            let context = call_context().expect("served by a skeleton");
+            (context.metadata, context.deadline.is_some())
+        }
+    }
+
+    #[test]
+    fn call_context_per_connection() {
+        let reg = create_registry_in("context").expect("context is free");
+        reg.bind("caller", Caller);
+        let rmt_reg = get_registry_in("context").expect("registry is in this process");
+        let first: CallerStub = rmt_reg.lookup("caller").expect("caller is in").into();
+        let second: CallerStub = rmt_reg.lookup("caller").expect("caller is in").into();
+
+        let (conn, req, local) = first
+            .context()
+            .expect("call")
+            .expect("served by a skeleton");
+        assert_eq!((req, local), (1, true));
+        assert_eq!(first.context(), Ok(Some((conn, 2, true))));
+        let (other, req, _) = second
+            .context()
+            .expect("call")
+            .expect("served by a skeleton");
+        assert_ne!(other, conn);
+        assert_eq!(req, 1);
+        assert_eq!(Caller.context(), None);
+    }
+
+    #[test]
+    fn request_headers() {
+        let reg = create_registry_in("headers").expect("headers is free");
+        reg.bind("caller", Caller);
+        let rmt_reg = get_registry_in("headers").expect("registry is in this process");
+        let mut stub: CallerStub = rmt_reg.lookup("caller").expect("caller is in").into();
+        stub.set_header("tenant", "liacs");
+
+        let once = Headers::from([
+            ("trace".to_string(), "abc".to_string()),
+            (TIMEOUT_HEADER.to_string(), "500".to_string()),
+        ]);
+        let (headers, deadline) = stub.call_headers(once).headers().expect("call");
+        assert_eq!(headers.get("tenant").map(String::as_str), Some("liacs"));
+        assert_eq!(headers.get("trace").map(String::as_str), Some("abc"));
+        assert!(deadline);
+        // per-call headers are gone on the next call, defaults stay
+        let (mut headers, deadline) = stub.headers().expect("call");
+        assert!(headers.remove(TRACEPARENT_HEADER).is_some());
+        assert_eq!(
+            headers,
+            Headers::from([("tenant".to_string(), "liacs".to_string())])
+        );
+        assert!(!deadline);
+
+        // the largest timeout a caller can send does not take the connection down
+        let forever = Headers::from([(TIMEOUT_HEADER.to_string(), u64::MAX.to_string())]);
+        assert!(stub.call_headers(forever).headers().is_ok());
+        assert!(stub.headers().is_ok());
+    }
+
+    /// Logs the calls it sees and tags them with a header.
+    struct Recorder {
+        tag: &'static str,
+        log: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Interceptor for Recorder {
+        fn before_call(&self, call: &mut Call) -> RMIResult<()> {
+            let entry = format!("{} before {}::{}", self.tag, call.object, call.method);
+            self.log.lock().unwrap().push(entry);
+            call.headers.insert("via".to_string(), self.tag.to_string());
+            Ok(())
+        }
+        fn after_call(&self, call: &Call, outcome: &Outcome) {
+            if outcome.error.is_none() {
+                assert!(call.request_size > 0 && outcome.response_size > 0);
+            }
+            let entry = format!(
+                "{} after {} ok={}",
+                self.tag,
+                call.method,
+                outcome.error.is_none()
+            );
+            self.log.lock().unwrap().push(entry);
+        }
+    }
+
+    struct Refuse;
+
+    impl Interceptor for Refuse {
+        fn before_call(&self, call: &mut Call) -> RMIResult<()> {
+            Err(RMIError::PermissionDenied(call.method.to_string()))
+        }
+    }
+
+    #[test]
+    fn interceptor_chains() {
+        let log = Arc::new(Mutex::new(vec![]));
+        let recorder = |tag| Recorder {
+            tag,
+            log: Arc::clone(&log),
+        };
+        let reg = create_registry_in("intercept").expect("intercept is free");
+        reg.add_interceptor(recorder("server"));
+        reg.bind("caller", Caller);
+        let rmt_reg = get_registry_in("intercept").expect("registry is in this process");
+        let mut stub: CallerStub = rmt_reg.lookup("caller").expect("caller is in").into();
+        stub.add_interceptor(recorder("first"));
+        stub.add_interceptor(recorder("second"));
+
+        let (headers, _) = stub.headers().expect("call");
+        // client interceptors run before the request is sent, the last one to write wins
+        assert_eq!(headers.get("via").map(String::as_str), Some("second"));
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec![
+                "server before Registry::ping",
+                "server after ping ok=true",
+                "server before Registry::lookup",
+                "server after lookup ok=true",
+                "first before Caller::headers",
+                "second before Caller::headers",
+                "server before Caller::headers",
+                "server after headers ok=true",
+                "second after headers ok=true",
+                "first after headers ok=true",
+            ]
+        );
+
+        log.lock().unwrap().clear();
+        stub.add_interceptor(Refuse);
+        match stub.context() {
+            Err(RMIError::PermissionDenied(method)) => assert_eq!(method, "context"),
+            other => panic!("expected the interceptor to refuse, got {other:?}"),
+        }
+        // the server never heard of it
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec![
+                "first before Caller::context",
+                "second before Caller::context",
+                "second after context ok=false",
+                "first after context ok=false",
+            ]
+        );
+    }
+
+    #[test]
+    fn trace_propagation() {
+        let reg = create_registry_in("trace").expect("trace is free");
+        reg.bind("caller", Caller);
+        let rmt_reg = get_registry_in("trace").expect("registry is in this process");
+        let stub: CallerStub = rmt_reg.lookup("caller").expect("caller is in").into();
+
+        let parent = TraceContext::root();
+        let traceparent = Headers::from([(TRACEPARENT_HEADER.to_string(), parent.to_string())]);
+        let served = stub.call_headers(traceparent).trace().expect("call");
+        let served: TraceContext = served
+            .expect("served by a skeleton")
+            .parse()
+            .expect("valid");
+        assert_eq!(served.trace_id, parent.trace_id);
+        assert_ne!(served.span_id, parent.span_id);
+        // without one the stub starts a trace of its own
+        let other: TraceContext = stub
+            .trace()
+            .expect("call")
+            .expect("traced")
+            .parse()
+            .expect("valid");
+        assert_ne!(other.trace_id, parent.trace_id);
+    }
+
+    #[test]
+    fn remote_bind_policy() {
+        let reg = create_registry(AUTH_PORT);
+        reg.use_in_process(false);
+        let exported = Skeleton::new(Arc::new(MockRemoteObject::silent()));
+        let port = exported.export().expect("exported object listens");
+        let remote = RemoteRef::new(SocketAddr::from(([127, 0, 0, 1], port)), 0);
+
+        let rmt_reg = get_registry("localhost", AUTH_PORT).expect("registry is listening");
+        rmt_reg
+            .bind("exported", remote)
+            .expect("callers on this host may bind");
+        assert_eq!(rmt_reg.list(), Ok(vec!["exported".to_string()]));
+        // every lookup gets the same address, the exported skeleton takes many clients
+        for i in 0..2 {
+            let stub: MockRemoteObjectStub =
+                rmt_reg.lookup("exported").expect("exported is in").into();
+            assert_eq!(stub.run("exported", vec![i; 2]), Ok(vec![i; 2]));
+        }
+
+        let stranger = Principal {
+            peer_addr: Some(SocketAddr::from(([192, 0, 2, 1], 40000))),
+            local: false,
+            name: Some("ops".to_string()),
+        };
+        match reg.authorize_mutation(&stranger, "bind") {
+            Err(RMIError::PermissionDenied(_)) => (),
+            other => panic!("other hosts may not bind by default, got {other:?}"),
+        }
+        reg.set_authorizer(Roles::new().grant("ops", REGISTRY_WRITE_ROLE));
+        assert_eq!(reg.authorize_mutation(&stranger, "unbind"), Ok(()));
+
+        rmt_reg.unbind("exported").expect("exported was bound");
+        match rmt_reg.unbind("exported") {
+            Err(RMIError::NameNotFound(_)) => (),
+            other => panic!("expected NameNotFound, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn get_registry_errors() {
+        match get_registry("no-such-host.invalid", UNREACHABLE_PORT) {
+            Err(RMIError::NameResolution(_)) => (),
+            other => panic!("expected NameResolution, got {other:?}"),
+        }
+        match get_registry("localhost", UNREACHABLE_PORT) {
+            Err(RMIError::RegistryUnreachable(_)) => (),
+            other => panic!("expected RegistryUnreachable, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn get_registry_ping() {
+        let _reg = create_registry(PING_PORT);
+        let rmt_reg = get_registry("localhost", PING_PORT).expect("registry is listening");
+        rmt_reg.ping().expect("registry answers pings");
+    }
+
+    #[test]
+    fn silent_client() {
+        let reg = create_registry(SILENT_PORT);
+        reg.use_in_process(false);
+        // connects and never sends its request
+        let _silent = TcpStream::connect(("127.0.0.1", SILENT_PORT)).expect("registry listens");
+        let rmt_reg = get_registry("localhost", SILENT_PORT).expect("registry is listening");
+        rmt_reg.ping().expect("registry answers pings");
+    }
+
     #[test]
     fn populate_clear() {
         let reg = create_registry(POPUL_PORT);
@@ -56,7 +687,7 @@ mod tests {
             });
         }
 
-        std::thread::sleep(time::Duration::from_millis(100));
+        pool.join();
         let num_objects = reg
             .lock()
             .expect("should be able to lock")
@@ -79,20 +710,16 @@ mod tests {
             });
         }
 
-        std::thread::sleep(time::Duration::from_millis(100));
+        pool.join();
         let names = reg.lock().expect("should be able to lock").list();
 
-        match names {
-            Result::Err(RMIError::EmptyRegistry()) => (),
-            _ => panic!(),
-        }
-        // assert_eq!(names.err(), Option::Some(RMIError::EmptyRegistry()));
+        assert_eq!(names, Ok(vec![]));
     }
 
     #[test]
     fn bind_lookup_list_remove() {
         let reg = create_registry(BIND_PORT);
-        let rmt_reg = get_registry("localhost", BIND_PORT);
+        let rmt_reg = get_registry("localhost", BIND_PORT).expect("registry is listening");
 
         let verbose = MockRemoteObject::verbose();
         let silent = MockRemoteObject::silent();
@@ -112,11 +739,742 @@ mod tests {
         eprintln!("local: {:?} vs remote: {:?}", l, l_rmt);
         reg.remove("silent").expect("still in");
 
-        match reg.list() {
-            Ok(_) => panic!("should not have any other objects"),
-            Err(RMIError::EmptyRegistry()) => (),
-            Err(_) => panic!("should return EmptyRegistry error"),
+        assert_eq!(reg.list(), Ok(vec![]));
+        assert_eq!(rmt_reg.list(), Ok(vec![]));
+    }
+
+    #[test]
+    fn urls() {
+        let url: RmiUrl = "rrmi://node1:2000/jobs/worker-3/counter"
+            .parse()
+            .expect("valid url");
+        assert_eq!(url, RmiUrl::new("node1", 2000, "jobs/worker-3/counter"));
+        assert_eq!(url.to_string(), "rrmi://node1:2000/jobs/worker-3/counter");
+        let registry: RmiUrl = "//node1".parse().expect("valid url");
+        assert_eq!(registry, RmiUrl::new("node1", 1099, ""));
+        let v6: RmiUrl = "rrmi://[::1]/counter".parse().expect("valid url");
+        assert_eq!(v6.to_string(), "rrmi://[::1]:1099/counter");
+
+        for bad in [
+            "node1/counter",
+            "rrmi://",
+            "rrmi://node1:x/a",
+            "rrmi://node1/a/",
+        ] {
+            assert!(bad.parse::<RmiUrl>().is_err(), "{bad} should not parse");
+        }
+        assert!(check_name("jobs/worker-3/counter").is_ok());
+        for bad in ["", "/jobs", "jobs/", "jobs//counter", "jobs\ncounter"] {
+            assert!(
+                matches!(check_name(bad), Err(RMIError::BadArguments(_))),
+                "{bad:?} should be rejected"
+            );
+        }
+        assert!(matches!(
+            Naming::lookup("rrmi://localhost:1099/"),
+            Err(RMIError::BadArguments(_))
+        ));
+    }
+
+    #[test]
+    fn hierarchical_names() {
+        let reg = create_registry_in("hierarchy").expect("hierarchy is free");
+        reg.bind("jobs/worker-3/counter", MockRemoteObject::silent());
+        reg.bind("jobs/worker-4/counter", MockRemoteObject::silent());
+        reg.bind("jobsboard", MockRemoteObject::silent());
+        let rmt_reg = get_registry_in("hierarchy").expect("registry is in this process");
+
+        assert_eq!(
+            rmt_reg.list_under("jobs"),
+            Ok(vec![
+                "jobs/worker-3/counter".to_string(),
+                "jobs/worker-4/counter".to_string()
+            ])
+        );
+        assert_eq!(
+            rmt_reg.list_under("jobs/worker-4"),
+            Ok(vec!["jobs/worker-4/counter".to_string()])
+        );
+        assert_eq!(rmt_reg.list_under("job"), Ok(vec![]));
+        assert_eq!(rmt_reg.list_under(""), rmt_reg.list());
+    }
+
+    #[test]
+    fn federation() {
+        let front = create_registry(FRONT_PORT);
+        let peer = create_registry(PEER_PORT);
+        peer.bind("jobs/counter", MockRemoteObject::silent());
+        let to_peer = get_registry("localhost", PEER_PORT).expect("peer is listening");
+        front.add_peer("node2", to_peer);
+
+        let url = format!("rrmi://localhost:{FRONT_PORT}/node2/jobs/counter");
+        let stub: MockRemoteObjectStub = Naming::lookup(&url).expect("forwarded to node2").into();
+        assert_eq!(stub.run("federated", vec![3; 2]), Ok(vec![3; 2]));
+        let info = Naming::describe(&url).expect("forwarded to node2");
+        assert_eq!(info.name, "node2/jobs/counter");
+
+        // only names under the prefix are forwarded, without it
+        let rmt_front = get_registry("localhost", FRONT_PORT).expect("front is listening");
+        assert!(matches!(
+            rmt_front.lookup("jobs/counter"),
+            Err(RMIError::NameNotFound(_))
+        ));
+        assert!(matches!(
+            rmt_front.lookup("node2/jobs/gone"),
+            Err(RMIError::NameNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn federation_slow_peer() {
+        let [front_port, peer_port] = SLOW_PEER_PORTS;
+        let front = create_registry(front_port);
+        front.use_in_process(false);
+        // takes connections and never answers them
+        let _peer = TcpListener::bind(("127.0.0.1", peer_port)).expect("peer port is free");
+        let to_peer = RegistryStub::new(RemoteRef::new(
+            SocketAddr::from(([127, 0, 0, 1], peer_port)),
+            0,
+        ));
+        front.add_peer("node2", to_peer);
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        thread::spawn(move || {
+            let rmt_front = get_registry("localhost", front_port).expect("front is listening");
+            let _ = tx.send(rmt_front.lookup("node2/jobs/counter").map(|_| ()));
+        });
+        thread::sleep(Duration::from_millis(200));
+        // others are served while the lookup waits on the peer
+        let started = std::time::Instant::now();
+        let rmt_front = get_registry("localhost", front_port).expect("front is listening");
+        assert_eq!(rmt_front.list(), Ok(vec![]));
+        assert!(started.elapsed() < Duration::from_secs(1));
+        let forwarded = rx
+            .recv_timeout(Duration::from_secs(10))
+            .expect("forwarding times out");
+        assert!(forwarded.is_err());
+    }
+
+    /// Polls `done` for up to two seconds, for what happens in the background.
+    fn eventually(done: impl Fn() -> bool) -> bool {
+        for _ in 0..200 {
+            if done() {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        done()
+    }
+
+    #[test]
+    fn replication() {
+        let replica = |port: u16| {
+            let reg = create_registry(port);
+            reg.use_in_process(false);
+            reg
+        };
+        let members: Vec<RegistryStub> = REPLICA_PORTS
+            .iter()
+            .map(|&port| {
+                RegistryStub::new(RemoteRef::new(SocketAddr::from(([127, 0, 0, 1], port)), 0))
+            })
+            .collect();
+        let mut replicas: Vec<_> = REPLICA_PORTS.iter().map(|&port| replica(port)).collect();
+        for (me, reg) in replicas.iter().enumerate() {
+            reg.use_replicas(members.clone(), me).expect("valid rank");
+        }
+        let exported = Skeleton::new(Arc::new(MockRemoteObject::silent()));
+        let port = exported.export().expect("exported object listens");
+        let object = RemoteRef::new(SocketAddr::from(([127, 0, 0, 1], port)), 0);
+
+        // binds made at a follower go through the leader and reach every replica
+        members[2]
+            .bind("jobs/counter", object.clone())
+            .expect("bound at the leader");
+        let counter = vec!["jobs/counter".to_string()];
+        assert_eq!(replicas[2].list(), Ok(counter.clone()));
+        assert_eq!(replicas[0].list(), Ok(counter.clone()));
+        assert!(eventually(|| replicas[1].list() == Ok(counter.clone())));
+
+        let hosts: Vec<(&str, u16)> = REPLICA_PORTS
+            .iter()
+            .map(|&port| ("localhost", port))
+            .collect();
+        let client = get_registry_replicas(&hosts).expect("replicas are listening");
+        let leader = replicas.remove(0);
+        assert!(leader.shutdown(Duration::from_secs(1)).is_clean());
+        drop(leader);
+
+        // the client fails over, the next replica leads
+        let stub: MockRemoteObjectStub = client.lookup("jobs/counter").expect("replicated").into();
+        assert_eq!(stub.run("failed over", vec![5; 2]), Ok(vec![5; 2]));
+        client
+            .bind("jobs/other", object)
+            .expect("bound at the new leader");
+        client.unbind("jobs/counter").expect("still bound");
+        let other = vec!["jobs/other".to_string()];
+        assert!(eventually(|| replicas
+            .iter()
+            .all(|reg| reg.list() == Ok(other.clone()))));
+
+        // a replica coming back catches up first
+        let back = replica(REPLICA_PORTS[0]);
+        back.use_replicas(members, 0).expect("valid rank");
+        assert_eq!(back.list(), Ok(other));
+    }
+
+    /// Passes the connections to `port` on to `to` while `up`, and closes `port` otherwise.
+    fn relay(port: u16, to: u16, up: Arc<AtomicBool>) {
+        thread::spawn(move || {
+            loop {
+                while !up.load(Ordering::SeqCst) {
+                    thread::sleep(Duration::from_millis(10));
+                }
+                let listener = TcpListener::bind(("127.0.0.1", port)).expect("relay port is free");
+                for stream in listener.incoming().flatten() {
+                    if !up.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let Ok(target) = TcpStream::connect(("127.0.0.1", to)) else {
+                        continue;
+                    };
+                    let pipe = |mut from: TcpStream, mut to: TcpStream| {
+                        thread::spawn(move || {
+                            let _ = std::io::copy(&mut from, &mut to);
+                            let _ = to.shutdown(std::net::Shutdown::Write);
+                        });
+                    };
+                    pipe(
+                        stream.try_clone().expect("relay stream clones"),
+                        target.try_clone().expect("relay stream clones"),
+                    );
+                    pipe(target, stream);
+                }
+            }
+        });
+    }
+
+    #[test]
+    fn replication_partition() {
+        // the replicas reach each other through relays the test cuts
+        let [zero, one, to_zero, to_one] = PARTITION_PORTS;
+        let up = Arc::new(AtomicBool::new(true));
+        relay(to_zero, zero, Arc::clone(&up));
+        relay(to_one, one, Arc::clone(&up));
+        let relayed = |relaying: bool| {
+            for port in [to_zero, to_one] {
+                let open = || TcpStream::connect(("127.0.0.1", port)).is_ok() == relaying;
+                assert!(eventually(open));
+            }
+        };
+        relayed(true);
+        let stub = |port: u16| {
+            RegistryStub::new(RemoteRef::new(SocketAddr::from(([127, 0, 0, 1], port)), 0))
+        };
+        let replica = |port: u16| {
+            let reg = create_registry(port);
+            reg.use_in_process(false);
+            reg
+        };
+        let first = replica(zero);
+        first
+            .use_replicas(vec![stub(zero), stub(to_one)], 0)
+            .expect("valid rank");
+        let second = replica(one);
+        second
+            .use_replicas(vec![stub(to_zero), stub(one)], 1)
+            .expect("valid rank");
+        let exported = Skeleton::new(Arc::new(MockRemoteObject::silent()));
+        let port = exported.export().expect("exported object listens");
+        let object = RemoteRef::new(SocketAddr::from(([127, 0, 0, 1], port)), 0);
+        let names = |names: &[&str]| Ok(names.iter().map(|n| n.to_string()).collect());
+
+        second
+            .bind_remote("jobs/both", object.clone())
+            .expect("bound at the leader");
+        assert_eq!(first.list(), names(&["jobs/both"]));
+        assert_eq!(second.list(), names(&["jobs/both"]));
+
+        // cut off, both sides lead
+        up.store(false, Ordering::SeqCst);
+        for port in [to_zero, to_one] {
+            assert!(eventually(
+                || TcpStream::connect(("127.0.0.1", port)).is_err()
+            ));
+        }
+        first
+            .bind_remote("jobs/zero", object.clone())
+            .expect("the first replica leads");
+        second
+            .bind_remote("jobs/one", object.clone())
+            .expect("the second replica leads its side");
+        assert_eq!(first.list(), names(&["jobs/both", "jobs/zero"]));
+        assert_eq!(second.list(), names(&["jobs/both", "jobs/one"]));
+
+        // back together, the first replica's log wins and the other side's change is dropped
+        up.store(true, Ordering::SeqCst);
+        for port in [to_zero, to_one] {
+            assert!(eventually(
+                || TcpStream::connect(("127.0.0.1", port)).is_ok()
+            ));
+        }
+        second
+            .bind_remote("jobs/healed", object)
+            .expect("bound at the leader");
+        let agreed = names(&["jobs/both", "jobs/healed", "jobs/zero"]);
+        assert_eq!(second.list(), agreed);
+        assert!(eventually(|| first.list() == agreed));
+    }
+
+    #[test]
+    fn watch() {
+        let reg = create_registry(WATCH_PORT);
+        reg.use_in_process(false);
+        let rmt_reg = get_registry("localhost", WATCH_PORT).expect("registry is listening");
+        let (events, received) = std::sync::mpsc::channel();
+        let watch = rmt_reg
+            .watch("jobs", move |event| drop(events.send(event)))
+            .expect("registry can call back");
+        let next = || received.recv_timeout(Duration::from_secs(1));
+        let name = "jobs/worker-3".to_string();
+
+        reg.bind("jobs/worker-3", MockRemoteObject::silent());
+        assert_eq!(next(), Ok(WatchEvent::Bound { name: name.clone() }));
+        let exported = Skeleton::new(Arc::new(MockRemoteObject::silent()));
+        let port = exported.export().expect("exported object listens");
+        let remote = RemoteRef::new(SocketAddr::from(([127, 0, 0, 1], port)), 0);
+        rmt_reg
+            .bind("jobs/worker-3", remote.clone())
+            .expect("local binder");
+        assert_eq!(next(), Ok(WatchEvent::Rebound { name: name.clone() }));
+        // outside the prefix
+        reg.bind("jobsboard", MockRemoteObject::silent());
+        rmt_reg.unbind("jobs/worker-3").expect("still bound");
+        assert_eq!(next(), Ok(WatchEvent::Unbound { name }));
+
+        // ids are not handed out in order, the one after is nobody's watch
+        assert!(matches!(
+            rmt_reg.unwatch(watch.id().wrapping_add(1)),
+            Err(RMIError::BadArguments(_))
+        ));
+        assert_eq!(rmt_reg.unwatch(watch.id()), Ok(()));
+        reg.bind("jobs/worker-4", MockRemoteObject::silent());
+        assert!(received.recv_timeout(Duration::from_millis(200)).is_err());
+        drop(watch);
+
+        // a client on another host only gets events sent back to it
+        let stranger = Principal {
+            peer_addr: Some(SocketAddr::from(([192, 0, 2, 1], 40000))),
+            local: false,
+            name: None,
         };
+        let back = RemoteRef::new(SocketAddr::from(([192, 0, 2, 1], 40001)), 0);
+        assert_eq!(check_watcher(&stranger, &back), Ok(()));
+        let elsewhere = RemoteRef::new(SocketAddr::from(([192, 0, 2, 7], 40001)), 0);
+        assert!(matches!(
+            check_watcher(&stranger, &elsewhere),
+            Err(RMIError::PermissionDenied(_))
+        ));
+        assert!(matches!(
+            reg.authorize_mutation(&stranger, "watch"),
+            Err(RMIError::PermissionDenied(_))
+        ));
+    }
+
+    #[test]
+    fn leases() {
+        let reg = create_registry(LEASE_PORT);
+        reg.use_in_process(false);
+        let rmt_reg = get_registry("localhost", LEASE_PORT).expect("registry is listening");
+        let (events, received) = std::sync::mpsc::channel();
+        let _watch = rmt_reg
+            .watch("", move |event| drop(events.send(event)))
+            .expect("registry can call back");
+        let next = || received.recv_timeout(Duration::from_secs(2));
+        let exported = Skeleton::new(Arc::new(MockRemoteObject::silent()));
+        let port = exported.export().expect("exported object listens");
+        let remote = RemoteRef::new(SocketAddr::from(([127, 0, 0, 1], port)), 0);
+        let ttl = Duration::from_millis(300);
+        let worker = "worker".to_string();
+
+        let lease = rmt_reg
+            .bind_with_lease("worker", remote.clone(), ttl)
+            .expect("local binder");
+        assert_eq!(
+            next(),
+            Ok(WatchEvent::Bound {
+                name: worker.clone()
+            })
+        );
+        // a binder that stops renewing
+        reg.bind_remote_with_lease("crashed", remote.clone(), ttl)
+            .expect("valid lease");
+        assert_eq!(
+            next(),
+            Ok(WatchEvent::Bound {
+                name: "crashed".to_string()
+            })
+        );
+        assert_eq!(
+            next(),
+            Ok(WatchEvent::Expired {
+                name: "crashed".to_string()
+            })
+        );
+        assert_eq!(reg.list(), Ok(vec![worker.clone()]));
+        assert!(matches!(
+            rmt_reg.renew("crashed", ttl),
+            Err(RMIError::NameNotFound(_))
+        ));
+        assert!(matches!(
+            rmt_reg.renew("worker", Duration::ZERO),
+            Err(RMIError::BadArguments(_))
+        ));
+        assert!(matches!(
+            rmt_reg.renew("worker", Duration::MAX),
+            Err(RMIError::BadArguments(_))
+        ));
+
+        // the lease binds the name again when the registry lost it
+        reg.unbind("worker").expect("still bound");
+        assert_eq!(
+            next(),
+            Ok(WatchEvent::Unbound {
+                name: worker.clone()
+            })
+        );
+        assert_eq!(
+            next(),
+            Ok(WatchEvent::Bound {
+                name: worker.clone()
+            })
+        );
+        drop(lease);
+        assert_eq!(
+            next(),
+            Ok(WatchEvent::Unbound {
+                name: worker.clone()
+            })
+        );
+        assert_eq!(reg.list(), Ok(vec![]));
+
+        // a name bound to another remote on purpose stays so
+        let lease = rmt_reg
+            .bind_with_lease("worker", remote.clone(), ttl)
+            .expect("local binder");
+        let other = RemoteRef::new(SocketAddr::from(([127, 0, 0, 1], port)), 1);
+        reg.bind_remote("worker", other.clone())
+            .expect("no replicas to refuse it");
+        std::thread::sleep(ttl * 3);
+        assert_eq!(reg.lookup("worker"), Ok(other.clone()));
+        drop(lease);
+        assert_eq!(reg.lookup("worker"), Ok(other));
+    }
+
+    #[derive(Debug)]
+    struct Worker(u8);
+
+    #[remote_object]
+    impl Worker {
+        #[remote]
+        fn who(&self) -> u8 {
+            self.0
+        }
+    }
+
+    #[test]
+    fn groups() {
+        let reg = create_registry_in("groups").expect("groups is free");
+        let mut ids = vec![];
+        for n in 1..=3 {
+            let (_, id) = reg.join("workers", Worker(n)).expect("workers is a group");
+            ids.push(id);
+        }
+        reg.bind("single", Worker(0));
+        assert!(matches!(
+            reg.join("single", Worker(4)),
+            Err(RMIError::BadArguments(_))
+        ));
+        let rmt_reg = get_registry_in("groups").expect("registry is in this process");
+        assert_eq!(
+            rmt_reg.list(),
+            Ok(vec!["single".to_string(), "workers".to_string()])
+        );
+        let info = rmt_reg.describe("workers").expect("workers is bound");
+        assert_eq!(info.type_name.as_deref(), Some("Worker"));
+
+        // plain lookups hand out the members in turn
+        let who = |stub: Stub| WorkerStub::from(stub).who();
+        let turns: Vec<_> = (0..4)
+            .map(|_| who(rmt_reg.lookup("workers").expect("workers is bound")))
+            .collect();
+        assert_eq!(turns, vec![Ok(1), Ok(2), Ok(3), Ok(1)]);
+
+        let group: GroupStub<WorkerStub> = rmt_reg.lookup_group("workers").expect("bound");
+        assert_eq!(group.len(), 3);
+        let calls: Vec<_> = (0..4).map(|_| group.call(|w| w.who())).collect();
+        assert_eq!(calls, vec![Ok(1), Ok(2), Ok(3), Ok(1)]);
+        let group = group.with_balance(Balance::Random);
+        for _ in 0..10 {
+            assert!(matches!(group.call(|w| w.who()), Ok(1..=3)));
+        }
+        // a single object is a group of one
+        let single: GroupStub<WorkerStub> = rmt_reg.lookup_group("single").expect("bound");
+        assert_eq!(single.call(|w| w.who()), Ok(0));
+
+        reg.leave("workers", ids[1]).expect("member of workers");
+        assert_eq!(
+            reg.leave("workers", ids[1]),
+            Err(RMIError::ObjectNotFound(ids[1]))
+        );
+        let group: GroupStub<WorkerStub> = rmt_reg.lookup_group("workers").expect("bound");
+        let calls: Vec<_> = (0..2).map(|_| group.call(|w| w.who())).collect();
+        assert_eq!(calls, vec![Ok(1), Ok(3)]);
+
+        rmt_reg.unbind("workers").expect("workers is bound");
+        assert_eq!(rmt_reg.list(), Ok(vec!["single".to_string()]));
+        assert!(matches!(
+            rmt_reg.lookup_group::<WorkerStub>("workers"),
+            Err(RMIError::NameNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn group_drops_failing_members() {
+        let reg = create_registry(GROUP_PORT);
+        reg.use_in_process(false);
+        let rmt_reg = get_registry("localhost", GROUP_PORT).expect("registry is listening");
+        let (events, received) = std::sync::mpsc::channel();
+        let _watch = rmt_reg
+            .watch("", move |event| drop(events.send(event)))
+            .expect("registry can call back");
+        let next = || received.recv_timeout(Duration::from_secs(2));
+        let workers = "workers".to_string();
+
+        let exported = Skeleton::new(Arc::new(Worker(1)));
+        let port = exported.export().expect("exported object listens");
+        let alive = RemoteRef::new(SocketAddr::from(([127, 0, 0, 1], port)), 0);
+        // accepts connections and closes them without an answer
+        let flaky = TcpListener::bind("127.0.0.1:0").expect("free port");
+        let flaky_addr = flaky.local_addr().expect("bound listener");
+        thread::spawn(move || {
+            for stream in flaky.incoming() {
+                let mut stream = stream.expect("connection");
+                let _ = stream.read(&mut [0; 64]);
+            }
+        });
+        let gone = RemoteRef::new(SocketAddr::from(([127, 0, 0, 1], UNREACHABLE_PORT)), 0);
+
+        rmt_reg.join("workers", alive).expect("local binder");
+        assert_eq!(
+            next(),
+            Ok(WatchEvent::Bound {
+                name: workers.clone()
+            })
+        );
+        rmt_reg
+            .join("workers", RemoteRef::new(flaky_addr, 0))
+            .expect("local binder");
+        let id = rmt_reg.join("workers", gone).expect("local binder");
+        assert_eq!(
+            next(),
+            Ok(WatchEvent::Rebound {
+                name: workers.clone()
+            })
+        );
+        assert_eq!(
+            next(),
+            Ok(WatchEvent::Rebound {
+                name: workers.clone()
+            })
+        );
+
+        // the member nothing listens for is left out
+        let group: GroupStub<WorkerStub> = rmt_reg
+            .lookup_group("workers")
+            .expect("workers is bound")
+            .with_balance(Balance::LeastOutstanding)
+            .with_max_errors(2);
+        assert_eq!(group.len(), 2);
+        let calls: Vec<_> = (0..6).map(|_| group.call(|w| w.who())).collect();
+        assert_eq!(calls.iter().filter(|call| call.is_err()).count(), 2);
+        assert_eq!(group.len(), 1);
+        for _ in 0..3 {
+            assert_eq!(group.call(|w| w.who()), Ok(1));
+        }
+
+        rmt_reg.leave("workers", id).expect("member of workers");
+        assert_eq!(
+            next(),
+            Ok(WatchEvent::Rebound {
+                name: workers.clone()
+            })
+        );
+        rmt_reg.unbind("workers").expect("workers is bound");
+        assert_eq!(next(), Ok(WatchEvent::Unbound { name: workers }));
+    }
+
+    #[test]
+    fn journal() {
+        let path = std::env::temp_dir().join(format!("rrmi-journal-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let exported = Skeleton::new(Arc::new(MockRemoteObject::silent()));
+        let port = exported.export().expect("exported object listens");
+        let alive = RemoteRef::new(SocketAddr::from(([127, 0, 0, 1], port)), 0);
+        let gone = RemoteRef::new(SocketAddr::from(([127, 0, 0, 1], UNREACHABLE_PORT)), 0);
+
+        let reg = create_registry_in("journal").expect("journal is free");
+        assert_eq!(reg.use_journal(&path), Ok(vec![]));
+        reg.bind_remote("alive", alive.clone())
+            .expect("no replicas to refuse it");
+        reg.bind_remote("gone", gone)
+            .expect("no replicas to refuse it");
+        reg.bind_remote("unbound", alive.clone())
+            .expect("no replicas to refuse it");
+        reg.unbind("unbound").expect("unbound was bound");
+        reg.bind_remote("replaced", alive.clone())
+            .expect("no replicas to refuse it");
+        reg.bind("replaced", MockRemoteObject::silent());
+        drop(reg);
+
+        // a crash in the middle of writing leaves half an entry
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .expect("journal was written");
+        std::io::Write::write_all(&mut file, &[0, 0, 0, 9, 0xa1]).expect("journal is writable");
+
+        let reg = create_registry_in("journal").expect("journal is free");
+        reg.bind("local", MockRemoteObject::silent());
+        assert_eq!(reg.use_journal(&path), Ok(vec!["alive".to_string()]));
+        let rmt_reg = get_registry_in("journal").expect("registry is in this process");
+        assert_eq!(
+            rmt_reg.list(),
+            Ok(vec!["alive".to_string(), "local".to_string()])
+        );
+        let stub: MockRemoteObjectStub = rmt_reg.lookup("alive").expect("alive is back").into();
+        assert_eq!(stub.run("restored", vec![7; 2]), Ok(vec![7; 2]));
+
+        // the journal is rewritten with what was restored, the old one is kept
+        let (recorded, complete) = Journal::replay(&path).expect("journal is readable");
+        assert_eq!(recorded.keys().collect::<Vec<_>>(), vec!["alive"]);
+        assert!(complete);
+        let backup = path.with_extension("bak");
+        assert!(Journal::replay(&backup).is_ok_and(|(_, complete)| !complete));
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&backup);
+    }
+
+    #[test]
+    fn journal_corrupt() {
+        let path = std::env::temp_dir().join(format!("rrmi-corrupt-{}", std::process::id()));
+        let gone = RemoteRef::new(SocketAddr::from(([127, 0, 0, 1], UNREACHABLE_PORT)), 0);
+        let before = BTreeMap::from([("before".to_string(), gone.clone())]);
+        let mut journal = Journal::create(&path, &before).expect("temp dir is writable");
+        // an entry that does not decode in the middle of the file
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .expect("journal was written");
+        std::io::Write::write_all(&mut file, &[0, 0, 0, 3, 0xff, 0xff, 0xff])
+            .expect("journal is writable");
+        journal.bind("after", &gone).expect("journal is writable");
+        assert_eq!(Journal::replay(&path), Ok((before.clone(), false)));
+
+        // what follows is kept aside rather than compacted away
+        let reg = create_registry_in("corrupt").expect("corrupt is free");
+        assert_eq!(reg.use_journal(&path), Ok(vec![]));
+        let backup = path.with_extension("bak");
+        let (_, complete) = Journal::replay(&backup).expect("backup is readable");
+        assert!(!complete);
+        let len = std::fs::metadata(&backup).expect("backup is there").len();
+        assert!(len > std::fs::metadata(&path).expect("journal is there").len());
+
+        // a length larger than the file is not allocated
+        std::fs::write(&path, [0xff, 0xff, 0xff, 0xff, 1, 2, 3]).expect("temp dir is writable");
+        assert_eq!(Journal::replay(&path), Ok((BTreeMap::new(), false)));
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&backup);
+    }
+
+    #[derive(Debug)]
+    struct Sleeper;
+
+    #[remote_object]
+    impl Sleeper {
+        #[remote]
+        fn nap(&self, ms: u64) -> u64 {
+            thread::sleep(Duration::from_millis(ms));
+            ms
+        }
+    }
+
+    #[test]
+    fn shutdown_frees_port() {
+        let reg = create_registry(SHUTDOWN_PORT);
+        reg.use_in_process(false);
+        reg.use_unix_sockets(false);
+        reg.bind("sleeper", Sleeper);
+        let rmt_reg = get_registry("localhost", SHUTDOWN_PORT).expect("registry is listening");
+        let stub: SleeperStub = rmt_reg.lookup("sleeper").expect("sleeper is in").into();
+        assert_eq!(stub.nap(0), Ok(0));
+
+        // the stub sits idle, it is hung up on without waiting
+        let report = reg.shutdown(Duration::from_secs(1));
+        assert_eq!(
+            report,
+            ShutdownReport {
+                drained: 0,
+                closed: 1,
+                aborted: vec![],
+            }
+        );
+        assert!(stub.nap(0).is_err());
+        assert!(matches!(
+            get_registry("localhost", SHUTDOWN_PORT),
+            Err(RMIError::RegistryUnreachable(_))
+        ));
+        assert_eq!(
+            reg.shutdown(Duration::from_secs(1)),
+            ShutdownReport::default()
+        );
+
+        // dropping does the same
+        let again = create_registry(SHUTDOWN_PORT);
+        drop(again);
+        let again = create_registry(SHUTDOWN_PORT);
+        let rmt_reg = get_registry("localhost", SHUTDOWN_PORT).expect("registry is back");
+        assert_eq!(rmt_reg.list(), Ok(vec![]));
+        drop(again);
+    }
+
+    #[test]
+    fn shutdown_drains_calls() {
+        let reg = create_registry_in("drain").expect("drain is free");
+        reg.bind("sleeper", Sleeper);
+        let rmt_reg = get_registry_in("drain").expect("registry is in this process");
+        let quick: SleeperStub = rmt_reg.lookup("sleeper").expect("sleeper is in").into();
+        let slow: SleeperStub = rmt_reg.lookup("sleeper").expect("sleeper is in").into();
+        let idle: SleeperStub = rmt_reg.lookup("sleeper").expect("sleeper is in").into();
+        assert_eq!(idle.nap(0), Ok(0));
+
+        let quick = thread::spawn(move || quick.nap(200));
+        let _slow = thread::spawn(move || slow.nap(2000));
+        thread::sleep(Duration::from_millis(100));
+        let report = reg.shutdown(Duration::from_millis(500));
+        assert_eq!(report.drained, 1);
+        assert_eq!(report.closed, 3);
+        assert_eq!(
+            report.aborted,
+            vec![AbortedCall {
+                object: "Sleeper",
+                method: Some("nap"),
+                peer: None,
+            }]
+        );
+        assert_eq!(report.aborted[0].to_string(), "Sleeper.nap");
+        assert_eq!(quick.join().expect("no panic"), Ok(200));
+        assert!(get_registry_in("drain").is_err());
     }
 
     #[test]
@@ -129,7 +1487,7 @@ mod tests {
         eprintln!("reg preparation");
         let reg = create_registry(LOCAL_PORT);
         reg.bind("verbose", obj_verbose);
-        let rmt_reg = get_registry("localhost", LOCAL_PORT);
+        let rmt_reg = get_registry("localhost", LOCAL_PORT).expect("registry is listening");
         let stb = rmt_reg.lookup("verbose").expect("verbose should be in");
         eprintln!("Stub: {stb:?} will turn into MockRemoteObjectStub");
         let stub = MockRemoteObjectStub::from(stb);
@@ -174,7 +1532,8 @@ mod tests {
     #[ignore]
     fn remote_stub() {
         // runs after remote_listen on 00650??.student.liacs.nl
-        let reg = get_registry(REMOTE_HOST, REMOTE_TEST_PORT);
+        let reg =
+            get_registry(REMOTE_HOST, REMOTE_TEST_PORT).expect("remote registry is listening");
         let stub: MockRemoteObjectStub = reg.lookup("verbose").expect("should work").into();
         let res = stub.run("send the data", vec![42; 2]);
         println!("{res:?}");
@@ -211,3 +1570,165 @@ mod tests {
         let _ = send_data(data_serial, &mut stream);
     }
 }
+
+#[cfg(all(test, feature = "tls"))]
+mod tests_tls {
+    use std::path::PathBuf;
+
+    use rcgen::{BasicConstraints, CertificateParams, CertifiedIssuer, DnType, IsCa, KeyPair};
+    use rrmi_macros::remote_object;
+
+    use crate::remote::RemoteObject;
+    use crate::remote::registry::get_registry;
+    use crate::{
+        Endpoint, RMIError, RMIResult, TlsClientConfig, TlsServerConfig, create_registry_tls,
+        get_registry_tls, peer_identity,
+    };
+
+    static TLS_PORT: u16 = 10989;
+    static MTLS_PORT: u16 = 10988;
+    static SILENT_PORT: u16 = 10971;
+
+    #[derive(Debug)]
+    struct WhoAmI;
+
+    #[remote_object]
+    impl WhoAmI {
+        #[remote]
+        fn who(&self) -> Option<String> {
+            peer_identity().and_then(|peer| peer.common_name)
+        }
+    }
+
+    /// PEM files for a CA, a localhost server and a client called `alice`, signed by the CA.
+    struct Pki {
+        dir: PathBuf,
+    }
+
+    impl Pki {
+        fn new(tag: &str) -> Pki {
+            let dir = std::env::temp_dir().join(format!("rrmi-pki-{}-{tag}", std::process::id()));
+            std::fs::create_dir_all(&dir).expect("temp dir is writable");
+            let mut ca_params = CertificateParams::new(vec![]).expect("valid params");
+            ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+            ca_params
+                .distinguished_name
+                .push(DnType::CommonName, format!("rrmi {tag} CA"));
+            let ca_key = KeyPair::generate().expect("key generation works");
+            let ca = CertifiedIssuer::self_signed(ca_params, ca_key).expect("CA is valid");
+            let write = |file: &str, pem: String| {
+                std::fs::write(dir.join(file), pem).expect("temp dir is writable")
+            };
+            write("ca.pem", ca.pem());
+            for (name, cn, sans) in [
+                ("server", "localhost", vec!["localhost", "127.0.0.1", "::1"]),
+                ("client", "alice", vec![]),
+            ] {
+                let sans = sans.into_iter().map(str::to_string).collect::<Vec<_>>();
+                let mut params = CertificateParams::new(sans).expect("valid params");
+                params.distinguished_name.push(DnType::CommonName, cn);
+                let key = KeyPair::generate().expect("key generation works");
+                let cert = params.signed_by(&key, &ca).expect("CA signs");
+                write(&format!("{name}.pem"), cert.pem());
+                write(&format!("{name}.key"), key.serialize_pem());
+            }
+            Pki { dir }
+        }
+
+        fn path(&self, file: &str) -> PathBuf {
+            self.dir.join(file)
+        }
+    }
+
+    impl Drop for Pki {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    #[test]
+    fn tls_registry() {
+        let pki = Pki::new("tls");
+        let server = TlsServerConfig::from_pem(pki.path("server.pem"), pki.path("server.key"))
+            .expect("PEM files are valid");
+        let reg = create_registry_tls(TLS_PORT, server);
+        reg.bind("who", WhoAmI);
+
+        let client = TlsClientConfig::from_pem(pki.path("ca.pem")).expect("PEM file is valid");
+        let rmt_reg = get_registry_tls("localhost", TLS_PORT, client).expect("registry is up");
+        assert_eq!(rmt_reg.list(), Ok(vec!["who".to_string()]));
+        let stb = rmt_reg.lookup("who").expect("who should be in");
+        assert!(matches!(stb.remote.addr, Endpoint::Tls(_)));
+        let stub: WhoAmIStub = stb.into();
+        // no client certificate, no identity
+        assert_eq!(stub.who(), Ok(None));
+
+        match get_registry("localhost", TLS_PORT) {
+            Err(RMIError::RegistryUnreachable(_)) => (),
+            other => panic!("plaintext clients should not get in, got {other:?}"),
+        }
+        let stranger = Pki::new("stranger");
+        let client = TlsClientConfig::from_pem(stranger.path("ca.pem")).expect("valid PEM");
+        match get_registry_tls("localhost", TLS_PORT, client) {
+            Err(RMIError::RegistryUnreachable(_)) => (),
+            other => panic!("an untrusted server should be refused, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn mutual_tls_identity() {
+        let pki = Pki::new("mtls");
+        let server = TlsServerConfig::from_pem_mutual(
+            pki.path("server.pem"),
+            pki.path("server.key"),
+            pki.path("ca.pem"),
+        )
+        .expect("PEM files are valid");
+        let reg = create_registry_tls(MTLS_PORT, server);
+        reg.bind("who", WhoAmI);
+
+        let alice = TlsClientConfig::from_pem_mutual(
+            pki.path("ca.pem"),
+            pki.path("client.pem"),
+            pki.path("client.key"),
+        )
+        .expect("PEM files are valid");
+        let rmt_reg = get_registry_tls("localhost", MTLS_PORT, alice).expect("registry is up");
+        let stub: WhoAmIStub = rmt_reg.lookup("who").expect("who should be in").into();
+        assert_eq!(stub.who(), Ok(Some("alice".to_string())));
+
+        let anonymous = TlsClientConfig::from_pem(pki.path("ca.pem")).expect("valid PEM");
+        match get_registry_tls("localhost", MTLS_PORT, anonymous) {
+            Err(RMIError::RegistryUnreachable(_)) => (),
+            other => panic!("clients without a certificate should be refused, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn bad_pem_files() {
+        let missing = std::env::temp_dir().join("rrmi-no-such.pem");
+        let result: RMIResult<TlsClientConfig> = TlsClientConfig::from_pem(&missing);
+        assert!(matches!(result, Err(RMIError::Tls(_))));
+        let result = TlsServerConfig::from_pem(&missing, &missing);
+        assert!(matches!(result, Err(RMIError::Tls(_))));
+    }
+
+    #[test]
+    fn silent_tls_client() {
+        let pki = Pki::new("silent");
+        let server = TlsServerConfig::from_pem(pki.path("server.pem"), pki.path("server.key"))
+            .expect("PEM files are valid");
+        let reg = create_registry_tls(SILENT_PORT, server);
+        reg.bind("who", WhoAmI);
+
+        // connects and never starts the handshake
+        let _silent =
+            std::net::TcpStream::connect(("localhost", SILENT_PORT)).expect("registry is up");
+        let client = TlsClientConfig::from_pem(pki.path("ca.pem")).expect("PEM file is valid");
+        // the next client gets in while the silent one is still connected
+        let rmt_reg =
+            get_registry_tls("localhost", SILENT_PORT, client).expect("registry is listening");
+        let names = rmt_reg.list();
+        assert_eq!(names, Ok(vec!["who".to_string()]));
+    }
+}
@@ -0,0 +1,113 @@
+use std::fmt::{Debug, Display};
+use std::time::SystemTime;
+
+use rrmi_macros::remote_object;
+use tracing::debug;
+
+use crate::remote::registry::RegistryStub;
+use crate::remote::{ObjectInfo, RemoteObject};
+use crate::stub::{Deserialize, Serialize, Skeleton};
+
+/// A change of a name in a registry, as watchers are told about it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum WatchEvent {
+    /// the name was not bound before
+    Bound {
+        name: String,
+    },
+    /// the name was bound to something else before
+    Rebound {
+        name: String,
+    },
+    Unbound {
+        name: String,
+    },
+    /// the lease of the name ran out, see `RegistryStub::bind_with_lease`
+    Expired {
+        name: String,
+    },
+}
+
+impl WatchEvent {
+    pub fn name(&self) -> &str {
+        match self {
+            WatchEvent::Bound { name }
+            | WatchEvent::Rebound { name }
+            | WatchEvent::Unbound { name }
+            | WatchEvent::Expired { name } => name,
+        }
+    }
+}
+
+impl Display for WatchEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WatchEvent::Bound { name } => write!(f, "bound {name}"),
+            WatchEvent::Rebound { name } => write!(f, "rebound {name}"),
+            WatchEvent::Unbound { name } => write!(f, "unbound {name}"),
+            WatchEvent::Expired { name } => write!(f, "expired {name}"),
+        }
+    }
+}
+
+/// The object `RegistryStub::watch` exports for the registry to call back with events.
+pub(crate) struct Watcher {
+    on_event: Box<dyn Fn(WatchEvent) + Send + Sync>,
+}
+
+impl Debug for Watcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Watcher").finish_non_exhaustive()
+    }
+}
+
+#[remote_object]
+impl Watcher {
+    pub(crate) fn new(on_event: impl Fn(WatchEvent) + Send + Sync + 'static) -> Self {
+        Watcher {
+            on_event: Box::new(on_event),
+        }
+    }
+
+    #[remote]
+    fn notify(&self, event: WatchEvent) {
+        (self.on_event)(event)
+    }
+}
+
+/// What a registry needs to call a watcher through a `DynamicStub`.
+pub(crate) fn watcher_info() -> ObjectInfo {
+    let watcher = Watcher::new(drop);
+    ObjectInfo {
+        name: "watcher".to_string(),
+        type_name: Some(watcher.name().to_string()),
+        id: 0,
+        addr: None,
+        fingerprint: Some(watcher.fingerprint()),
+        bound_at: SystemTime::UNIX_EPOCH,
+        methods: watcher.methods(),
+    }
+}
+
+/// The events of the names under a prefix, see `RegistryStub::watch`. Dropping it stops them.
+#[derive(Debug)]
+pub struct Watch {
+    pub(crate) registry: RegistryStub,
+    pub(crate) id: u64,
+    // serves the calls of the registry until dropped after the watch
+    pub(crate) _watcher: Skeleton,
+}
+
+impl Watch {
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+impl Drop for Watch {
+    fn drop(&mut self) {
+        if let Err(e) = self.registry.unwatch(self.id) {
+            debug!(id = self.id, error = %e, "could not stop watching");
+        }
+    }
+}
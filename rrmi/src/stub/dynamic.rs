@@ -0,0 +1,192 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::{LazyLock, Mutex};
+
+use serde_cbor::Value;
+
+use crate::error::RMIError;
+use crate::remote::{
+    Call, Interceptor, Interceptors, MethodSignature, ObjectInfo, RMIResult, invoke,
+};
+use crate::stub::Stub;
+use crate::transport::{Client, Headers};
+
+// names calls are counted under, leaked once each since `Call` holds `&'static str`
+static NAMES: LazyLock<Mutex<HashSet<&'static str>>> = LazyLock::new(Mutex::default);
+
+fn intern(name: &str) -> &'static str {
+    let mut names = NAMES.lock().expect("DynamicStub: unable to get names lock");
+    match names.get(name) {
+        Some(name) => name,
+        None => {
+            let name: &'static str = Box::leak(name.to_string().into_boxed_str());
+            names.insert(name);
+            name
+        }
+    }
+}
+
+/// The enum variant `#[remote_object]` generates for `method`.
+fn variant(method: &str) -> String {
+    method
+        .split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                None => String::new(),
+                Some(first) => first.to_uppercase().to_string() + chars.as_str(),
+            }
+        })
+        .collect()
+}
+
+/// Calls the methods of a remote object by name, with arguments and results as CBOR values,
+/// for tools that were not compiled against the object's generated stub.
+///
+/// It needs the method signatures `Registry::describe` returns, so objects bound by reference
+/// from another process cannot be called this way.
+#[derive(Debug)]
+pub struct DynamicStub {
+    transport_client: Client,
+    info: ObjectInfo,
+    headers: Headers,
+    call_headers: RefCell<Headers>,
+    interceptors: Interceptors,
+}
+
+impl DynamicStub {
+    /// Connects to the object `stub` points at, described by `info`.
+    pub fn new(stub: Stub, info: ObjectInfo) -> RMIResult<Self> {
+        Ok(DynamicStub {
+            transport_client: stub.connect()?,
+            info,
+            headers: Headers::new(),
+            call_headers: RefCell::new(Headers::new()),
+            interceptors: Interceptors::default(),
+        })
+    }
+
+    pub fn info(&self) -> &ObjectInfo {
+        &self.info
+    }
+
+    pub fn method(&self, name: &str) -> Option<&MethodSignature> {
+        self.info.methods.iter().find(|method| method.name == name)
+    }
+
+    /// Sends `value` as header `key` with every call from this stub.
+    pub fn set_header(&mut self, key: &str, value: &str) {
+        self.headers.insert(key.to_string(), value.to_string());
+    }
+
+    /// Sends `headers` with the next call only, over the ones set with `set_header`.
+    pub fn call_headers(&self, headers: Headers) -> &Self {
+        self.call_headers.borrow_mut().extend(headers);
+        self
+    }
+
+    /// Adds `interceptor` at the end of the chain every call from this stub goes through.
+    pub fn add_interceptor(&mut self, interceptor: impl Interceptor + 'static) {
+        self.interceptors.push(interceptor);
+    }
+
+    /// Calls `method` with `args` in the order of its parameters, returns what it returned.
+    ///
+    /// Fails with `RMIError::MethodNotFound` for methods the object does not have and with
+    /// `RMIError::BadArguments` when the number of arguments is off. Arguments of the wrong
+    /// type are refused by the object.
+    pub fn call(&self, method: &str, args: Vec<Value>) -> RMIResult<Value> {
+        let signature = self
+            .method(method)
+            .ok_or_else(|| RMIError::MethodNotFound(method.to_string()))?;
+        if args.len() != signature.params.len() {
+            return Err(RMIError::BadArguments(format!(
+                "{method} takes {} arguments, got {}",
+                signature.params.len(),
+                args.len()
+            )));
+        }
+        let variant = variant(method);
+        let body = match args.is_empty() {
+            true => Value::Text(variant.clone()),
+            false => {
+                let fields = signature
+                    .params
+                    .iter()
+                    .map(|(name, _)| Value::Text(name.clone()))
+                    .zip(args)
+                    .collect();
+                Value::Map(BTreeMap::from([(
+                    Value::Text(variant.clone()),
+                    Value::Map(fields),
+                )]))
+            }
+        };
+        let object = self.info.type_name.as_deref().unwrap_or(&self.info.name);
+        let call = Call::new(intern(object), intern(method), self.take_headers(), 0);
+        invoke(
+            &self.transport_client,
+            &self.interceptors,
+            call,
+            body,
+            |response: Value| read_response(response, &variant),
+        )
+    }
+
+    /// Like `call` with JSON values, `args` being an array of the arguments in order, an
+    /// object of them by parameter name, the only argument or null for none.
+    #[cfg(feature = "json")]
+    pub fn call_json(&self, method: &str, args: serde_json::Value) -> RMIResult<serde_json::Value> {
+        let bad = |e: serde_cbor::Error| RMIError::BadArguments(format!("{method}: {e}"));
+        let args = match args {
+            serde_json::Value::Array(args) => args,
+            serde_json::Value::Object(mut named) => {
+                let signature = self
+                    .method(method)
+                    .ok_or_else(|| RMIError::MethodNotFound(method.to_string()))?;
+                let args = signature
+                    .params
+                    .iter()
+                    .map(|(name, _)| named.remove(name).unwrap_or(serde_json::Value::Null))
+                    .collect();
+                if let Some(unknown) = named.keys().next() {
+                    return Err(RMIError::BadArguments(format!(
+                        "{method} has no parameter {unknown}"
+                    )));
+                }
+                args
+            }
+            serde_json::Value::Null => vec![],
+            arg => vec![arg],
+        };
+        let args = args
+            .into_iter()
+            .map(|arg| serde_cbor::value::to_value(arg).map_err(bad))
+            .collect::<RMIResult<_>>()?;
+        let result = self.call(method, args)?;
+        serde_json::to_value(result).map_err(|e| RMIError::DeserializationError(e.to_string()))
+    }
+
+    fn take_headers(&self) -> Headers {
+        let mut headers = self.headers.clone();
+        headers.extend(self.call_headers.take());
+        headers
+    }
+}
+
+/// Unwraps the response variant of the method called, or the error the object sent instead.
+fn read_response(response: Value, variant: &str) -> RMIResult<Value> {
+    let wrong = || RMIError::TransportError("Wrong response".to_string());
+    let Value::Map(response) = response else {
+        return Err(wrong());
+    };
+    let mut entries = response.into_iter();
+    match (entries.next(), entries.next()) {
+        (Some((Value::Text(name), value)), None) if name == variant => Ok(value),
+        (Some((Value::Text(name), error)), None) if name == "RmiError" => {
+            let error: RMIError = serde_cbor::value::from_value(error).map_err(|_| wrong())?;
+            Err(error)
+        }
+        _ => Err(wrong()),
+    }
+}
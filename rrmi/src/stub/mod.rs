@@ -1,8 +1,15 @@
+mod dynamic;
 mod serialization;
+mod serving;
 mod skeleton;
+#[allow(clippy::module_inception)]
 mod stub;
 
+pub use dynamic::DynamicStub;
 pub use serialization::{Deserialize, Serialize, marshal, unmarshal};
+pub use serving::{AbortedCall, ShutdownReport};
+pub(crate) use serving::{DROP_TIMEOUT, Serving, note_method};
 pub use skeleton::Skeleton;
+pub(crate) use skeleton::wake_tcp;
 #[allow(unused_imports)]
-pub use stub::Stub;
+pub use stub::{RemoteStub, Stub};
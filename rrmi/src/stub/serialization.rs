@@ -7,6 +7,7 @@ pub use serde::{Deserialize, Serialize};
 
 #[cfg(feature = "tracing")]
 use tracing::instrument;
+use tracing::{debug, trace};
 
 #[cfg(feature = "tracing")]
 #[instrument]
@@ -15,7 +16,7 @@ where
     T: Serialize + Debug,
 {
     serde_cbor::to_vec(&data).map_err(|e| {
-        eprintln!("Marshaling error: {e}");
+        debug!(error = %e, "marshalling failed");
         RMIError::SerializationError(e.to_string())
     })
 }
@@ -23,15 +24,16 @@ where
 #[cfg(not(feature = "tracing"))]
 pub fn marshal<T: Serialize>(data: &T) -> RMIResult<Vec<u8>> {
     serde_cbor::to_vec(&data).map_err(|e| {
-        eprintln!("Marshaling error: {e}");
+        debug!(error = %e, "marshalling failed");
         RMIError::SerializationError(e.to_string())
     })
 }
 
 #[cfg_attr(feature = "tracing", instrument)]
 pub fn unmarshal<T: for<'de> Deserialize<'de>>(bytes: &Vec<u8>) -> RMIResult<T> {
-    serde_cbor::from_slice(&bytes).map_err(|e| {
-        eprintln!("Unmarshaling error: {e} on bytes: {bytes:?}");
+    serde_cbor::from_slice(bytes).map_err(|e| {
+        debug!(error = %e, len = bytes.len(), "unmarshalling failed");
+        trace!(?bytes, "could not unmarshal");
         RMIError::DeserializationError(e.to_string())
     })
 }
@@ -0,0 +1,362 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::{Debug, Display};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crate::error::RMIError;
+use crate::remote::RMIResult;
+use crate::transport::{Closer, Connection};
+
+/// How long dropping a registry or a skeleton waits for the calls it is still serving.
+pub const DROP_TIMEOUT: Duration = Duration::from_secs(5);
+// how often shutdown checks on the calls it waits for
+const DRAIN_POLL: Duration = Duration::from_millis(5);
+
+thread_local! {
+    // the connection served on this thread, so shutdown can tell which call it is running
+    static SERVED: RefCell<Option<Arc<Served>>> = const { RefCell::new(None) };
+}
+
+/// A call that was still running when `shutdown` stopped waiting for it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AbortedCall {
+    pub object: &'static str,
+    /// `None` when the request had not been decoded yet
+    pub method: Option<&'static str>,
+    pub peer: Option<SocketAddr>,
+}
+
+impl Display for AbortedCall {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.object, self.method.unwrap_or("?"))?;
+        if let Some(peer) = self.peer {
+            write!(f, " from {peer}")?;
+        }
+        Ok(())
+    }
+}
+
+/// What `Registry::shutdown` and `Skeleton::shutdown` did to the clients they were serving.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ShutdownReport {
+    /// calls that were running when the shutdown started and finished in time
+    pub drained: usize,
+    /// connections hung up on, idle or not
+    pub closed: usize,
+    /// calls still running at the deadline, their connections are closed under them and
+    /// their threads left to finish on their own
+    pub aborted: Vec<AbortedCall>,
+}
+
+impl ShutdownReport {
+    /// True when no call had to be aborted.
+    pub fn is_clean(&self) -> bool {
+        self.aborted.is_empty()
+    }
+
+    pub(crate) fn merge(&mut self, other: ShutdownReport) {
+        self.drained += other.drained;
+        self.closed += other.closed;
+        self.aborted.extend(other.aborted);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Idle,
+    Busy(Option<&'static str>),
+    Closed,
+}
+
+/// A connection being served, as seen from `shutdown`.
+struct Served {
+    object: &'static str,
+    peer: Option<SocketAddr>,
+    state: Mutex<State>,
+    closer: Option<Closer>,
+}
+
+impl Served {
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state
+            .lock()
+            .expect("Serving: unable to get connection lock")
+    }
+
+    fn close(&self) {
+        if let Some(close) = &self.closer {
+            close();
+        }
+    }
+
+    /// Closes the connection unless a call is running on it, true if it did.
+    fn close_if_idle(&self) -> bool {
+        let mut state = self.state();
+        match *state {
+            State::Idle => {
+                *state = State::Closed;
+                drop(state);
+                self.close();
+                true
+            }
+            State::Busy(_) | State::Closed => false,
+        }
+    }
+
+    fn aborted(&self) -> AbortedCall {
+        let method = match *self.state() {
+            State::Busy(method) => method,
+            State::Idle | State::Closed => None,
+        };
+        AbortedCall {
+            object: self.object,
+            method,
+            peer: self.peer,
+        }
+    }
+}
+
+/// Tells the running call of this thread's connection apart in shutdown reports.
+pub(crate) fn note_method(method: &'static str) {
+    SERVED.with(|served| {
+        if let Some(served) = served.borrow().as_ref() {
+            let mut state = served.state();
+            if let State::Busy(_) = *state {
+                *state = State::Busy(Some(method));
+            }
+        }
+    });
+}
+
+/// A connection registered with `Serving::connect`, unregistered when dropped.
+pub(crate) struct Connected {
+    serving: Serving,
+    id: u64,
+    served: Arc<Served>,
+}
+
+impl Connected {
+    /// Marks a call as running, false when the connection was closed for shutdown instead.
+    pub(crate) fn begin(&self) -> bool {
+        let mut state = self.served.state();
+        match *state {
+            State::Closed => false,
+            State::Idle | State::Busy(_) => {
+                *state = State::Busy(None);
+                true
+            }
+        }
+    }
+
+    /// Marks the call as done, false when the connection should not take another one.
+    pub(crate) fn end(&self) -> bool {
+        let mut state = self.served.state();
+        if self.serving.is_stopping() || *state == State::Closed {
+            *state = State::Closed;
+            return false;
+        }
+        *state = State::Idle;
+        true
+    }
+}
+
+impl Drop for Connected {
+    fn drop(&mut self) {
+        SERVED.with(|served| served.borrow_mut().take());
+        self.serving
+            .inner
+            .connections
+            .lock()
+            .expect("Serving: unable to get connections lock")
+            .remove(&self.id);
+    }
+}
+
+#[derive(Default)]
+struct Inner {
+    stopping: AtomicBool,
+    next_id: AtomicU64,
+    threads: Mutex<Vec<JoinHandle<()>>>,
+    // how to get each thread blocked in accept going again, by token
+    wakers: Mutex<HashMap<u64, Box<dyn Fn() + Send>>>,
+    connections: Mutex<HashMap<u64, Arc<Served>>>,
+}
+
+/// The threads a registry or skeleton listens and serves on, and what `shutdown` needs to
+/// stop them: a way to wake up every blocking accept and the connections being served.
+#[derive(Clone, Default)]
+pub(crate) struct Serving {
+    inner: Arc<Inner>,
+}
+
+impl Serving {
+    pub(crate) fn is_stopping(&self) -> bool {
+        self.inner.stopping.load(Ordering::SeqCst)
+    }
+
+    /// Runs `serve` on a thread named `name` that shutdown waits for.
+    pub(crate) fn spawn(
+        &self,
+        name: String,
+        serve: impl FnOnce() + Send + 'static,
+    ) -> RMIResult<()> {
+        if self.is_stopping() {
+            return Err(RMIError::TransportError(format!("{name}: shut down")));
+        }
+        let handle = std::thread::Builder::new()
+            .name(name)
+            .spawn(serve)
+            .map_err(|e| RMIError::IoError(e.to_string()))?;
+        let mut threads = self
+            .inner
+            .threads
+            .lock()
+            .expect("Serving: unable to get threads lock");
+        threads.retain(|thread| !thread.is_finished());
+        threads.push(handle);
+        Ok(())
+    }
+
+    /// Registers how to wake up a thread blocked in accept, typically by connecting to it.
+    pub(crate) fn add_waker(&self, wake: impl Fn() + Send + 'static) -> u64 {
+        let token = self.inner.next_id.fetch_add(1, Ordering::Relaxed);
+        self.inner
+            .wakers
+            .lock()
+            .expect("Serving: unable to get wakers lock")
+            .insert(token, Box::new(wake));
+        token
+    }
+
+    pub(crate) fn remove_waker(&self, token: u64) {
+        self.inner
+            .wakers
+            .lock()
+            .expect("Serving: unable to get wakers lock")
+            .remove(&token);
+    }
+
+    /// Registers `stream`, served by this thread for `object`. `None` once shutting down,
+    /// the connection should be dropped then.
+    pub(crate) fn connect(
+        &self,
+        object: &'static str,
+        stream: &dyn Connection,
+    ) -> Option<Connected> {
+        let served = Arc::new(Served {
+            object,
+            peer: stream.peer_addr(),
+            state: Mutex::new(State::Idle),
+            closer: stream.closer(),
+        });
+        let id = self.inner.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut connections = self
+            .inner
+            .connections
+            .lock()
+            .expect("Serving: unable to get connections lock");
+        // checked under the lock so shutdown cannot miss the connection
+        if self.is_stopping() {
+            return None;
+        }
+        connections.insert(id, Arc::clone(&served));
+        drop(connections);
+        SERVED.with(|current| *current.borrow_mut() = Some(Arc::clone(&served)));
+        Some(Connected {
+            serving: self.clone(),
+            id,
+            served,
+        })
+    }
+
+    /// Stops accepting, hangs up on idle clients, waits up to `timeout` for running calls to
+    /// finish and joins the threads that are done. Only the first call does anything.
+    pub(crate) fn shutdown(&self, timeout: Duration) -> ShutdownReport {
+        let mut report = ShutdownReport::default();
+        let deadline = Instant::now() + timeout;
+        let connections = {
+            let connections = self
+                .inner
+                .connections
+                .lock()
+                .expect("Serving: unable to get connections lock");
+            if self.inner.stopping.swap(true, Ordering::SeqCst) {
+                return report;
+            }
+            connections.values().cloned().collect::<Vec<_>>()
+        };
+        let wakers: Vec<_> = self
+            .inner
+            .wakers
+            .lock()
+            .expect("Serving: unable to get wakers lock")
+            .drain()
+            .map(|(_, wake)| wake)
+            .collect();
+        for wake in wakers {
+            wake();
+        }
+        let mut running = vec![];
+        for served in connections {
+            if served.close_if_idle() {
+                report.closed += 1;
+            } else {
+                running.push(served);
+            }
+        }
+        let current = std::thread::current().id();
+        let others_done = || {
+            self.inner
+                .threads
+                .lock()
+                .expect("Serving: unable to get threads lock")
+                .iter()
+                .all(|thread| thread.is_finished() || thread.thread().id() == current)
+        };
+        loop {
+            running.retain(|served| {
+                let done = *served.state() == State::Closed;
+                if done {
+                    report.drained += 1;
+                    report.closed += 1;
+                }
+                !done
+            });
+            if (running.is_empty() && others_done()) || Instant::now() >= deadline {
+                break;
+            }
+            std::thread::sleep(DRAIN_POLL);
+        }
+        for served in running {
+            report.aborted.push(served.aborted());
+            served.close();
+            report.closed += 1;
+        }
+        let threads = std::mem::take(
+            &mut *self
+                .inner
+                .threads
+                .lock()
+                .expect("Serving: unable to get threads lock"),
+        );
+        for thread in threads {
+            // the others are left behind, stuck in a call or a handshake
+            if thread.is_finished() {
+                let _ = thread.join();
+            }
+        }
+        report
+    }
+}
+
+impl Debug for Serving {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Serving")
+            .field("stopping", &self.is_stopping())
+            .finish_non_exhaustive()
+    }
+}
@@ -1,87 +1,381 @@
 use std::fmt::Debug;
 use std::io::ErrorKind;
+use std::net::{Ipv4Addr, SocketAddr, TcpStream};
+#[cfg(unix)]
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
 #[cfg(feature = "tracing")]
 use tracing::instrument;
 #[cfg(feature = "tracing")]
 use tracing::{Level, span};
 
-use crate::remote::{RMIResult, RemoteObject};
+use tracing::{debug, warn};
+
+use crate::error::RMIError;
+use crate::remote::{
+    Authorizer, Hooks, Interceptor, Principal, RMIResult, RemoteObject, enter_connection,
+    next_request, set_thread_hooks,
+};
+use crate::stub::{DROP_TIMEOUT, Serving, ShutdownReport};
+#[cfg(target_os = "linux")]
+use crate::transport::ShmStream;
 use crate::transport::utils::get_tcp_socket_os;
+use crate::transport::{Connection, MemoryListener, MemoryStream, PreSharedKey, memory_name};
+#[cfg(feature = "tls")]
+use crate::transport::{TlsServerConfig, TlsStream, set_peer_identity};
+#[cfg(unix)]
+use crate::transport::{UnixListener, UnixStream, unix_socket_path};
+
+// how long waking up an accept loop may take before shutdown gives up on it
+const WAKE_TIMEOUT: Duration = Duration::from_millis(500);
 
+/// Serves a remote object on as many listeners as it gets clients. Dropping it shuts them
+/// down, see `shutdown`.
 pub struct Skeleton {
     object: Arc<dyn RemoteObject>, // Arc because eventually we to listen from several ports
+    hooks: Hooks,
+    serving: Serving,
 }
 
 impl Skeleton {
     pub fn new(object: Arc<dyn RemoteObject>) -> Self {
-        Skeleton { object }
+        Self::with_hooks(object, Hooks::default())
+    }
+
+    /// A skeleton using the authorizer and interceptors of its registry, which can change them
+    /// later on.
+    pub(crate) fn with_hooks(object: Arc<dyn RemoteObject>, hooks: Hooks) -> Self {
+        Skeleton {
+            object,
+            hooks,
+            serving: Serving::default(),
+        }
+    }
+
+    pub(crate) fn object(&self) -> &dyn RemoteObject {
+        self.object.as_ref()
+    }
+
+    /// Decides who may call methods marked `#[remote(require = "role")]`, nobody can without one.
+    pub fn set_authorizer(&self, authorizer: impl Authorizer + 'static) {
+        *self
+            .hooks
+            .authorizer
+            .write()
+            .expect("Skeleton: unable to get authorizer lock") = Some(Arc::new(authorizer));
+    }
+
+    /// Adds `interceptor` at the end of the chain every call to the object goes through.
+    pub fn add_interceptor(&self, interceptor: impl Interceptor + 'static) {
+        self.hooks.add_interceptor(interceptor);
+    }
+
+    /// Serves every client that connects to the returned TCP port, each on its own thread,
+    /// for objects bound by reference in a registry of another process.
+    #[cfg_attr(feature = "tracing", instrument)]
+    pub fn export(&self) -> RMIResult<u16> {
+        let listener = get_tcp_socket_os()?;
+        let object_name = self.object.name();
+        let addr = listener
+            .local_addr()
+            .unwrap_or_else(|_| panic!("{object_name}: does not have an address"));
+        debug!(object = object_name, %addr, "exported");
+        let port = addr.port();
+        let object = Arc::clone(&self.object);
+        let hooks = self.hooks.clone();
+        let serving = self.serving.clone();
+        let waker = self.serving.add_waker(move || wake_tcp(port));
+        self.serving
+            .spawn(format!("Export{object_name}:{port}"), move || {
+                for stream in listener.incoming() {
+                    if serving.is_stopping() {
+                        break;
+                    }
+                    let stream = match stream.and_then(|s| s.set_nodelay(true).map(|_| s)) {
+                        Ok(stream) => stream,
+                        Err(e) => {
+                            warn!(object = object_name, error = %e, "could not accept");
+                            continue;
+                        }
+                    };
+                    let object = Arc::clone(&object);
+                    let hooks = hooks.clone();
+                    let connection = serving.clone();
+                    let name = format!("Skeleton{object_name}:{port}");
+                    let served = move || {
+                        let mut stream = stream;
+                        serve(object.as_ref(), &mut stream, hooks, &connection)
+                    };
+                    if let Err(e) = serving.spawn(name, served) {
+                        warn!(object = object_name, error = %e, "could not serve connection");
+                    }
+                }
+            })
+            .inspect_err(|_| self.serving.remove_waker(waker))?;
+        Ok(port)
     }
 
     #[cfg_attr(feature = "tracing", instrument)]
     pub fn listen(&self) -> RMIResult<u16> {
         let listener = get_tcp_socket_os()?;
-        let obj_clone = Arc::clone(&self.object);
-        let object_name = obj_clone.name();
+        let object_name = self.object.name();
         let addr = listener
             .local_addr()
-            .expect(&format!("{object_name}: does not have an address"));
-        eprintln!("{object_name} uses address: {addr}");
+            .unwrap_or_else(|_| panic!("{object_name}: does not have an address"));
+        debug!(object = object_name, %addr, "listening");
         let port = addr.port();
         let name = format!("Skeleton{object_name}:{port}");
-        let _handle_skeleton = std::thread::Builder::new().name(name).spawn(move || {
-            // for stream in listener.incoming() {
-            #[cfg(feature = "tracing")]
-            let span = span!(Level::TRACE, "listen");
-            #[cfg(feature = "tracing")]
-            let _enter = span.enter();
-            let stream = listener.accept();
-            match stream {
-                Ok((mut stream, _)) => {
-                    eprintln!(
-                        "{object_name} established connection with {:?}",
-                        stream.peer_addr()
-                    );
-                    stream.set_nodelay(true).expect("Could not set NO_DELAY");
-                    let mut buf = [0u8; 4];
-                    loop {
-                        #[cfg(feature = "tracing")]
-                        let span = span!(Level::TRACE, "peek");
-                        #[cfg(feature = "tracing")]
-                        let _enter = span.enter();
-                        match stream.peek(&mut buf) {
-                            Ok(0) => {
-                                eprintln!("{:?}: Connection closed.", obj_clone.name());
-                                break;
-                            }
-                            Ok(_) => (),
-                            Err(e) => match e.kind() {
-                                ErrorKind::ConnectionReset | ErrorKind::BrokenPipe => {
-                                    eprintln!("Connection closed due to error: {e}")
-                                }
-                                _k => eprintln!("Connection error {e:?}"),
-                            },
-                        };
-                        #[cfg(feature = "tracing")]
-                        drop(_enter);
-                        match obj_clone.run(&mut stream) {
-                            Ok(_) => {}
-                            Err(e) => {
-                                eprintln!(
-                                    "{:?} Connection closed when running: {e}",
-                                    stream.peer_addr()
-                                );
-                                break;
-                            }
+        self.spawn(
+            name,
+            move || wake_tcp(port),
+            move || {
+                let (stream, _) = listener.accept()?;
+                stream.set_nodelay(true)?;
+                Ok(stream)
+            },
+        )?;
+        Ok(port)
+    }
+
+    /// Like `listen` but only serves a client that proves it knows `key`, others are hung up on.
+    #[cfg_attr(feature = "tracing", instrument)]
+    pub fn listen_psk(&self, key: &PreSharedKey) -> RMIResult<u16> {
+        let listener = get_tcp_socket_os()?;
+        let object_name = self.object.name();
+        let addr = listener
+            .local_addr()
+            .unwrap_or_else(|_| panic!("{object_name}: does not have an address"));
+        debug!(object = object_name, %addr, "listening with pre-shared key");
+        let port = addr.port();
+        let name = format!("Skeleton{object_name}:{port}");
+        let key = key.clone();
+        let serving = self.serving.clone();
+        self.spawn(
+            name,
+            move || wake_tcp(port),
+            move || {
+                // a stranger connecting first must not take the place of the client
+                loop {
+                    let (mut stream, peer) = listener.accept()?;
+                    if serving.is_stopping() {
+                        return Err(ErrorKind::Interrupted.into());
+                    }
+                    stream.set_nodelay(true)?;
+                    match key.accept(&mut stream) {
+                        Ok(()) => return Ok(stream),
+                        Err(e) => {
+                            warn!(object = object_name, %peer, error = %e, "failed to authenticate")
                         }
                     }
                 }
-                Err(e) => eprintln!("Transport error: {e}"),
-            };
-            // }
-        });
-        Ok(addr.port())
+            },
+        )?;
+        Ok(port)
+    }
+
+    /// Like `listen` but clients have to complete a TLS handshake first.
+    #[cfg(feature = "tls")]
+    #[cfg_attr(feature = "tracing", instrument)]
+    pub fn listen_tls(&self, tls: &TlsServerConfig) -> RMIResult<u16> {
+        let listener = get_tcp_socket_os()?;
+        let object_name = self.object.name();
+        let addr = listener
+            .local_addr()
+            .unwrap_or_else(|_| panic!("{object_name}: does not have an address"));
+        debug!(object = object_name, %addr, "listening with TLS");
+        let port = addr.port();
+        let name = format!("Skeleton{object_name}:{port}");
+        let tls = tls.clone();
+        self.spawn(
+            name,
+            move || wake_tcp(port),
+            move || {
+                let (stream, _) = listener.accept()?;
+                stream.set_nodelay(true)?;
+                TlsStream::accept(stream, &tls)
+            },
+        )?;
+        Ok(port)
+    }
+
+    /// Like `listen` but on a Unix domain socket, for clients on the same host.
+    #[cfg(unix)]
+    #[cfg_attr(feature = "tracing", instrument)]
+    pub fn listen_unix(&self) -> RMIResult<PathBuf> {
+        let path = unix_socket_path();
+        let listener =
+            UnixListener::bind(&path).map_err(|e| RMIError::TransportError(e.to_string()))?;
+        let object_name = self.object.name();
+        debug!(object = object_name, socket = %path.display(), "listening");
+        let name = format!("Skeleton{object_name}:{}", path.display());
+        let socket = path.clone();
+        let wake = wake_unix(path.clone());
+        self.spawn(name, wake, move || {
+            let accepted = listener.accept();
+            // the connection outlives the file, nobody else should connect to it
+            let _ = std::fs::remove_file(&socket);
+            accepted.map(|(stream, _)| stream)
+        })?;
+        Ok(path)
+    }
+
+    /// Like `listen_unix` but the socket only negotiates a shared memory ring the calls go through.
+    #[cfg(target_os = "linux")]
+    #[cfg_attr(feature = "tracing", instrument)]
+    pub fn listen_shm(&self) -> RMIResult<PathBuf> {
+        let path = unix_socket_path();
+        let listener =
+            UnixListener::bind(&path).map_err(|e| RMIError::TransportError(e.to_string()))?;
+        let object_name = self.object.name();
+        debug!(object = object_name, socket = %path.display(), "listening with shared memory");
+        let name = format!("Skeleton{object_name}:{}", path.display());
+        let socket = path.clone();
+        let wake = wake_unix(path.clone());
+        self.spawn(name, wake, move || {
+            let accepted = ShmStream::accept(&listener);
+            let _ = std::fs::remove_file(&socket);
+            accepted
+        })?;
+        Ok(path)
+    }
+
+    /// Like `listen` but over in-process channels, for clients in this process.
+    #[cfg_attr(feature = "tracing", instrument)]
+    pub fn listen_memory(&self) -> RMIResult<String> {
+        let object_name = self.object.name();
+        let listener = MemoryListener::bind(&memory_name(object_name))?;
+        let name = listener.name().to_string();
+        let channel = name.clone();
+        let wake = move || drop(MemoryStream::connect(&channel));
+        self.spawn(format!("Skeleton{name}"), wake, move || listener.accept())?;
+        Ok(name)
+    }
+
+    /// Stops listening, hangs up on idle clients and waits up to `timeout` for running calls
+    /// to finish. Calls still running by then are reported as aborted.
+    #[cfg_attr(feature = "tracing", instrument)]
+    pub fn shutdown(&self, timeout: Duration) -> ShutdownReport {
+        let report = self.serving.shutdown(timeout);
+        debug!(object = self.object.name(), ?report, "shut down");
+        report
+    }
+
+    /// Serves one connection, whatever `accept` returns, on its own thread. `wake` gets
+    /// `accept` to return when shutting down before a client came.
+    fn spawn<C, F>(
+        &self,
+        name: String,
+        wake: impl Fn() + Send + 'static,
+        accept: F,
+    ) -> RMIResult<()>
+    where
+        C: Connection + 'static,
+        F: FnOnce() -> std::io::Result<C> + Send + 'static,
+    {
+        let obj_clone = Arc::clone(&self.object);
+        let hooks = self.hooks.clone();
+        let serving = self.serving.clone();
+        let waker = self.serving.add_waker(wake);
+        self.serving
+            .spawn(name, move || {
+                #[cfg(feature = "tracing")]
+                let span = span!(Level::TRACE, "listen");
+                #[cfg(feature = "tracing")]
+                let _enter = span.enter();
+                let accepted = accept();
+                serving.remove_waker(waker);
+                if serving.is_stopping() {
+                    return;
+                }
+                match accepted {
+                    Ok(mut stream) => {
+                        debug!(
+                            object = obj_clone.name(),
+                            peer = ?stream.peer_addr(),
+                            "connection established"
+                        );
+                        serve(obj_clone.as_ref(), &mut stream, hooks, &serving);
+                    }
+                    Err(e) => warn!(object = obj_clone.name(), error = %e, "could not accept"),
+                };
+            })
+            .inspect_err(|_| self.serving.remove_waker(waker))
+    }
+}
+
+/// Gets a thread blocked accepting on `port` of this host going again.
+pub(crate) fn wake_tcp(port: u16) {
+    let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+    let _ = TcpStream::connect_timeout(&addr, WAKE_TIMEOUT);
+}
+
+#[cfg(unix)]
+fn wake_unix(path: PathBuf) -> impl Fn() + Send + 'static {
+    move || drop(UnixStream::connect(&path))
+}
+
+fn serve(object: &dyn RemoteObject, stream: &mut dyn Connection, hooks: Hooks, serving: &Serving) {
+    let Some(connection) = serving.connect(object.name(), stream) else {
+        return;
+    };
+    enter_connection(Principal::of(stream));
+    set_thread_hooks(Some(hooks));
+    #[cfg(feature = "tls")]
+    set_peer_identity(stream.peer_identity());
+    loop {
+        #[cfg(feature = "tracing")]
+        let span = span!(Level::TRACE, "peek");
+        #[cfg(feature = "tracing")]
+        let _enter = span.enter();
+        match stream.readable() {
+            Ok(false) => {
+                debug!(object = object.name(), "connection closed");
+                break;
+            }
+            Ok(true) => (),
+            Err(e) => match e.kind() {
+                ErrorKind::ConnectionReset | ErrorKind::BrokenPipe => {
+                    debug!(object = object.name(), error = %e, "connection closed")
+                }
+                _k => warn!(object = object.name(), error = ?e, "connection error"),
+            },
+        };
+        #[cfg(feature = "tracing")]
+        drop(_enter);
+        if !connection.begin() {
+            debug!(object = object.name(), "connection closed for shutdown");
+            break;
+        }
+        next_request();
+        let result = object.run(stream);
+        let open = connection.end();
+        match result {
+            Ok(_) => {}
+            Err(e) => {
+                debug!(
+                    object = object.name(),
+                    peer = ?stream.peer_addr(),
+                    error = %e,
+                    "connection closed when running"
+                );
+                break;
+            }
+        }
+        if !open {
+            debug!(object = object.name(), "connection closed for shutdown");
+            break;
+        }
+    }
+}
+
+impl Drop for Skeleton {
+    fn drop(&mut self) {
+        let report = self.shutdown(DROP_TIMEOUT);
+        for call in &report.aborted {
+            warn!(%call, "call aborted, skeleton dropped while it was running");
+        }
     }
 }
 
@@ -90,4 +384,3 @@ impl Debug for Skeleton {
         write!(f, "Skeleton[{:?}]", self.object.name())
     }
 }
-//#TODO tests
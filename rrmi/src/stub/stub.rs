@@ -1,20 +1,68 @@
 use std::fmt::Debug;
 
 use crate::RemoteRef;
+use crate::remote::RMIResult;
+#[cfg(feature = "tls")]
+use crate::transport::TlsClientConfig;
+use crate::transport::{Client, PreSharedKey};
 
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub struct Stub {
     // Generic Stub as an intermediate step before generating ObjectStub
     pub remote: RemoteRef,
+    #[cfg(feature = "tls")]
+    pub tls: Option<TlsClientConfig>,
+    pub psk: Option<PreSharedKey>,
 }
 
 impl Stub {
     pub fn new(remote: RemoteRef) -> Self {
-        Stub { remote }
+        Stub {
+            remote,
+            #[cfg(feature = "tls")]
+            tls: None,
+            psk: None,
+        }
     }
 
     pub fn from(remote: RemoteRef) -> Self {
-        Stub { remote }
+        Self::new(remote)
     }
+
+    /// A stub that connects with `tls` when the remote is behind TLS.
+    #[cfg(feature = "tls")]
+    pub fn with_tls(remote: RemoteRef, tls: Option<TlsClientConfig>) -> Self {
+        Stub {
+            tls,
+            ..Stub::new(remote)
+        }
+    }
+
+    /// A stub that authenticates with `key` before its first call.
+    pub fn with_psk(remote: RemoteRef, psk: Option<PreSharedKey>) -> Self {
+        Stub {
+            psk,
+            ..Stub::new(remote)
+        }
+    }
+
+    /// Opens the connection generated stubs send their calls on.
+    pub fn connect(&self) -> RMIResult<Client> {
+        #[cfg(feature = "tls")]
+        if let Some(tls) = &self.tls {
+            return Client::connect_tls(&self.remote, tls, None);
+        }
+        if let Some(psk) = &self.psk {
+            return Client::connect_psk(&self.remote, psk, None);
+        }
+        Client::connect(&self.remote)
+    }
+}
+
+/// A stub generated by `#[remote_object]`, the typed side of a `Stub`.
+pub trait RemoteStub: Sized {
+    /// Connects to the object `stub` points at. `From<Stub>` does the same but panics when
+    /// the object cannot be reached.
+    fn connect(stub: Stub) -> RMIResult<Self>;
 }
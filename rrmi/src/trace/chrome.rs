@@ -0,0 +1,156 @@
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+
+use serde_json::{Value, json};
+
+use crate::error::RMIError;
+use crate::remote::RMIResult;
+
+/// Span stubs open around every call they make, see `remote::invoke`.
+pub(crate) const CALL_SPAN: &str = "rrmi.call";
+/// Span skeletons open around every call they serve, see `remote::before_call`.
+pub(crate) const SERVE_SPAN: &str = "rrmi.serve";
+
+/// Reads the events of a Chrome trace written by `tracing-chrome`, also when the process was
+/// killed before closing the JSON array.
+pub fn read_chrome_trace(path: impl AsRef<Path>) -> RMIResult<Vec<Value>> {
+    let path = path.as_ref();
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| RMIError::IoError(format!("{}: {e}", path.display())))?;
+    let parse = |text: &str| serde_json::from_str::<Vec<Value>>(text);
+    parse(&text)
+        .or_else(|_| parse(&format!("{}]", text.trim_end().trim_end_matches(','))))
+        .map_err(|e| RMIError::DeserializationError(format!("{}: {e}", path.display())))
+}
+
+/// A span that was entered and exited on one thread, timestamps in microseconds.
+struct Span<'a> {
+    name: &'a str,
+    args: &'a Value,
+    start: f64,
+    end: f64,
+}
+
+impl Span<'_> {
+    fn arg(&self, key: &str) -> Option<&str> {
+        self.args.get(key)?.as_str()
+    }
+
+    fn middle(&self) -> f64 {
+        (self.start + self.end) / 2.0
+    }
+}
+
+fn rpc_spans(events: &[Value]) -> Vec<Span<'_>> {
+    let mut open: HashMap<u64, Vec<&Value>> = HashMap::new();
+    let mut spans = vec![];
+    for event in events {
+        let tid = event["tid"].as_u64().unwrap_or_default();
+        match event["ph"].as_str() {
+            Some("B") => open.entry(tid).or_default().push(event),
+            Some("E") => {
+                let Some(begin) = open.get_mut(&tid).and_then(Vec::pop) else {
+                    continue;
+                };
+                let name = begin["name"].as_str().unwrap_or_default();
+                if name == CALL_SPAN || name == SERVE_SPAN {
+                    spans.push(Span {
+                        name,
+                        args: &begin["args"],
+                        start: begin["ts"].as_f64().unwrap_or_default(),
+                        end: event["ts"].as_f64().unwrap_or_default(),
+                    });
+                }
+            }
+            _ => (),
+        }
+    }
+    spans
+}
+
+fn median(values: &mut [f64]) -> f64 {
+    values.sort_by(f64::total_cmp);
+    values[values.len() / 2]
+}
+
+/// How much to add to the timestamps of each trace to line it up with the first one.
+///
+/// Every call seen by both sides ties two traces together: the skeleton span sits in the
+/// middle of the stub span, give or take the difference between the network legs.
+fn clock_offsets(traces: &[Vec<Span>]) -> Vec<f64> {
+    let mut calls = HashMap::new();
+    for (i, spans) in traces.iter().enumerate() {
+        for span in spans.iter().filter(|span| span.name == CALL_SPAN) {
+            if let Some(id) = span.arg("span_id") {
+                calls.insert(id, (i, span));
+            }
+        }
+    }
+    let mut estimates: HashMap<(usize, usize), Vec<f64>> = HashMap::new();
+    for (server, spans) in traces.iter().enumerate() {
+        for span in spans.iter().filter(|span| span.name == SERVE_SPAN) {
+            if let Some(&(client, call)) = span.arg("parent_id").and_then(|id| calls.get(id))
+                && client != server
+            {
+                let estimate = call.middle() - span.middle();
+                estimates
+                    .entry((client, server))
+                    .or_default()
+                    .push(estimate);
+                estimates
+                    .entry((server, client))
+                    .or_default()
+                    .push(-estimate);
+            }
+        }
+    }
+    let mut offsets = vec![None; traces.len()];
+    for start in 0..traces.len() {
+        if offsets[start].is_some() {
+            continue;
+        }
+        // traces no call connects to the first ones keep their own clock
+        offsets[start] = Some(0.0);
+        let mut queue = VecDeque::from([start]);
+        while let Some(from) = queue.pop_front() {
+            for to in 0..traces.len() {
+                if offsets[to].is_none()
+                    && let Some(estimates) = estimates.get_mut(&(from, to))
+                {
+                    offsets[to] = Some(offsets[from].unwrap_or_default() + median(estimates));
+                    queue.push_back(to);
+                }
+            }
+        }
+    }
+    offsets.into_iter().map(Option::unwrap_or_default).collect()
+}
+
+/// Merges the Chrome traces of several processes, each given with the name it shows up under.
+///
+/// Each trace becomes its own process and its timestamps are shifted onto the clock of the
+/// first trace, using the calls both sides recorded with the `tracing` feature on.
+pub fn merge_chrome_traces(traces: Vec<(String, Vec<Value>)>) -> Vec<Value> {
+    let offsets = {
+        let spans: Vec<Vec<Span>> = traces.iter().map(|(_, events)| rpc_spans(events)).collect();
+        clock_offsets(&spans)
+    };
+    let mut merged = vec![];
+    for (i, ((name, events), offset)) in traces.into_iter().zip(offsets).enumerate() {
+        let pid = i + 1;
+        merged.push(json!({
+            "ph": "M",
+            "pid": pid,
+            "name": "process_name",
+            "args": { "name": name },
+        }));
+        for mut event in events {
+            event["pid"] = pid.into();
+            if let Some(ts) = event["ts"].as_f64() {
+                event["ts"] = (ts + offset).into();
+            }
+            merged.push(event);
+        }
+    }
+    merged
+}
@@ -0,0 +1,121 @@
+use std::cell::Cell;
+use std::fmt::Display;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::RMIError;
+
+/// Header carrying the W3C trace context of a call, see <https://www.w3.org/TR/trace-context/>.
+pub const TRACEPARENT_HEADER: &str = "traceparent";
+
+const SAMPLED: u8 = 0x01;
+
+thread_local! {
+    // xorshift state, ids only need to be unique, not unpredictable
+    static SEED: Cell<u64> = Cell::new(seed());
+}
+
+fn seed() -> u64 {
+    let mut bytes = [0u8; 8];
+    if getrandom::fill(&mut bytes).is_err() {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        return now.as_nanos() as u64 | 1;
+    }
+    u64::from_ne_bytes(bytes) | 1
+}
+
+fn random_u64() -> u64 {
+    SEED.with(|seed| {
+        let mut x = seed.get();
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        seed.set(x);
+        x
+    })
+}
+
+/// The trace a call belongs to and the span that made it, what `traceparent` carries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TraceContext {
+    pub trace_id: u128,
+    pub span_id: u64,
+    pub sampled: bool,
+}
+
+impl TraceContext {
+    /// The first span of a new trace.
+    pub fn root() -> Self {
+        TraceContext {
+            trace_id: ((random_u64() as u128) << 64) | random_u64() as u128,
+            span_id: random_u64(),
+            sampled: true,
+        }
+    }
+
+    /// A new span in the same trace, made by this one.
+    pub fn child(&self) -> Self {
+        TraceContext {
+            span_id: random_u64(),
+            ..*self
+        }
+    }
+
+    pub fn trace_id_hex(&self) -> String {
+        format!("{:032x}", self.trace_id)
+    }
+
+    pub fn span_id_hex(&self) -> String {
+        format!("{:016x}", self.span_id)
+    }
+}
+
+impl Display for TraceContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let flags = if self.sampled { SAMPLED } else { 0 };
+        write!(
+            f,
+            "00-{:032x}-{:016x}-{flags:02x}",
+            self.trace_id, self.span_id
+        )
+    }
+}
+
+impl FromStr for TraceContext {
+    type Err = RMIError;
+
+    /// Parses a `traceparent` value, later versions are read as version 00.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || RMIError::BadArguments(format!("invalid traceparent: {s}"));
+        let mut parts = s.trim().split('-');
+        let (Some(version), Some(trace_id), Some(span_id), Some(flags)) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid());
+        };
+        // from_str_radix alone would take a leading +
+        let hex = |part: &str, len: usize| {
+            part.len() == len && part.bytes().all(|b| b.is_ascii_hexdigit())
+        };
+        if !(hex(version, 2) && hex(trace_id, 32) && hex(span_id, 16) && hex(flags, 2)) {
+            return Err(invalid());
+        }
+        let version = u8::from_str_radix(version, 16).map_err(|_| invalid())?;
+        if version == 0xff || (version == 0 && parts.next().is_some()) {
+            return Err(invalid());
+        }
+        let trace_id = u128::from_str_radix(trace_id, 16).map_err(|_| invalid())?;
+        let span_id = u64::from_str_radix(span_id, 16).map_err(|_| invalid())?;
+        let flags = u8::from_str_radix(flags, 16).map_err(|_| invalid())?;
+        if trace_id == 0 || span_id == 0 {
+            return Err(invalid());
+        }
+        Ok(TraceContext {
+            trace_id,
+            span_id,
+            sampled: flags & SAMPLED != 0,
+        })
+    }
+}
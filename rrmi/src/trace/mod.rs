@@ -0,0 +1,10 @@
+#[cfg(feature = "tracing")]
+mod chrome;
+mod context;
+mod tests;
+
+#[cfg(feature = "tracing")]
+pub(crate) use chrome::{CALL_SPAN, SERVE_SPAN};
+#[cfg(feature = "tracing")]
+pub use chrome::{merge_chrome_traces, read_chrome_trace};
+pub use context::{TRACEPARENT_HEADER, TraceContext};
@@ -0,0 +1,74 @@
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests {
+    use crate::trace::TraceContext;
+
+    #[test]
+    fn traceparent_round_trip() {
+        let value = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+        let trace: TraceContext = value.parse().expect("example from the spec");
+        assert_eq!(trace.trace_id, 0x4bf92f3577b34da6a3ce929d0e0e4736);
+        assert_eq!(trace.span_id, 0x00f067aa0ba902b7);
+        assert!(trace.sampled);
+        assert_eq!(trace.to_string(), value);
+
+        let child = trace.child();
+        assert_eq!(child.trace_id, trace.trace_id);
+        assert_ne!(child.span_id, trace.span_id);
+        assert_ne!(TraceContext::root().trace_id, TraceContext::root().trace_id);
+    }
+
+    #[test]
+    fn traceparent_invalid() {
+        for value in [
+            "",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e473-00f067aa0ba902b7-01",
+            "00-+bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+        ] {
+            assert!(value.parse::<TraceContext>().is_err(), "{value} is invalid");
+        }
+        // later versions may add fields
+        let future = "01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-00-extra";
+        assert!(!future.parse::<TraceContext>().expect("read as 00").sampled);
+    }
+
+    #[test]
+    #[cfg(feature = "tracing")]
+    fn merge_lines_up_clocks() {
+        use crate::trace::merge_chrome_traces;
+        use serde_json::json;
+
+        let client = vec![
+            json!({"ph": "B", "pid": 1, "tid": 1, "ts": 100.0, "name": "rrmi.call",
+                   "args": {"span_id": "aa"}}),
+            json!({"ph": "E", "pid": 1, "tid": 1, "ts": 200.0, "name": "rrmi.call"}),
+        ];
+        // the server started tracing a second earlier
+        let server = vec![
+            json!({"ph": "M", "pid": 1, "tid": 3, "name": "thread_name",
+                   "args": {"name": "Skeleton"}}),
+            json!({"ph": "B", "pid": 1, "tid": 3, "ts": 1140.0, "name": "rrmi.serve",
+                   "args": {"parent_id": "aa"}}),
+            json!({"ph": "E", "pid": 1, "tid": 3, "ts": 1160.0, "name": "rrmi.serve"}),
+        ];
+        let merged = merge_chrome_traces(vec![
+            ("client".to_string(), client),
+            ("server".to_string(), server),
+        ]);
+        assert_eq!(merged.len(), 7);
+        assert_eq!(merged[0]["name"], "process_name");
+        assert_eq!(merged[0]["args"]["name"], "client");
+        assert_eq!(merged[3]["args"]["name"], "server");
+        let serve: Vec<_> = merged.iter().filter(|e| e["pid"] == 2).collect();
+        assert_eq!(serve[2]["ts"], 140.0);
+        assert_eq!(serve[3]["ts"], 160.0);
+        // events without a timestamp are left alone
+        assert!(serve[1]["ts"].is_null());
+        assert_eq!(merged[1]["ts"], 100.0);
+    }
+}
@@ -0,0 +1,157 @@
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::error::RMIError;
+use crate::remote::{RMIResult, RemoteRef};
+use crate::stub::{Deserialize, Serialize};
+use crate::transport::memory::MemoryTransport;
+#[cfg(target_os = "linux")]
+use crate::transport::shm::ShmTransport;
+use crate::transport::tcp::{SocketAddr, TcpClient};
+#[cfg(feature = "tls")]
+use crate::transport::tls::{TlsClientConfig, TlsTransport};
+#[cfg(unix)]
+use crate::transport::unix::UnixTransport;
+use crate::transport::{PreSharedKey, Transport};
+
+/// Where a remote object can be reached.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Endpoint {
+    Tcp(SocketAddr),
+    /// TCP wrapped in TLS, clients need a `TlsClientConfig` to connect
+    Tls(SocketAddr),
+    /// Unix domain socket, only reachable from the same host
+    Unix(PathBuf),
+    /// Unix domain socket to negotiate a shared memory ring on, only reachable from the same host
+    Shm(PathBuf),
+    /// In-process channels, only reachable from the same process
+    Memory(String),
+}
+
+impl From<SocketAddr> for Endpoint {
+    fn from(addr: SocketAddr) -> Self {
+        Endpoint::Tcp(addr)
+    }
+}
+
+impl From<PathBuf> for Endpoint {
+    fn from(path: PathBuf) -> Self {
+        Endpoint::Unix(path)
+    }
+}
+
+impl Display for Endpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Endpoint::Tcp(addr) => write!(f, "tcp://{addr}"),
+            Endpoint::Tls(addr) => write!(f, "tls://{addr}"),
+            Endpoint::Unix(path) => write!(f, "unix://{}", path.display()),
+            Endpoint::Shm(path) => write!(f, "shm://{}", path.display()),
+            Endpoint::Memory(name) => write!(f, "mem://{name}"),
+        }
+    }
+}
+
+/// A connection to a remote object over whichever transport its endpoint asks for.
+#[derive(Debug)]
+pub enum Client {
+    Tcp(TcpClient),
+    #[cfg(feature = "tls")]
+    Tls(TlsTransport),
+    #[cfg(unix)]
+    Unix(UnixTransport),
+    #[cfg(target_os = "linux")]
+    Shm(ShmTransport),
+    Memory(MemoryTransport),
+}
+
+impl Client {
+    pub fn connect(remote: &RemoteRef) -> RMIResult<Self> {
+        match &remote.addr {
+            Endpoint::Tcp(addr) => TcpClient::connect(*addr).map(Client::Tcp),
+            Endpoint::Tls(addr) => Err(needs_tls(addr)),
+            #[cfg(unix)]
+            Endpoint::Unix(path) => UnixTransport::connect(path).map(Client::Unix),
+            #[cfg(not(unix))]
+            Endpoint::Unix(path) => Err(unsupported(path)),
+            #[cfg(target_os = "linux")]
+            Endpoint::Shm(path) => ShmTransport::connect(path).map(Client::Shm),
+            #[cfg(not(target_os = "linux"))]
+            Endpoint::Shm(path) => Err(unsupported(path)),
+            Endpoint::Memory(name) => MemoryTransport::connect(name).map(Client::Memory),
+        }
+    }
+
+    pub fn connect_timeout(remote: &RemoteRef, timeout: Duration) -> RMIResult<Self> {
+        match &remote.addr {
+            Endpoint::Tcp(addr) => TcpClient::connect_timeout(*addr, timeout).map(Client::Tcp),
+            Endpoint::Tls(addr) => Err(needs_tls(addr)),
+            #[cfg(unix)]
+            Endpoint::Unix(path) => UnixTransport::connect_timeout(path, timeout).map(Client::Unix),
+            #[cfg(not(unix))]
+            Endpoint::Unix(path) => Err(unsupported(path)),
+            #[cfg(target_os = "linux")]
+            Endpoint::Shm(path) => ShmTransport::connect_timeout(path, timeout).map(Client::Shm),
+            #[cfg(not(target_os = "linux"))]
+            Endpoint::Shm(path) => Err(unsupported(path)),
+            // nothing to time out on, the listener is in this process or not at all
+            Endpoint::Memory(name) => MemoryTransport::connect(name).map(Client::Memory),
+        }
+    }
+
+    /// Like `connect`, or `connect_timeout` when given a timeout, then proves knowledge of `key`
+    /// to TCP servers. The other transports do not leave the host and skip the handshake.
+    pub fn connect_psk(
+        remote: &RemoteRef,
+        key: &PreSharedKey,
+        timeout: Option<Duration>,
+    ) -> RMIResult<Self> {
+        let client = match timeout {
+            Some(timeout) => Self::connect_timeout(remote, timeout)?,
+            None => Self::connect(remote)?,
+        };
+        if let Client::Tcp(tcp) = &client {
+            tcp.authenticate(key)?;
+        }
+        Ok(client)
+    }
+
+    /// Like `connect`, or `connect_timeout` when given a timeout, but also reaches TLS endpoints.
+    #[cfg(feature = "tls")]
+    pub fn connect_tls(
+        remote: &RemoteRef,
+        tls: &TlsClientConfig,
+        timeout: Option<Duration>,
+    ) -> RMIResult<Self> {
+        match (&remote.addr, timeout) {
+            (Endpoint::Tls(addr), _) => TlsTransport::connect(*addr, tls, timeout).map(Client::Tls),
+            (_, Some(timeout)) => Self::connect_timeout(remote, timeout),
+            (_, None) => Self::connect(remote),
+        }
+    }
+}
+
+fn needs_tls(addr: &SocketAddr) -> RMIError {
+    RMIError::Tls(format!("{addr} only takes TLS connections"))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn unsupported(path: &std::path::Path) -> RMIError {
+    RMIError::TransportError(format!("Transport unsupported here: {}", path.display()))
+}
+
+impl Transport for Client {
+    fn call(&self, request: Vec<u8>) -> RMIResult<Vec<u8>> {
+        match self {
+            Client::Tcp(client) => client.call(request),
+            #[cfg(feature = "tls")]
+            Client::Tls(client) => client.call(request),
+            #[cfg(unix)]
+            Client::Unix(client) => client.call(request),
+            #[cfg(target_os = "linux")]
+            Client::Shm(client) => client.call(request),
+            Client::Memory(client) => client.call(request),
+        }
+    }
+}
@@ -0,0 +1,207 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Debug;
+use std::io::{ErrorKind, Read, Write};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::Duration;
+
+use crate::error::RMIError;
+use crate::remote::RMIResult;
+use crate::transport::Transport;
+use crate::transport::stream::{Closer, Connection, exchange};
+
+// name -> listener, the in-process equivalent of ports and socket paths
+static NAMESPACE: LazyLock<Mutex<HashMap<String, Sender<MemoryStream>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+static NEXT_NAME: AtomicUsize = AtomicUsize::new(0);
+// how often a reader waiting on an idle stream checks whether it was closed on its side
+const CLOSE_POLL: Duration = Duration::from_millis(100);
+
+/// A fresh in-process endpoint name starting with `prefix`.
+pub fn memory_name(prefix: &str) -> String {
+    let n = NEXT_NAME.fetch_add(1, Ordering::Relaxed);
+    format!("{prefix}/{n}")
+}
+
+/// One end of an in-process byte pipe, bytes written on one end are read on the other.
+pub struct MemoryStream {
+    name: String,
+    tx: Sender<Vec<u8>>,
+    inbox: Mutex<Inbox>,
+}
+
+struct Inbox {
+    rx: Receiver<Vec<u8>>,
+    buf: VecDeque<u8>,
+    closed: Arc<AtomicBool>,
+}
+
+impl Inbox {
+    /// Blocks until there is something to read, false once either end is closed.
+    fn fill(&mut self) -> bool {
+        while self.buf.is_empty() {
+            match self.rx.recv_timeout(CLOSE_POLL) {
+                Ok(chunk) => self.buf.extend(chunk),
+                Err(RecvTimeoutError::Timeout) if !self.closed.load(Ordering::Relaxed) => (),
+                Err(_) => return false,
+            }
+        }
+        true
+    }
+}
+
+impl MemoryStream {
+    pub fn pair(name: &str) -> (MemoryStream, MemoryStream) {
+        let (a_tx, b_rx) = mpsc::channel();
+        let (b_tx, a_rx) = mpsc::channel();
+        let end = |tx, rx| MemoryStream {
+            name: name.to_string(),
+            tx,
+            inbox: Mutex::new(Inbox {
+                rx,
+                buf: VecDeque::new(),
+                closed: Arc::new(AtomicBool::new(false)),
+            }),
+        };
+        (end(a_tx, a_rx), end(b_tx, b_rx))
+    }
+
+    /// Connects to the `MemoryListener` bound at `name` in this process.
+    pub fn connect(name: &str) -> RMIResult<MemoryStream> {
+        let namespace = NAMESPACE
+            .lock()
+            .expect("Memory transport: unable to get namespace lock");
+        let listener = namespace.get(name).ok_or(RMIError::TransportError(format!(
+            "Nothing listening in process at {name}"
+        )))?;
+        let (client, server) = MemoryStream::pair(name);
+        listener
+            .send(server)
+            .map_err(|_| RMIError::TransportError(format!("{name}: listener is gone")))?;
+        Ok(client)
+    }
+}
+
+impl Read for MemoryStream {
+    fn read(&mut self, out: &mut [u8]) -> std::io::Result<usize> {
+        let inbox = self
+            .inbox
+            .get_mut()
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+        if !inbox.fill() {
+            return Ok(0);
+        }
+        let n = out.len().min(inbox.buf.len());
+        for (dst, src) in out.iter_mut().zip(inbox.buf.drain(..n)) {
+            *dst = src;
+        }
+        Ok(n)
+    }
+}
+
+impl Write for MemoryStream {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.tx
+            .send(buf.to_vec())
+            .map_err(|_| std::io::Error::from(ErrorKind::BrokenPipe))?;
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Connection for MemoryStream {
+    fn readable(&self) -> std::io::Result<bool> {
+        let mut inbox = self
+            .inbox
+            .lock()
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+        Ok(inbox.fill())
+    }
+    fn closer(&self) -> Option<Closer> {
+        let closed = Arc::clone(&self.inbox.lock().ok()?.closed);
+        Some(Box::new(move || closed.store(true, Ordering::Relaxed)))
+    }
+    fn in_process(&self) -> bool {
+        true
+    }
+}
+
+impl Debug for MemoryStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "MemoryStream[{}]", self.name)
+    }
+}
+
+/// Accepts `MemoryStream`s connecting to `name`, which is free again once this is dropped.
+#[derive(Debug)]
+pub struct MemoryListener {
+    name: String,
+    rx: Receiver<MemoryStream>,
+}
+
+impl MemoryListener {
+    pub fn bind(name: &str) -> RMIResult<MemoryListener> {
+        let mut namespace = NAMESPACE
+            .lock()
+            .expect("Memory transport: unable to get namespace lock");
+        if namespace.contains_key(name) {
+            return Err(RMIError::TransportError(format!(
+                "{name} already bound in process"
+            )));
+        }
+        let (tx, rx) = mpsc::channel();
+        namespace.insert(name.to_string(), tx);
+        Ok(MemoryListener {
+            name: name.to_string(),
+            rx,
+        })
+    }
+
+    pub fn accept(&self) -> std::io::Result<MemoryStream> {
+        self.rx
+            .recv()
+            .map_err(|_| std::io::Error::from(ErrorKind::NotConnected))
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Drop for MemoryListener {
+    fn drop(&mut self) {
+        if let Ok(mut namespace) = NAMESPACE.lock() {
+            namespace.remove(&self.name);
+        }
+    }
+}
+
+/// Client side of the in-process transport: full marshal/unmarshal path, no sockets.
+#[derive(Debug)]
+pub struct MemoryTransport {
+    stream: RefCell<MemoryStream>,
+}
+
+impl MemoryTransport {
+    pub fn new(name: &str) -> Self {
+        Self::connect(name).expect("Could not connect to server")
+    }
+
+    pub fn connect(name: &str) -> RMIResult<Self> {
+        let stream = MemoryStream::connect(name)?;
+        Ok(Self {
+            stream: RefCell::new(stream),
+        })
+    }
+}
+
+impl Transport for MemoryTransport {
+    fn call(&self, request: Vec<u8>) -> RMIResult<Vec<u8>> {
+        let mut stream = self.stream.borrow_mut();
+        exchange(&mut *stream, request)
+    }
+}
@@ -1,12 +1,38 @@
+mod endpoint;
+mod memory;
+mod psk;
+#[cfg(target_os = "linux")]
+mod shm;
+mod stream;
 mod tcp;
 mod tests;
+#[cfg(feature = "tls")]
+mod tls;
+#[cfg(unix)]
+mod unix;
 pub mod utils;
 use std::fmt::Debug;
 
+use std::collections::HashMap;
+
 use crate::RMI_ID;
 use crate::remote::RMIResult;
-use crate::stub::{Deserialize, Serialize};
-pub use tcp::{IpAddr, SocketAddr, TcpClient, TcpListener, TcpStream, receive_data, send_data};
+use crate::stub::{Deserialize, Serialize, marshal, unmarshal};
+pub use endpoint::{Client, Endpoint};
+pub use memory::{MemoryListener, MemoryStream, MemoryTransport, memory_name};
+pub use psk::PreSharedKey;
+#[cfg(target_os = "linux")]
+pub use shm::{ShmStream, ShmTransport};
+pub use stream::{Closer, Connection, receive_data, send_data};
+pub use tcp::{IpAddr, SocketAddr, TcpClient, TcpStream};
+#[cfg(feature = "tls")]
+pub(crate) use tls::set_peer_identity;
+#[cfg(feature = "tls")]
+pub use tls::{
+    PeerIdentity, TlsClientConfig, TlsServerConfig, TlsStream, TlsTransport, peer_identity,
+};
+#[cfg(unix)]
+pub use unix::{UnixListener, UnixStream, UnixTransport, unix_socket_path};
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[allow(dead_code)]
@@ -26,24 +52,37 @@ impl Default for RMIRequest {
     }
 }
 
-#[cfg(feature = "tracing")]
-pub trait Transport {
-    fn send<
-        REQ: Serialize + for<'de> Deserialize<'de> + Debug,
-        RES: Serialize + for<'de> Deserialize<'de> + Debug,
-    >(
-        &self,
-        req: REQ,
-    ) -> RMIResult<RES>;
+/// Out-of-band data sent along with a call, like trace ids or tenants.
+pub type Headers = HashMap<String, String>;
+
+/// Header with the milliseconds a caller is willing to wait, becomes the call context deadline.
+pub const TIMEOUT_HEADER: &str = "rrmi-timeout-ms";
+
+/// A request to a remote object together with its headers, as stubs send it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Envelope<T> {
+    pub headers: Headers,
+    pub body: T,
 }
 
+/// Anything that can be sent as a request or a response.
+#[cfg(feature = "tracing")]
+pub trait Message: Serialize + for<'de> Deserialize<'de> + Debug {}
+#[cfg(feature = "tracing")]
+impl<T: Serialize + for<'de> Deserialize<'de> + Debug> Message for T {}
+
+/// Anything that can be sent as a request or a response.
+#[cfg(not(feature = "tracing"))]
+pub trait Message: Serialize + for<'de> Deserialize<'de> {}
 #[cfg(not(feature = "tracing"))]
+impl<T: Serialize + for<'de> Deserialize<'de>> Message for T {}
+
 pub trait Transport {
-    fn send<
-        REQ: Serialize + for<'de> Deserialize<'de>,
-        RES: Serialize + for<'de> Deserialize<'de>,
-    >(
-        &self,
-        req: REQ,
-    ) -> RMIResult<RES>;
+    /// One round trip of an already marshalled request, returns the marshalled response.
+    fn call(&self, request: Vec<u8>) -> RMIResult<Vec<u8>>;
+
+    fn send<REQ: Message, RES: Message>(&self, req: REQ) -> RMIResult<RES> {
+        let response = self.call(marshal(&req)?)?;
+        unmarshal(&response)
+    }
 }
@@ -0,0 +1,163 @@
+use std::fmt::Debug;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use hmac::{Hmac, KeyInit, Mac};
+use sha2::Sha256;
+
+use crate::error::RMIError;
+use crate::remote::RMIResult;
+
+const MAGIC: &[u8; 8] = b"rrmi-psk";
+const NONCE_LEN: usize = 32;
+const MAC_LEN: usize = 32;
+const ACCEPTED: u8 = 1;
+const REJECTED: u8 = 0;
+// a client that connects and says nothing should not hold up the accept loop
+pub(crate) const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Secret shared by a registry and its clients, proven with HMAC-SHA256 over fresh nonces
+/// on every connection so it never goes over the wire.
+///
+/// Client and server prove knowledge of the key to each other before any request is read:
+///
+/// 1. client: `"rrmi-psk"`, client nonce
+/// 2. server: server nonce, HMAC(key, "server" | client nonce | server nonce)
+/// 3. client: HMAC(key, "client" | client nonce | server nonce)
+/// 4. server: 1 if the client proof checks out, 0 and hang up otherwise
+#[derive(Clone)]
+pub struct PreSharedKey {
+    key: Arc<[u8]>,
+}
+
+impl PreSharedKey {
+    pub fn new(key: impl AsRef<[u8]>) -> Self {
+        PreSharedKey {
+            key: Arc::from(key.as_ref()),
+        }
+    }
+
+    /// Reads the key from a file, without the trailing newline if there is one.
+    pub fn from_file(path: impl AsRef<Path>) -> RMIResult<Self> {
+        let path = path.as_ref();
+        let mut key = std::fs::read(path)
+            .map_err(|e| RMIError::IoError(format!("{}: {e}", path.display())))?;
+        while key.last().is_some_and(|b| *b == b'\n' || *b == b'\r') {
+            key.pop();
+        }
+        if key.is_empty() {
+            return Err(RMIError::Unauthorized(format!(
+                "{}: empty key",
+                path.display()
+            )));
+        }
+        Ok(Self::new(key))
+    }
+
+    fn mac(&self, label: &[u8], client_nonce: &[u8], server_nonce: &[u8]) -> Hmac<Sha256> {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC takes keys of any size");
+        mac.update(label);
+        mac.update(client_nonce);
+        mac.update(server_nonce);
+        mac
+    }
+
+    fn proof(&self, label: &[u8], client_nonce: &[u8], server_nonce: &[u8]) -> Vec<u8> {
+        self.mac(label, client_nonce, server_nonce)
+            .finalize()
+            .into_bytes()
+            .to_vec()
+    }
+
+    fn verify(&self, label: &[u8], client_nonce: &[u8], server_nonce: &[u8], proof: &[u8]) -> bool {
+        self.mac(label, client_nonce, server_nonce)
+            .verify_slice(proof)
+            .is_ok()
+    }
+
+    /// Client side of the handshake, `Unauthorized` when either side does not know the key.
+    pub fn authenticate<S: Read + Write + ?Sized>(&self, stream: &mut S) -> RMIResult<()> {
+        let io_err = |e: std::io::Error| RMIError::TransportError(e.to_string());
+        let client_nonce = nonce()?;
+        let mut hello = MAGIC.to_vec();
+        hello.extend_from_slice(&client_nonce);
+        stream.write_all(&hello).map_err(io_err)?;
+
+        let mut server_hello = [0u8; NONCE_LEN + MAC_LEN];
+        stream.read_exact(&mut server_hello).map_err(|e| {
+            RMIError::Unauthorized(format!("server did not answer the handshake: {e}"))
+        })?;
+        let (server_nonce, server_proof) = server_hello.split_at(NONCE_LEN);
+        if !self.verify(b"server", &client_nonce, server_nonce, server_proof) {
+            return Err(RMIError::Unauthorized(
+                "server does not know the key".to_string(),
+            ));
+        }
+        let proof = self.proof(b"client", &client_nonce, server_nonce);
+        stream.write_all(&proof).map_err(io_err)?;
+
+        let mut verdict = [REJECTED];
+        let _ = stream.read_exact(&mut verdict);
+        match verdict[0] {
+            ACCEPTED => Ok(()),
+            _ => Err(RMIError::Unauthorized("rejected by server".to_string())),
+        }
+    }
+
+    /// Server side of the handshake, nothing the client sends is decoded unless this succeeds.
+    pub fn accept(&self, stream: &mut TcpStream) -> RMIResult<()> {
+        let io_err = |e: std::io::Error| RMIError::TransportError(e.to_string());
+        let previous = stream.read_timeout().map_err(io_err)?;
+        stream
+            .set_read_timeout(Some(HANDSHAKE_TIMEOUT))
+            .map_err(io_err)?;
+        let result = self.challenge(stream);
+        stream.set_read_timeout(previous).map_err(io_err)?;
+        result
+    }
+
+    fn challenge<S: Read + Write + ?Sized>(&self, stream: &mut S) -> RMIResult<()> {
+        let io_err = |e: std::io::Error| RMIError::Unauthorized(e.to_string());
+        // magic first so clients talking plain CBOR are turned away without waiting on them
+        let mut magic = [0u8; MAGIC.len()];
+        stream.read_exact(&mut magic).map_err(io_err)?;
+        if &magic != MAGIC {
+            return Err(RMIError::Unauthorized(
+                "client did not start a handshake".to_string(),
+            ));
+        }
+        let mut client_nonce = [0u8; NONCE_LEN];
+        stream.read_exact(&mut client_nonce).map_err(io_err)?;
+        let client_nonce = &client_nonce[..];
+        let server_nonce = nonce()?;
+        let mut server_hello = server_nonce.to_vec();
+        server_hello.extend(self.proof(b"server", client_nonce, &server_nonce));
+        stream.write_all(&server_hello).map_err(io_err)?;
+
+        let mut proof = [0u8; MAC_LEN];
+        stream.read_exact(&mut proof).map_err(io_err)?;
+        if !self.verify(b"client", client_nonce, &server_nonce, &proof) {
+            let _ = stream.write_all(&[REJECTED]);
+            return Err(RMIError::Unauthorized(
+                "client does not know the key".to_string(),
+            ));
+        }
+        stream.write_all(&[ACCEPTED]).map_err(io_err)
+    }
+}
+
+impl Debug for PreSharedKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PreSharedKey[{} bytes]", self.key.len())
+    }
+}
+
+fn nonce() -> RMIResult<[u8; NONCE_LEN]> {
+    let mut nonce = [0u8; NONCE_LEN];
+    getrandom::fill(&mut nonce).map_err(|e| RMIError::IoError(e.to_string()))?;
+    Ok(nonce)
+}
@@ -0,0 +1,516 @@
+use std::cell::RefCell;
+use std::fmt::Debug;
+use std::fs::File;
+use std::io::{ErrorKind, Read, Write};
+use std::mem::{MaybeUninit, size_of};
+use std::net::Shutdown;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::ptr;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::time::Duration;
+
+use socket2::SockRef;
+
+use crate::error::RMIError;
+use crate::remote::RMIResult;
+use crate::transport::Transport;
+use crate::transport::stream::{Closer, Connection, exchange};
+
+// bytes in flight per direction, a power of two so positions wrap with a mask
+const RING_CAPACITY: usize = 1 << 20;
+const MAGIC: u64 = u64::from_be_bytes(*b"rrmi-shm");
+// iterations to spin on an empty/full ring before sleeping on the futex
+const SPINS: usize = 256;
+// how often a sleeping end checks that the other process is still alive
+const LIVENESS_POLL: Duration = Duration::from_millis(100);
+// the region cannot change size once the skeleton checked it
+const SEALS: libc::c_int = libc::F_SEAL_SHRINK | libc::F_SEAL_GROW;
+
+#[repr(C, align(64))]
+struct Header {
+    magic: u64,
+    capacity: u64,
+}
+
+/// One direction of the connection, a single producer single consumer byte queue.
+#[repr(C, align(64))]
+struct Ring {
+    head: AtomicU64, // bytes consumed
+    tail: AtomicU64, // bytes produced
+    seq: AtomicU32,  // futex word, bumped whenever head or tail moves
+    waiters: AtomicU32,
+    closed: AtomicU32,
+}
+
+// [Header][Ring 0][Ring 1][data 0][data 1], ring 0 goes from client to server
+const RINGS_OFFSET: usize = size_of::<Header>();
+const DATA_OFFSET: usize = RINGS_OFFSET + 2 * size_of::<Ring>();
+const fn region_len(capacity: usize) -> usize {
+    DATA_OFFSET + 2 * capacity
+}
+
+struct Mapping {
+    ptr: *mut u8,
+    len: usize,
+}
+
+// the mapping is only touched through atomics and the ring protocol
+unsafe impl Send for Mapping {}
+unsafe impl Sync for Mapping {}
+
+impl Mapping {
+    fn new(file: &File, len: usize) -> std::io::Result<Mapping> {
+        let ptr = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(Mapping {
+            ptr: ptr.cast(),
+            len,
+        })
+    }
+
+    fn header(&self) -> &Header {
+        unsafe { &*self.ptr.cast::<Header>() }
+    }
+
+    fn header_mut(&mut self) -> &mut Header {
+        unsafe { &mut *self.ptr.cast::<Header>() }
+    }
+
+    fn ring(&self, i: usize) -> &Ring {
+        unsafe { &*self.ptr.add(RINGS_OFFSET).cast::<Ring>().add(i) }
+    }
+
+    fn data(&self, i: usize, capacity: usize) -> *mut u8 {
+        unsafe { self.ptr.add(DATA_OFFSET + i * capacity) }
+    }
+}
+
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        unsafe { libc::munmap(self.ptr.cast(), self.len) };
+    }
+}
+
+fn futex_wait(word: &AtomicU32, expected: u32, timeout: Duration) {
+    let ts = libc::timespec {
+        tv_sec: timeout.as_secs() as libc::time_t,
+        tv_nsec: timeout.subsec_nanos() as libc::c_long,
+    };
+    // not FUTEX_PRIVATE: the word is shared with another process
+    unsafe {
+        libc::syscall(
+            libc::SYS_futex,
+            word.as_ptr(),
+            libc::FUTEX_WAIT,
+            expected,
+            &ts as *const libc::timespec,
+            ptr::null::<u32>(),
+            0,
+        )
+    };
+}
+
+fn futex_wake(word: &AtomicU32) {
+    unsafe {
+        libc::syscall(
+            libc::SYS_futex,
+            word.as_ptr(),
+            libc::FUTEX_WAKE,
+            i32::MAX,
+            ptr::null::<libc::timespec>(),
+            ptr::null::<u32>(),
+            0,
+        )
+    };
+}
+
+/// A connection whose bytes go through a memfd mapped by both processes.
+///
+/// The Unix socket it was negotiated on stays open only to notice when the other side dies.
+pub struct ShmStream {
+    map: Mapping,
+    capacity: usize,
+    tx: usize,
+    rx: usize,
+    peer: UnixStream,
+}
+
+impl ShmStream {
+    /// Client side: sets up the shared region and hands it to the skeleton listening at `path`.
+    pub fn connect(path: &Path, timeout: Option<Duration>) -> RMIResult<ShmStream> {
+        ShmStream::offer(path, timeout, RING_CAPACITY as u64, SEALS)
+    }
+
+    /// Like `connect`, with a region claiming `capacity` and sealed with `seals`.
+    #[cfg(test)]
+    pub(crate) fn connect_forged(
+        path: &Path,
+        capacity: u64,
+        seals: libc::c_int,
+    ) -> RMIResult<ShmStream> {
+        ShmStream::offer(path, None, capacity, seals)
+    }
+
+    fn offer(
+        path: &Path,
+        timeout: Option<Duration>,
+        capacity: u64,
+        seals: libc::c_int,
+    ) -> RMIResult<ShmStream> {
+        let err = |e: std::io::Error| RMIError::TransportError(format!("{}: {e}", path.display()));
+        let peer = UnixStream::connect(path).map_err(err)?;
+        peer.set_read_timeout(timeout).map_err(err)?;
+        peer.set_write_timeout(timeout).map_err(err)?;
+        let file = memfd().map_err(err)?;
+        let len = region_len(RING_CAPACITY);
+        file.set_len(len as u64).map_err(err)?;
+        if unsafe { libc::fcntl(file.as_raw_fd(), libc::F_ADD_SEALS, seals) } < 0 {
+            return Err(err(std::io::Error::last_os_error()));
+        }
+        let mut map = Mapping::new(&file, len).map_err(err)?;
+        let header = map.header_mut();
+        header.magic = MAGIC;
+        header.capacity = capacity;
+        send_fd(&peer, file.as_raw_fd()).map_err(err)?;
+        // the skeleton answers once it mapped the region
+        let mut ack = [0u8; 1];
+        (&peer).read_exact(&mut ack).map_err(err)?;
+        ShmStream::new(map, RING_CAPACITY, 0, peer).map_err(err)
+    }
+
+    /// Server side: takes the next client of `listener` and maps the region it sends.
+    pub fn accept(listener: &UnixListener) -> std::io::Result<ShmStream> {
+        let (mut peer, _) = listener.accept()?;
+        let file = File::from(recv_fd(&peer)?);
+        // unsealed, the client could shrink it under the mapping
+        let seals = unsafe { libc::fcntl(file.as_raw_fd(), libc::F_GET_SEALS) };
+        if seals < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        if seals & SEALS != SEALS {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidData,
+                "region not sealed",
+            ));
+        }
+        let len = file.metadata()?.len() as usize;
+        if len < region_len(RING_CAPACITY) {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidData,
+                "region too small",
+            ));
+        }
+        let map = Mapping::new(&file, len)?;
+        let header = map.header();
+        if header.magic != MAGIC || header.capacity != RING_CAPACITY as u64 {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidData,
+                "not an rrmi region",
+            ));
+        }
+        peer.write_all(&[1])?;
+        ShmStream::new(map, RING_CAPACITY, 1, peer)
+    }
+
+    fn new(map: Mapping, capacity: usize, tx: usize, peer: UnixStream) -> std::io::Result<Self> {
+        peer.set_nonblocking(true)?;
+        Ok(ShmStream {
+            map,
+            capacity,
+            tx,
+            rx: 1 - tx,
+            peer,
+        })
+    }
+
+    fn peer_gone(&self) -> bool {
+        let mut buf = [MaybeUninit::<u8>::uninit(); 1];
+        match SockRef::from(&self.peer).peek(&mut buf) {
+            Ok(0) => true,
+            Ok(_) => false,
+            Err(e) => e.kind() != ErrorKind::WouldBlock,
+        }
+    }
+
+    /// Blocks until `ready` holds or the ring is closed, false if it closed.
+    fn wait(&self, ring: &Ring, ready: impl Fn() -> bool) -> bool {
+        for _ in 0..SPINS {
+            if ready() {
+                return true;
+            }
+            std::hint::spin_loop();
+        }
+        loop {
+            let seq = ring.seq.load(Ordering::SeqCst);
+            ring.waiters.fetch_add(1, Ordering::SeqCst);
+            let done = ready() || ring.closed.load(Ordering::SeqCst) != 0;
+            if !done {
+                futex_wait(&ring.seq, seq, LIVENESS_POLL);
+            }
+            ring.waiters.fetch_sub(1, Ordering::SeqCst);
+            if ready() {
+                return true;
+            }
+            if ring.closed.load(Ordering::SeqCst) != 0 {
+                return false;
+            }
+            if ring.seq.load(Ordering::SeqCst) == seq && self.peer_gone() {
+                self.close();
+                return false;
+            }
+        }
+    }
+
+    fn notify(ring: &Ring) {
+        ring.seq.fetch_add(1, Ordering::SeqCst);
+        if ring.waiters.load(Ordering::SeqCst) > 0 {
+            futex_wake(&ring.seq);
+        }
+    }
+
+    fn close(&self) {
+        for i in [self.tx, self.rx] {
+            let ring = self.map.ring(i);
+            ring.closed.store(1, Ordering::SeqCst);
+            ShmStream::notify(ring);
+        }
+    }
+
+    /// How many bytes ring `i` holds, `None` when its counters make no sense: the peer
+    /// writes them and may be buggy or hostile.
+    fn fill(&self, i: usize) -> Option<u64> {
+        let ring = self.map.ring(i);
+        ring.tail
+            .load(Ordering::SeqCst)
+            .checked_sub(ring.head.load(Ordering::SeqCst))
+            .filter(|fill| *fill <= self.capacity as u64)
+    }
+
+    /// Sets the counters of the ring this end reads, or writes with `sending`, as a peer can.
+    #[cfg(test)]
+    pub(crate) fn set_counters(&self, sending: bool, head: u64, tail: u64) {
+        let ring = self.map.ring(if sending { self.tx } else { self.rx });
+        ring.head.store(head, Ordering::SeqCst);
+        ring.tail.store(tail, Ordering::SeqCst);
+    }
+
+    /// Closes the stream over a ring the peer broke.
+    fn corrupt(&self) -> std::io::Error {
+        self.close();
+        std::io::Error::new(ErrorKind::InvalidData, "shared memory ring is corrupt")
+    }
+}
+
+impl Read for ShmStream {
+    fn read(&mut self, out: &mut [u8]) -> std::io::Result<usize> {
+        if out.is_empty() {
+            return Ok(0);
+        }
+        let ring = self.map.ring(self.rx);
+        if !self.wait(ring, || self.fill(self.rx) != Some(0)) {
+            // whatever was written before closing has been read
+            return Ok(0);
+        }
+        let head = ring.head.load(Ordering::Relaxed);
+        let available = self.fill(self.rx).ok_or_else(|| self.corrupt())?;
+        let n = out.len().min(available as usize).min(self.capacity);
+        let start = head as usize & (self.capacity - 1);
+        let first = n.min(self.capacity - start);
+        let data = self.map.data(self.rx, self.capacity);
+        unsafe {
+            ptr::copy_nonoverlapping(data.add(start), out.as_mut_ptr(), first);
+            ptr::copy_nonoverlapping(data, out.as_mut_ptr().add(first), n - first);
+        }
+        ring.head
+            .store(head.wrapping_add(n as u64), Ordering::SeqCst);
+        ShmStream::notify(ring);
+        Ok(n)
+    }
+}
+
+impl Write for ShmStream {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let ring = self.map.ring(self.tx);
+        let capacity = self.capacity as u64;
+        let free = || self.fill(self.tx).map(|fill| capacity - fill);
+        if !self.wait(ring, || free() != Some(0)) || ring.closed.load(Ordering::SeqCst) != 0 {
+            return Err(std::io::Error::from(ErrorKind::BrokenPipe));
+        }
+        let tail = ring.tail.load(Ordering::Relaxed);
+        let free = free().ok_or_else(|| self.corrupt())?;
+        let n = buf.len().min(free as usize).min(self.capacity);
+        let start = tail as usize & (self.capacity - 1);
+        let first = n.min(self.capacity - start);
+        let data = self.map.data(self.tx, self.capacity);
+        unsafe {
+            ptr::copy_nonoverlapping(buf.as_ptr(), data.add(start), first);
+            ptr::copy_nonoverlapping(buf.as_ptr().add(first), data, n - first);
+        }
+        ring.tail
+            .store(tail.wrapping_add(n as u64), Ordering::SeqCst);
+        ShmStream::notify(ring);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Connection for ShmStream {
+    fn readable(&self) -> std::io::Result<bool> {
+        Ok(self.wait(self.map.ring(self.rx), || self.fill(self.rx) != Some(0)))
+    }
+    fn closer(&self) -> Option<Closer> {
+        // a sleeping end notices within LIVENESS_POLL that the socket is gone
+        let peer = self.peer.try_clone().ok()?;
+        Some(Box::new(move || {
+            let _ = peer.shutdown(Shutdown::Both);
+        }))
+    }
+    fn same_host(&self) -> bool {
+        true
+    }
+}
+
+impl Drop for ShmStream {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+impl Debug for ShmStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "ShmStream[{}B, fd {}]",
+            self.capacity,
+            self.peer.as_raw_fd()
+        )
+    }
+}
+
+fn memfd() -> std::io::Result<File> {
+    let fd = unsafe {
+        libc::memfd_create(
+            c"rrmi".as_ptr(),
+            libc::MFD_CLOEXEC | libc::MFD_ALLOW_SEALING,
+        )
+    };
+    if fd < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(unsafe { File::from_raw_fd(fd) })
+}
+
+// room for one cmsghdr carrying one fd, u64s to keep it aligned
+type ControlBuf = [u64; 4];
+
+fn send_fd(sock: &UnixStream, fd: RawFd) -> std::io::Result<()> {
+    let mut byte = [0u8; 1];
+    let mut iov = libc::iovec {
+        iov_base: byte.as_mut_ptr().cast(),
+        iov_len: 1,
+    };
+    let mut control: ControlBuf = [0; 4];
+    let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr().cast();
+    msg.msg_controllen = unsafe { libc::CMSG_SPACE(size_of::<RawFd>() as u32) } as _;
+    unsafe {
+        let cmsg = libc::CMSG_FIRSTHDR(&msg);
+        (*cmsg).cmsg_level = libc::SOL_SOCKET;
+        (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+        (*cmsg).cmsg_len = libc::CMSG_LEN(size_of::<RawFd>() as u32) as _;
+        ptr::write_unaligned(libc::CMSG_DATA(cmsg).cast::<RawFd>(), fd);
+    }
+    if unsafe { libc::sendmsg(sock.as_raw_fd(), &msg, 0) } < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+fn recv_fd(sock: &UnixStream) -> std::io::Result<OwnedFd> {
+    let mut byte = [0u8; 1];
+    let mut iov = libc::iovec {
+        iov_base: byte.as_mut_ptr().cast(),
+        iov_len: 1,
+    };
+    let mut control: ControlBuf = [0; 4];
+    let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr().cast();
+    msg.msg_controllen = size_of::<ControlBuf>() as _;
+    if unsafe { libc::recvmsg(sock.as_raw_fd(), &mut msg, libc::MSG_CMSG_CLOEXEC) } < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    let no_fd = || std::io::Error::new(ErrorKind::InvalidData, "expected a memfd");
+    unsafe {
+        let cmsg = libc::CMSG_FIRSTHDR(&msg);
+        if cmsg.is_null()
+            || (*cmsg).cmsg_level != libc::SOL_SOCKET
+            || (*cmsg).cmsg_type != libc::SCM_RIGHTS
+        {
+            return Err(no_fd());
+        }
+        let fd = ptr::read_unaligned(libc::CMSG_DATA(cmsg).cast::<RawFd>());
+        Ok(OwnedFd::from_raw_fd(fd))
+    }
+}
+
+/// Client side of the shared memory transport, for objects exported on the same machine.
+#[allow(unused)]
+#[derive(Debug)]
+pub struct ShmTransport {
+    server_path: PathBuf,
+    stream: RefCell<ShmStream>,
+}
+
+impl ShmTransport {
+    pub fn new(server_path: &Path) -> Self {
+        Self::connect(server_path).expect("Could not connect to server")
+    }
+
+    pub fn connect(server_path: &Path) -> RMIResult<Self> {
+        Self::with_timeout(server_path, None)
+    }
+
+    /// Connects, bounding the handshake by `timeout`. Calls themselves wait as long as the
+    /// other side is alive.
+    pub fn connect_timeout(server_path: &Path, timeout: Duration) -> RMIResult<Self> {
+        Self::with_timeout(server_path, Some(timeout))
+    }
+
+    fn with_timeout(server_path: &Path, timeout: Option<Duration>) -> RMIResult<Self> {
+        let stream = ShmStream::connect(server_path, timeout)?;
+        Ok(Self {
+            server_path: server_path.to_path_buf(),
+            stream: RefCell::new(stream),
+        })
+    }
+}
+
+impl Transport for ShmTransport {
+    fn call(&self, request: Vec<u8>) -> RMIResult<Vec<u8>> {
+        let mut stream = self.stream.borrow_mut();
+        exchange(&mut *stream, request)
+    }
+}
@@ -0,0 +1,118 @@
+use std::fmt::Debug;
+use std::io::{Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpStream};
+
+use crate::error::RMIError;
+use crate::remote::RMIResult;
+#[cfg(feature = "tls")]
+use crate::transport::PeerIdentity;
+use crate::transport::utils::is_same_host;
+
+use tracing::debug;
+#[cfg(feature = "tracing")]
+use tracing::instrument;
+
+/// Closes a connection from another thread than the one serving it, see `Connection::closer`.
+pub type Closer = Box<dyn Fn() + Send + Sync>;
+
+/// A byte stream a skeleton serves requests on, whatever transport it came from.
+pub trait Connection: Read + Write + Send + Debug {
+    /// Blocks until the next request arrives, `Ok(false)` when the other side closed the connection.
+    fn readable(&self) -> std::io::Result<bool>;
+
+    fn peer_addr(&self) -> Option<SocketAddr> {
+        None
+    }
+
+    fn local_addr(&self) -> Option<SocketAddr> {
+        None
+    }
+
+    /// Something that closes the connection from another thread, waking up whoever waits
+    /// in `readable`. Used to hang up on idle clients when shutting down.
+    fn closer(&self) -> Option<Closer> {
+        None
+    }
+
+    /// True when the other side lives in this process.
+    fn in_process(&self) -> bool {
+        false
+    }
+
+    /// True when the other side runs on this host, this process included.
+    fn same_host(&self) -> bool {
+        match (self.local_addr(), self.peer_addr()) {
+            _ if self.in_process() => true,
+            (Some(local), Some(peer)) => is_same_host(&local, &peer),
+            _ => false,
+        }
+    }
+
+    /// The certificate identity the other side presented, over mutual TLS.
+    #[cfg(feature = "tls")]
+    fn peer_identity(&self) -> Option<PeerIdentity> {
+        None
+    }
+}
+
+impl Connection for TcpStream {
+    fn readable(&self) -> std::io::Result<bool> {
+        let mut buf = [0u8; 4];
+        Ok(self.peek(&mut buf)? > 0)
+    }
+    fn peer_addr(&self) -> Option<SocketAddr> {
+        TcpStream::peer_addr(self).ok()
+    }
+    fn local_addr(&self) -> Option<SocketAddr> {
+        TcpStream::local_addr(self).ok()
+    }
+    fn closer(&self) -> Option<Closer> {
+        let stream = self.try_clone().ok()?;
+        Some(Box::new(move || {
+            let _ = stream.shutdown(Shutdown::Both);
+        }))
+    }
+}
+
+#[cfg_attr(feature = "tracing", instrument)]
+pub fn send_data<S: Write + Debug + ?Sized>(data_serial: Vec<u8>, stream: &mut S) -> RMIResult<()> {
+    let len = data_serial.len() as u32;
+    stream.write_all(&len.to_be_bytes()).map_err(|e| {
+        debug!(error = %e, "write len failed");
+        RMIError::TransportError(e.to_string())
+    })?;
+    stream.write_all(&data_serial).map_err(|e| {
+        debug!(error = %e, "write data failed");
+        RMIError::TransportError(e.to_string())
+    })?;
+    stream.flush().map_err(|e| {
+        debug!(error = %e, "flush failed");
+        RMIError::TransportError(e.to_string())
+    })?;
+    Ok(())
+}
+#[cfg_attr(feature = "tracing", instrument)]
+pub fn receive_data<S: Read + Debug + ?Sized>(stream: &mut S) -> Vec<u8> {
+    let mut len_bytes = [0u8; 4];
+    let _ = stream.read_exact(&mut len_bytes);
+    let response_len = u32::from_be_bytes(len_bytes) as usize;
+
+    let mut bytes = vec![0u8; response_len];
+    // a truncated frame, or something that is not a frame at all like a TLS alert
+    match stream.read_exact(&mut bytes) {
+        Ok(()) => bytes,
+        Err(_) => Vec::new(),
+    }
+}
+
+/// One request/response round trip on a client stream, shared by the stream transports.
+pub(crate) fn exchange<S>(stream: &mut S, request: Vec<u8>) -> RMIResult<Vec<u8>>
+where
+    S: Read + Write + Debug,
+{
+    send_data(request, stream).map_err(|e| {
+        debug!(error = %e, "send_data failed");
+        e
+    })?;
+    Ok(receive_data(stream))
+}
@@ -1,47 +1,12 @@
 use std::cell::RefCell;
-use std::fmt::Debug;
-use std::io::{Read, Write};
-pub use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener, TcpStream};
-
-use crate::stub::{Deserialize, Serialize};
+pub use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream};
+use std::time::Duration;
 
 use crate::error::RMIError;
 use crate::remote::RMIResult;
-use crate::stub::{marshal, unmarshal};
-use crate::transport::Transport;
-
-#[cfg(feature = "tracing")]
-use tracing::instrument;
+use crate::transport::stream::exchange;
+use crate::transport::{PreSharedKey, Transport};
 
-#[cfg_attr(feature = "tracing", instrument)]
-pub fn send_data(data_serial: Vec<u8>, stream: &mut TcpStream) -> RMIResult<()> {
-    let len = data_serial.len() as u32;
-    let _ = stream.write_all(&len.to_be_bytes()).map_err(|e| {
-        eprintln!("write len failed {e}");
-        RMIError::TransportError(e.to_string())
-    })?;
-    let _ = stream.write_all(&data_serial).map_err(|e| {
-        eprintln!("write data failed {e}");
-        RMIError::TransportError(e.to_string())
-    })?;
-    let _ = stream.flush().map_err(|e| {
-        eprintln!("flush failed {e}");
-        RMIError::TransportError(e.to_string())
-    })?;
-    // eprintln!("tcp data sent");
-    Ok(())
-}
-#[cfg_attr(feature = "tracing", instrument)]
-pub fn receive_data(stream: &mut TcpStream) -> Vec<u8> {
-    let mut len_bytes = [0u8; 4];
-    let _ = stream.read_exact(&mut len_bytes);
-    let response_len = u32::from_be_bytes(len_bytes) as usize;
-
-    // eprintln!("tcp reading response {response_len:?} bytes...");
-    let mut bytes = vec![0u8; response_len];
-    let _ = stream.read_exact(&mut bytes);
-    bytes
-}
 #[allow(unused)]
 #[derive(Debug)]
 pub struct TcpClient {
@@ -52,66 +17,45 @@ pub struct TcpClient {
 
 impl TcpClient {
     pub fn new(server_addr: SocketAddr) -> Self {
-        let stream = TcpStream::connect(server_addr).expect("Could not connect to server");
-        stream.set_nodelay(true).expect("Could not set NO_DELAY");
-        let address = stream
-            .local_addr()
-            .expect("Could not get stream address")
-            .clone();
+        Self::connect(server_addr).expect("Could not connect to server")
+    }
+
+    pub fn connect(server_addr: SocketAddr) -> RMIResult<Self> {
+        let stream = TcpStream::connect(server_addr)
+            .map_err(|e| RMIError::TransportError(format!("{server_addr}: {e}")))?;
+        Self::from_stream(server_addr, stream)
+    }
+
+    /// Connects giving up after `timeout`, which also bounds every read and write on the stream.
+    pub fn connect_timeout(server_addr: SocketAddr, timeout: Duration) -> RMIResult<Self> {
+        let stream = TcpStream::connect_timeout(&server_addr, timeout)
+            .map_err(|e| RMIError::TransportError(format!("{server_addr}: {e}")))?;
+        let io_err = |e: std::io::Error| RMIError::TransportError(e.to_string());
+        stream.set_read_timeout(Some(timeout)).map_err(io_err)?;
+        stream.set_write_timeout(Some(timeout)).map_err(io_err)?;
+        Self::from_stream(server_addr, stream)
+    }
+
+    /// Proves knowledge of `key` to the server, see `PreSharedKey`.
+    pub fn authenticate(&self, key: &PreSharedKey) -> RMIResult<()> {
+        key.authenticate(&mut *self.stream.borrow_mut())
+    }
+
+    fn from_stream(server_addr: SocketAddr, stream: TcpStream) -> RMIResult<Self> {
+        let io_err = |e: std::io::Error| RMIError::TransportError(e.to_string());
+        stream.set_nodelay(true).map_err(io_err)?;
+        let address = stream.local_addr().map_err(io_err)?;
         let stream = RefCell::new(stream);
-        Self {
+        Ok(Self {
             server_addr,
             stream,
             address,
-        }
+        })
     }
 }
-#[cfg(feature = "tracing")]
-impl Transport for TcpClient {
-    fn send<
-        REQ: Serialize + for<'de> Deserialize<'de> + Debug,
-        RES: Serialize + for<'de> Deserialize<'de> + Debug,
-    >(
-        &self,
-        req: REQ,
-    ) -> RMIResult<RES> {
-        // eprintln!("marshaling");
-        let request_serialized = marshal(&req)?;
-        // eprintln!("send_data");
-        let mut stream = self.stream.borrow_mut();
-        send_data(request_serialized, &mut stream).map_err(|e| {
-            eprintln!("send_data failed: {e:?}");
-            e
-        })?;
-        // eprintln!("receive_data");
-        let response_bytes = receive_data(&mut stream);
-        // eprintln!("unmarshaling");
-        let response: RES = unmarshal(&response_bytes)?;
-        Ok(response)
-    }
-}
-
-#[cfg(not(feature = "tracing"))]
 impl Transport for TcpClient {
-    fn send<
-        REQ: Serialize + for<'de> Deserialize<'de>,
-        RES: Serialize + for<'de> Deserialize<'de>,
-    >(
-        &self,
-        req: REQ,
-    ) -> RMIResult<RES> {
-        // eprintln!("marshaling");
-        let request_serialized = marshal(&req)?;
-        // eprintln!("send_data");
+    fn call(&self, request: Vec<u8>) -> RMIResult<Vec<u8>> {
         let mut stream = self.stream.borrow_mut();
-        send_data(request_serialized, &mut stream).map_err(|e| {
-            eprintln!("send_data failed: {e:?}");
-            e
-        })?;
-        // eprintln!("receive_data");
-        let response_bytes = receive_data(&mut stream);
-        // eprintln!("unmarshaling");
-        let response: RES = unmarshal(&response_bytes)?;
-        Ok(response)
+        exchange(&mut *stream, request)
     }
 }
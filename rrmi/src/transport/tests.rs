@@ -1,8 +1,17 @@
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests {
     use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
-    use crate::utils::{get_local_ifs, get_local_ips, get_tcp_socket_linear, get_tcp_socket_os};
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream};
+
+    use crate::utils::{
+        get_addr, get_local_ifs, get_local_ips, get_tcp_socket_linear, get_tcp_socket_os,
+        happy_eyeballs, is_local, resolve_addrs,
+    };
+    use crate::{RMIError, RMIResult};
+    use std::thread;
+    use std::time::Duration;
     static TOTAL: usize = 100;
     #[test]
     fn get_own_ips() {
@@ -10,6 +19,106 @@ mod tests {
         eprintln!("{:#?}", get_local_ifs());
     }
 
+    #[test]
+    fn dual_stack_listener() {
+        let listener = get_tcp_socket_os().expect("should have available ports");
+        let port = listener.local_addr().expect("bound listener").port();
+        let loopbacks = [
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+            IpAddr::V6(Ipv6Addr::LOCALHOST),
+        ];
+        for ip in loopbacks {
+            let _client = TcpStream::connect(SocketAddr::new(ip, port))
+                .unwrap_or_else(|e| panic!("{ip} should reach the listener: {e}"));
+            let (_stream, peer) = listener.accept().expect("should accept");
+            assert_eq!(peer.ip().to_canonical(), ip);
+        }
+    }
+
+    #[test]
+    fn ipv6_addr() {
+        let v6_loopback = SocketAddr::new(IpAddr::V6(Ipv6Addr::LOCALHOST), 1099);
+        assert_eq!(get_addr("::1", 1099), v6_loopback);
+        assert_eq!(get_addr("[::1]", 1099), v6_loopback);
+        let v4_loopback = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 1099);
+        assert_eq!(get_addr("127.0.0.1", 1099), v4_loopback);
+        assert!(get_addr("localhost", 1099).ip().is_loopback());
+    }
+
+    #[test]
+    fn unresolvable_host() {
+        match resolve_addrs("no-such-host.invalid", 1099) {
+            Err(RMIError::NameResolution(_)) => (),
+            other => panic!("expected NameResolution, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn happy_eyeballs_skips_dead_addresses() {
+        let listener = get_tcp_socket_os().expect("should have available ports");
+        let live_port = listener.local_addr().expect("bound listener").port();
+        let dead_port = get_tcp_socket_os()
+            .expect("should have available ports")
+            .local_addr()
+            .expect("bound listener")
+            .port(); // dropped right away so nothing listens there
+        let v4 = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let v6 = IpAddr::V6(Ipv6Addr::LOCALHOST);
+        let addrs = [
+            SocketAddr::new(v6, dead_port),
+            SocketAddr::new(v4, dead_port),
+            SocketAddr::new(v4, live_port),
+        ];
+        let connect =
+            |addr| TcpStream::connect(addr).map_err(|e| RMIError::TransportError(e.to_string()));
+        let (addr, _stream) = happy_eyeballs(&addrs, connect).expect("one address is live");
+        assert_eq!(addr, SocketAddr::new(v4, live_port));
+
+        let errors = happy_eyeballs(&addrs[..2], connect).expect_err("both addresses are dead");
+        assert_eq!(errors.len(), 2);
+    }
+
+    #[test]
+    fn happy_eyeballs_errors_in_start_order() {
+        let v4 = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let addrs = [SocketAddr::new(v4, 1), SocketAddr::new(v4, 2)];
+        // the first attempt fails last
+        let fail = |addr: SocketAddr| -> RMIResult<()> {
+            if addr.port() == 1 {
+                thread::sleep(Duration::from_millis(600));
+            }
+            Err(RMIError::TransportError(addr.to_string()))
+        };
+        let errors = happy_eyeballs(&addrs, fail).expect_err("every attempt fails");
+        let failed: Vec<SocketAddr> = errors.into_iter().map(|(addr, _)| addr).collect();
+        assert_eq!(failed, addrs);
+    }
+
+    #[test]
+    fn loopback_detection() {
+        for local in [
+            "127.0.0.1",
+            "127.1.2.3",
+            "0.0.0.0",
+            "::1",
+            "::",
+            "::ffff:127.0.0.1",
+        ] {
+            let ip: IpAddr = local.parse().expect("valid ip");
+            assert!(is_local(&ip), "{local} is this host");
+        }
+        for remote in ["10.0.0.1", "2001:db8::1", "::ffff:10.0.0.1", "fe80::1"] {
+            let ip: IpAddr = remote.parse().expect("valid ip");
+            assert!(!is_local(&ip), "{remote} is not this host");
+        }
+        let ips = get_local_ips().expect("Should be able to get ips");
+        assert!(ips.iter().all(|ip| !ip.is_loopback()));
+        assert!(ips.iter().all(|ip| match ip {
+            IpAddr::V6(v6) => !v6.is_unicast_link_local(),
+            IpAddr::V4(_) => true,
+        }));
+    }
+
     #[test]
     fn get_ports() {
         let start = SystemTime::now()
@@ -89,6 +198,132 @@ mod tests_transport {
         recv_handle.join().expect("should be able to join");
     }
 
+    #[test]
+    #[cfg(unix)]
+    fn local_unix_test() {
+        use crate::transport::{Transport, UnixListener, UnixTransport, unix_socket_path};
+
+        let path = unix_socket_path();
+        let listener = UnixListener::bind(&path).expect("should be free");
+        let recv_handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().expect("should connect");
+            let bytes = receive_data(&mut stream);
+            let req: RMIRequest = unmarshal(&bytes).expect("RMIRequest");
+            let bytes = marshal(&req.object_id).expect("int is serializable");
+            send_data(bytes, &mut stream).expect("should send");
+        });
+        let transport = UnixTransport::connect(&path).expect("listener is bound");
+        let id: usize = transport
+            .send(RMIRequest::default())
+            .expect("should get a response");
+        assert_eq!(id, RMIRequest::default().object_id);
+        recv_handle.join().expect("should be able to join");
+        std::fs::remove_file(&path).expect("socket file exists");
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn local_shm_test() {
+        use crate::transport::{
+            ShmStream, ShmTransport, Transport, UnixListener, unix_socket_path,
+        };
+
+        let path = unix_socket_path();
+        let listener = UnixListener::bind(&path).expect("should be free");
+        let recv_handle = thread::spawn(move || {
+            let mut stream = ShmStream::accept(&listener).expect("should connect");
+            // more than a ring holds, so both ends have to wait on each other
+            for _ in 0..2 {
+                let bytes = receive_data(&mut stream);
+                let big: Vec<u8> = unmarshal(&bytes).expect("Vec<u8>");
+                let bytes = marshal(&big.len()).expect("int is serializable");
+                send_data(bytes, &mut stream).expect("should send");
+            }
+            // the client is gone, reads see the end of the stream
+            assert!(receive_data(&mut stream).is_empty());
+        });
+        let transport = ShmTransport::connect(&path).expect("listener is bound");
+        for size in [3 << 20, 17] {
+            let big: Vec<u8> = (0..size).map(|i| i as u8).collect();
+            let len: usize = transport.send(big).expect("should get a response");
+            assert_eq!(len, size);
+        }
+        drop(transport);
+        recv_handle.join().expect("should be able to join");
+        std::fs::remove_file(&path).expect("socket file exists");
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn shm_corrupt_ring() {
+        use crate::transport::{ShmStream, UnixListener, unix_socket_path};
+        use std::io::{ErrorKind, Read, Write};
+
+        let path = unix_socket_path();
+        let listener = UnixListener::bind(&path).expect("should be free");
+        let server = thread::spawn(move || ShmStream::accept(&listener).expect("should connect"));
+        let mut client = ShmStream::connect(&path, None).expect("listener is bound");
+        let _server = server.join().expect("should be able to join");
+        let mut out = vec![0u8; 8 << 20];
+        // tail behind head
+        client.set_counters(false, 10, 5);
+        let e = client.read(&mut out).expect_err("ring is corrupt");
+        assert_eq!(e.kind(), ErrorKind::InvalidData);
+        // more than the ring holds, with a buffer larger than the ring
+        client.set_counters(false, 0, 1 << 40);
+        let e = client.read(&mut out).expect_err("ring is corrupt");
+        assert_eq!(e.kind(), ErrorKind::InvalidData);
+        client.set_counters(true, 1 << 40, 0);
+        assert!(client.write(&out).is_err());
+        std::fs::remove_file(&path).expect("socket file exists");
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn shm_forged_region() {
+        use crate::transport::{ShmStream, UnixListener, unix_socket_path};
+
+        let path = unix_socket_path();
+        let listener = UnixListener::bind(&path).expect("should be free");
+        let seals = libc::F_SEAL_SHRINK | libc::F_SEAL_GROW;
+        // a capacity the region length would overflow on, and a region it could resize
+        for (capacity, seals) in [(1 << 63, seals), (1 << 20, 0)] {
+            let client = thread::spawn({
+                let path = path.clone();
+                move || ShmStream::connect_forged(&path, capacity, seals).is_err()
+            });
+            let e = ShmStream::accept(&listener).expect_err("region is forged");
+            assert_eq!(e.kind(), std::io::ErrorKind::InvalidData);
+            assert!(client.join().expect("should be able to join"));
+        }
+        std::fs::remove_file(&path).expect("socket file exists");
+    }
+
+    #[test]
+    fn local_memory_test() {
+        use crate::transport::memory_name;
+        use crate::transport::{MemoryListener, MemoryTransport, Transport};
+
+        let name = memory_name("tests");
+        let listener = MemoryListener::bind(&name).expect("should be free");
+        assert!(MemoryListener::bind(&name).is_err());
+        let recv_handle = thread::spawn(move || {
+            let mut stream = listener.accept().expect("should connect");
+            let bytes = receive_data(&mut stream);
+            let req: RMIRequest = unmarshal(&bytes).expect("RMIRequest");
+            let bytes = marshal(&req.object_id).expect("int is serializable");
+            send_data(bytes, &mut stream).expect("should send");
+        });
+        let transport = MemoryTransport::connect(&name).expect("listener is bound");
+        let id: usize = transport
+            .send(RMIRequest::default())
+            .expect("should get a response");
+        assert_eq!(id, RMIRequest::default().object_id);
+        recv_handle.join().expect("should be able to join");
+        // the listener was dropped with the thread, the name is free again
+        assert!(MemoryTransport::connect(&name).is_err());
+    }
+
     #[test]
     #[ignore]
     fn remote_send() {
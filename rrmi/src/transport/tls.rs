@@ -0,0 +1,348 @@
+use std::cell::RefCell;
+use std::fmt::Debug;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use rustls::crypto::{CryptoProvider, ring};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+use rustls::server::WebPkiClientVerifier;
+use rustls::{
+    ClientConfig, ClientConnection, RootCertStore, ServerConfig, ServerConnection, StreamOwned,
+};
+use x509_parser::extensions::GeneralName;
+
+use crate::error::RMIError;
+use crate::remote::RMIResult;
+use crate::transport::Transport;
+use crate::transport::psk::HANDSHAKE_TIMEOUT;
+use crate::transport::stream::{Closer, Connection, exchange};
+
+thread_local! {
+    // set by the skeleton for the connection it is serving on this thread
+    static PEER: RefCell<Option<PeerIdentity>> = const { RefCell::new(None) };
+}
+
+/// Who is on the other end of a TLS connection, according to the certificate it presented.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerIdentity {
+    pub common_name: Option<String>,
+    pub dns_names: Vec<String>,
+    /// the whole certificate, DER encoded
+    pub certificate: Vec<u8>,
+}
+
+impl PeerIdentity {
+    fn from_der(der: &CertificateDer<'_>) -> Option<Self> {
+        let (_, cert) = x509_parser::parse_x509_certificate(der).ok()?;
+        let common_name = cert
+            .subject()
+            .iter_common_name()
+            .next()
+            .and_then(|cn| cn.as_str().ok())
+            .map(str::to_string);
+        let dns_names = match cert.subject_alternative_name() {
+            Ok(Some(san)) => san
+                .value
+                .general_names
+                .iter()
+                .filter_map(|name| match name {
+                    GeneralName::DNSName(dns) => Some(dns.to_string()),
+                    _ => None,
+                })
+                .collect(),
+            _ => vec![],
+        };
+        Some(PeerIdentity {
+            common_name,
+            dns_names,
+            certificate: der.to_vec(),
+        })
+    }
+}
+
+/// The identity of the client whose call is running on this thread, when it came over
+/// mutual TLS. Meant to be called from inside `#[remote]` methods.
+pub fn peer_identity() -> Option<PeerIdentity> {
+    PEER.with(|peer| peer.borrow().clone())
+}
+
+pub(crate) fn set_peer_identity(identity: Option<PeerIdentity>) {
+    PEER.with(|peer| *peer.borrow_mut() = identity);
+}
+
+fn provider() -> Arc<CryptoProvider> {
+    Arc::new(ring::default_provider())
+}
+
+fn tls_err(e: impl std::fmt::Display) -> RMIError {
+    RMIError::Tls(e.to_string())
+}
+
+fn load_certs(path: &Path) -> RMIResult<Vec<CertificateDer<'static>>> {
+    let certs = CertificateDer::pem_file_iter(path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| tls_err(format!("{}: {e}", path.display())))?;
+    if certs.is_empty() {
+        return Err(tls_err(format!("{}: no certificates", path.display())));
+    }
+    Ok(certs)
+}
+
+fn load_key(path: &Path) -> RMIResult<PrivateKeyDer<'static>> {
+    PrivateKeyDer::from_pem_file(path).map_err(|e| tls_err(format!("{}: {e}", path.display())))
+}
+
+fn load_roots(path: &Path) -> RMIResult<RootCertStore> {
+    let mut roots = RootCertStore::empty();
+    for cert in load_certs(path)? {
+        roots.add(cert).map_err(tls_err)?;
+    }
+    Ok(roots)
+}
+
+/// Certificate and key registries and skeletons present to their clients.
+#[derive(Debug, Clone)]
+pub struct TlsServerConfig {
+    config: Arc<ServerConfig>,
+}
+
+impl TlsServerConfig {
+    /// Loads the certificate chain and private key from PEM files, clients are not asked for
+    /// certificates.
+    pub fn from_pem(cert_chain: impl AsRef<Path>, key: impl AsRef<Path>) -> RMIResult<Self> {
+        Self::build(cert_chain.as_ref(), key.as_ref(), None)
+    }
+
+    /// Like `from_pem` but only clients with a certificate signed by one of the CAs in
+    /// `client_ca` can connect.
+    pub fn from_pem_mutual(
+        cert_chain: impl AsRef<Path>,
+        key: impl AsRef<Path>,
+        client_ca: impl AsRef<Path>,
+    ) -> RMIResult<Self> {
+        Self::build(cert_chain.as_ref(), key.as_ref(), Some(client_ca.as_ref()))
+    }
+
+    fn build(cert_chain: &Path, key: &Path, client_ca: Option<&Path>) -> RMIResult<Self> {
+        let builder = ServerConfig::builder_with_provider(provider())
+            .with_safe_default_protocol_versions()
+            .map_err(tls_err)?;
+        let builder = match client_ca {
+            Some(ca) => {
+                let verifier = WebPkiClientVerifier::builder_with_provider(
+                    Arc::new(load_roots(ca)?),
+                    provider(),
+                )
+                .build()
+                .map_err(tls_err)?;
+                builder.with_client_cert_verifier(verifier)
+            }
+            None => builder.with_no_client_auth(),
+        };
+        let config = builder
+            .with_single_cert(load_certs(cert_chain)?, load_key(key)?)
+            .map_err(tls_err)?;
+        Ok(TlsServerConfig {
+            config: Arc::new(config),
+        })
+    }
+}
+
+/// CAs a client trusts, and optionally the certificate it identifies itself with.
+#[derive(Debug, Clone)]
+pub struct TlsClientConfig {
+    config: Arc<ClientConfig>,
+    server_name: Option<String>,
+}
+
+impl TlsClientConfig {
+    /// Trusts the CAs in the PEM file `ca`, without a client certificate.
+    pub fn from_pem(ca: impl AsRef<Path>) -> RMIResult<Self> {
+        Self::build(ca.as_ref(), None)
+    }
+
+    /// Like `from_pem` but presents the certificate chain and key, for servers requiring
+    /// mutual TLS.
+    pub fn from_pem_mutual(
+        ca: impl AsRef<Path>,
+        cert_chain: impl AsRef<Path>,
+        key: impl AsRef<Path>,
+    ) -> RMIResult<Self> {
+        Self::build(ca.as_ref(), Some((cert_chain.as_ref(), key.as_ref())))
+    }
+
+    fn build(ca: &Path, identity: Option<(&Path, &Path)>) -> RMIResult<Self> {
+        let builder = ClientConfig::builder_with_provider(provider())
+            .with_safe_default_protocol_versions()
+            .map_err(tls_err)?
+            .with_root_certificates(load_roots(ca)?);
+        let config = match identity {
+            Some((cert_chain, key)) => builder
+                .with_client_auth_cert(load_certs(cert_chain)?, load_key(key)?)
+                .map_err(tls_err)?,
+            None => builder.with_no_client_auth(),
+        };
+        Ok(TlsClientConfig {
+            config: Arc::new(config),
+            server_name: None,
+        })
+    }
+
+    /// The name server certificates are checked against, by default the host given to
+    /// `get_registry_tls`.
+    pub fn with_server_name(mut self, name: &str) -> Self {
+        self.server_name = Some(name.to_string());
+        self
+    }
+
+    pub(crate) fn server_name_or(mut self, name: &str) -> Self {
+        if self.server_name.is_none() {
+            self.server_name = Some(
+                name.trim_start_matches('[')
+                    .trim_end_matches(']')
+                    .to_string(),
+            );
+        }
+        self
+    }
+
+    fn server_name(&self, addr: SocketAddr) -> RMIResult<ServerName<'static>> {
+        match &self.server_name {
+            Some(name) => ServerName::try_from(name.clone()).map_err(tls_err),
+            None => Ok(ServerName::from(addr.ip())),
+        }
+    }
+}
+
+/// Server side of a TLS connection, the handshake is done by the time `accept` returns.
+pub struct TlsStream {
+    stream: RefCell<StreamOwned<ServerConnection, TcpStream>>,
+    identity: Option<PeerIdentity>,
+}
+
+impl TlsStream {
+    pub fn accept(mut sock: TcpStream, tls: &TlsServerConfig) -> std::io::Result<Self> {
+        let mut conn =
+            ServerConnection::new(Arc::clone(&tls.config)).map_err(std::io::Error::other)?;
+        let previous = (sock.read_timeout()?, sock.write_timeout()?);
+        sock.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+        sock.set_write_timeout(Some(HANDSHAKE_TIMEOUT))?;
+        while conn.is_handshaking() {
+            conn.complete_io(&mut sock)?;
+        }
+        sock.set_read_timeout(previous.0)?;
+        sock.set_write_timeout(previous.1)?;
+        let identity = conn
+            .peer_certificates()
+            .and_then(|certs| certs.first())
+            .and_then(PeerIdentity::from_der);
+        Ok(TlsStream {
+            stream: RefCell::new(StreamOwned::new(conn, sock)),
+            identity,
+        })
+    }
+}
+
+impl Read for TlsStream {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.stream.get_mut().read(buf)
+    }
+}
+
+impl Write for TlsStream {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.stream.get_mut().write(buf)
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.stream.get_mut().flush()
+    }
+}
+
+impl Connection for TlsStream {
+    fn readable(&self) -> std::io::Result<bool> {
+        let mut stream = self.stream.borrow_mut();
+        let StreamOwned { conn, sock } = &mut *stream;
+        loop {
+            let state = conn.process_new_packets().map_err(std::io::Error::other)?;
+            if state.plaintext_bytes_to_read() > 0 {
+                return Ok(true);
+            }
+            if state.peer_has_closed() {
+                return Ok(false);
+            }
+            while conn.wants_write() {
+                conn.write_tls(sock)?;
+            }
+            if conn.read_tls(sock)? == 0 {
+                return Ok(false);
+            }
+        }
+    }
+    fn peer_addr(&self) -> Option<SocketAddr> {
+        self.stream.borrow().sock.peer_addr().ok()
+    }
+    fn local_addr(&self) -> Option<SocketAddr> {
+        self.stream.borrow().sock.local_addr().ok()
+    }
+    fn closer(&self) -> Option<Closer> {
+        self.stream.borrow().sock.closer()
+    }
+    fn peer_identity(&self) -> Option<PeerIdentity> {
+        self.identity.clone()
+    }
+}
+
+impl Debug for TlsStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "TlsStream[{:?}]", self.peer_addr())
+    }
+}
+
+/// Client side of a TLS connection to a registry or skeleton.
+#[allow(unused)]
+#[derive(Debug)]
+pub struct TlsTransport {
+    server_addr: SocketAddr,
+    // boxed, rustls connection state is large next to the other clients
+    stream: RefCell<Box<StreamOwned<ClientConnection, TcpStream>>>,
+}
+
+impl TlsTransport {
+    /// Connects and completes the handshake, `timeout` bounds the connect and every read and
+    /// write when given.
+    pub fn connect(
+        server_addr: SocketAddr,
+        tls: &TlsClientConfig,
+        timeout: Option<Duration>,
+    ) -> RMIResult<Self> {
+        let io_err = |e: std::io::Error| RMIError::TransportError(format!("{server_addr}: {e}"));
+        let mut sock = match timeout {
+            Some(timeout) => TcpStream::connect_timeout(&server_addr, timeout),
+            None => TcpStream::connect(server_addr),
+        }
+        .map_err(io_err)?;
+        sock.set_read_timeout(timeout).map_err(io_err)?;
+        sock.set_write_timeout(timeout).map_err(io_err)?;
+        sock.set_nodelay(true).map_err(io_err)?;
+        let name = tls.server_name(server_addr)?;
+        let mut conn = ClientConnection::new(Arc::clone(&tls.config), name).map_err(tls_err)?;
+        while conn.is_handshaking() {
+            conn.complete_io(&mut sock).map_err(tls_err)?;
+        }
+        Ok(Self {
+            server_addr,
+            stream: RefCell::new(Box::new(StreamOwned::new(conn, sock))),
+        })
+    }
+}
+
+impl Transport for TlsTransport {
+    fn call(&self, request: Vec<u8>) -> RMIResult<Vec<u8>> {
+        let mut stream = self.stream.borrow_mut();
+        exchange(&mut **stream, request)
+    }
+}
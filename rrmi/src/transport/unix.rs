@@ -0,0 +1,80 @@
+use std::cell::RefCell;
+use std::mem::MaybeUninit;
+use std::net::Shutdown;
+pub use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use socket2::SockRef;
+
+use crate::error::RMIError;
+use crate::remote::RMIResult;
+use crate::transport::Transport;
+use crate::transport::stream::{Closer, Connection, exchange};
+
+static NEXT_SOCKET: AtomicUsize = AtomicUsize::new(0);
+
+/// A fresh socket path in the temp dir, unique per process and call.
+pub fn unix_socket_path() -> PathBuf {
+    let n = NEXT_SOCKET.fetch_add(1, Ordering::Relaxed);
+    std::env::temp_dir().join(format!("rrmi-{}-{n}.sock", std::process::id()))
+}
+
+impl Connection for UnixStream {
+    fn readable(&self) -> std::io::Result<bool> {
+        // std only has peek for unix sockets on nightly
+        let mut buf = [MaybeUninit::<u8>::uninit(); 4];
+        Ok(SockRef::from(self).peek(&mut buf)? > 0)
+    }
+    fn closer(&self) -> Option<Closer> {
+        let stream = self.try_clone().ok()?;
+        Some(Box::new(move || {
+            let _ = stream.shutdown(Shutdown::Both);
+        }))
+    }
+    fn same_host(&self) -> bool {
+        true
+    }
+}
+
+/// Client side of a Unix domain socket, for objects exported on the same host.
+#[allow(unused)]
+#[derive(Debug)]
+pub struct UnixTransport {
+    server_path: PathBuf,
+    stream: RefCell<UnixStream>,
+}
+
+impl UnixTransport {
+    pub fn new(server_path: &Path) -> Self {
+        Self::connect(server_path).expect("Could not connect to server")
+    }
+
+    pub fn connect(server_path: &Path) -> RMIResult<Self> {
+        let stream = UnixStream::connect(server_path)
+            .map_err(|e| RMIError::TransportError(format!("{}: {e}", server_path.display())))?;
+        Ok(Self {
+            server_path: server_path.to_path_buf(),
+            stream: RefCell::new(stream),
+        })
+    }
+
+    /// Connects and bounds every read and write on the stream by `timeout`.
+    pub fn connect_timeout(server_path: &Path, timeout: Duration) -> RMIResult<Self> {
+        let transport = Self::connect(server_path)?;
+        let io_err = |e: std::io::Error| RMIError::TransportError(e.to_string());
+        let stream = transport.stream.borrow();
+        stream.set_read_timeout(Some(timeout)).map_err(io_err)?;
+        stream.set_write_timeout(Some(timeout)).map_err(io_err)?;
+        drop(stream);
+        Ok(transport)
+    }
+}
+
+impl Transport for UnixTransport {
+    fn call(&self, request: Vec<u8>) -> RMIResult<Vec<u8>> {
+        let mut stream = self.stream.borrow_mut();
+        exchange(&mut *stream, request)
+    }
+}
@@ -1,66 +1,194 @@
 use if_addrs::Interface;
+use socket2::{Domain, Socket, Type};
 
-use crate::transport::tcp::{IpAddr, Ipv4Addr, SocketAddr, TcpListener};
+use crate::transport::tcp::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener};
 use crate::{error::RMIError, remote::RMIResult};
-use std::str::FromStr;
+use std::sync::{Arc, mpsc};
+use std::thread;
+use std::time::Duration;
+use tracing::{trace, warn};
 
 #[allow(dead_code)]
 static START: u16 = 31768;
 #[allow(dead_code)]
 static END: u16 = 60999;
+static BACKLOG: i32 = 128;
+static ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+/// Binds a listener on every local address for `port`.
+///
+/// Prefers a dual-stack `[::]` socket so IPv4 and IPv6 clients reach the same
+/// listener, and falls back to `0.0.0.0` on hosts without IPv6.
+pub fn get_tcp_listener(port: u16) -> RMIResult<TcpListener> {
+    bind_dual_stack(port)
+        .or_else(|_| TcpListener::bind((Ipv4Addr::UNSPECIFIED, port)))
+        .map_err(|e| RMIError::TransportError(e.to_string()))
+}
+
+fn bind_dual_stack(port: u16) -> std::io::Result<TcpListener> {
+    let socket = Socket::new(Domain::IPV6, Type::STREAM, None)?;
+    // do not depend on net.ipv6.bindv6only, we always want both families
+    socket.set_only_v6(false)?;
+    // like std's TcpListener, so a restarted registry is not blocked by TIME_WAIT
+    socket.set_reuse_address(true)?;
+    let addr = SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), port);
+    socket.bind(&addr.into())?;
+    socket.listen(BACKLOG)?;
+    Ok(socket.into())
+}
+
 #[allow(dead_code)]
 pub fn get_tcp_socket_linear() -> RMIResult<(TcpListener, u16)> {
     for port in START..END {
-        match TcpListener::bind(("0.0.0.0", port)) {
-            Ok(l) => return Ok((l, port)),
-            _ => {}
+        if let Ok(l) = get_tcp_listener(port) {
+            return Ok((l, port));
         }
     }
     Err(RMIError::TransportError("No available ports".to_string()))
 }
 pub fn get_tcp_socket_os() -> RMIResult<TcpListener> {
-    TcpListener::bind(("0.0.0.0", 0)).map_err(|e| RMIError::TransportError(e.to_string()))
+    get_tcp_listener(0)
 }
 
-pub fn get_addr(hostname: &str, port: u16) -> SocketAddr {
+/// Resolves `hostname` to every address it has, in the order the resolver prefers.
+pub fn resolve_addrs(hostname: &str, port: u16) -> RMIResult<Vec<SocketAddr>> {
+    // accept bracketed IPv6 literals like in URLs: [::1]
+    let hostname = hostname.trim_start_matches('[').trim_end_matches(']');
     let ips: Vec<IpAddr> = dns_lookup::lookup_host(hostname)
-        .expect("should be able to get own address")
+        .map_err(|e| RMIError::NameResolution(format!("{hostname}: {e}")))?
         .collect();
-    eprintln!("IPs for {hostname}: {ips:?}");
-    if ips.len() == 0 {
-        //fail test if not found
-        panic!("unable to resolve hostname: {hostname}")
+    trace!(hostname, ?ips, "resolved");
+    if ips.is_empty() {
+        return Err(RMIError::NameResolution(hostname.to_string()));
     }
-    let mut ip: IpAddr = ips[0];
-    if ips
+    Ok(ips
+        .into_iter()
+        .map(|ip| SocketAddr::new(ip, port))
+        .collect())
+}
+
+pub fn get_addr(hostname: &str, port: u16) -> SocketAddr {
+    //fail test if not found
+    let ips: Vec<IpAddr> = resolve_addrs(hostname, port)
+        .unwrap_or_else(|e| panic!("{e}"))
         .iter()
-        .any(|ip| ip.to_string().contains("127.0") || ip.to_string().contains("localhost"))
-    {
-        ip = IpAddr::from(Ipv4Addr::from_str("0.0.0.0").expect("0.0.0.0 should pass"));
-        eprintln!("{hostname} is this computer so using {ip:?}");
+        .map(SocketAddr::ip)
+        .collect();
+    let mut ip: IpAddr = ips[0];
+    if let Some(loopback) = ips.iter().find(|ip| is_local(ip)) {
+        // keep the family the name resolved to, 0.0.0.0 is not reachable over IPv6
+        ip = *loopback;
+        trace!(hostname, %ip, "is this computer");
     }
-    eprintln!("using {}:{port} for {hostname}", ip);
+    trace!(hostname, addr = %SocketAddr::new(ip, port), "using");
     SocketAddr::new(ip, port)
 }
 
-pub fn get_local_ips() -> Result<Vec<IpAddr>, ()> {
-    let ips = if_addrs::get_if_addrs()
-        .map_err(|err| {
-            eprintln!("Error getting ips: {err}");
-            ()
-        })?
+/// Tries `attempt` on each address until one succeeds, happy eyeballs style (RFC 8305).
+///
+/// Address families are interleaved and every attempt gets `ATTEMPT_DELAY` of head start
+/// before the next one is raced against it, so a blackholed IPv6 route does not stall an
+/// IPv4 address that works. Returns the first success or every failure in start order.
+pub fn happy_eyeballs<T, F>(
+    addrs: &[SocketAddr],
+    attempt: F,
+) -> Result<(SocketAddr, T), Vec<(SocketAddr, RMIError)>>
+where
+    T: Send + 'static,
+    F: Fn(SocketAddr) -> RMIResult<T> + Send + Sync + 'static,
+{
+    let attempt = Arc::new(attempt);
+    let (tx, rx) = mpsc::channel();
+    let mut pending = 0;
+    // by attempt, attempts finish in any order
+    let mut errors: Vec<Option<(SocketAddr, RMIError)>> = vec![];
+    for (index, addr) in interleave_families(addrs).into_iter().enumerate() {
+        let tx = tx.clone();
+        let attempt = Arc::clone(&attempt);
+        thread::spawn(move || tx.send((index, addr, attempt(addr))));
+        pending += 1;
+        errors.push(None);
+        match rx.recv_timeout(ATTEMPT_DELAY) {
+            Ok((_, addr, Ok(res))) => return Ok((addr, res)),
+            Ok((index, addr, Err(e))) => {
+                pending -= 1;
+                errors[index] = Some((addr, e));
+            }
+            Err(_) => {} // still running, race the next address against it
+        }
+    }
+    while pending > 0 {
+        match rx.recv() {
+            Ok((_, addr, Ok(res))) => return Ok((addr, res)),
+            Ok((index, addr, Err(e))) => {
+                pending -= 1;
+                errors[index] = Some((addr, e));
+            }
+            Err(_) => break,
+        }
+    }
+    Err(errors.into_iter().flatten().collect())
+}
+
+fn interleave_families(addrs: &[SocketAddr]) -> Vec<SocketAddr> {
+    let Some(first) = addrs.first() else {
+        return vec![];
+    };
+    let (preferred, other): (Vec<SocketAddr>, Vec<SocketAddr>) = addrs
+        .iter()
+        .partition(|addr| addr.is_ipv6() == first.is_ipv6());
+    let mut preferred = preferred.into_iter();
+    let mut other = other.into_iter();
+    let mut interleaved = Vec::with_capacity(addrs.len());
+    loop {
+        match (preferred.next(), other.next()) {
+            (None, None) => return interleaved,
+            (a, b) => interleaved.extend(a.into_iter().chain(b)),
+        }
+    }
+}
+
+/// True for addresses that always point back at this host: `127.0.0.0/8`, `::1`,
+/// `::ffff:127.x.y.z` and the unspecified addresses.
+pub fn is_local(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => v4.is_loopback() || v4.is_unspecified(),
+        IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+            Some(v4) => v4.is_loopback() || v4.is_unspecified(),
+            None => v6.is_loopback() || v6.is_unspecified(),
+        },
+    }
+}
+
+/// True when both ends of a connection are on this host: over loopback, or over one of
+/// our own addresses, which the kernel then also uses as the source.
+pub fn is_same_host(local: &SocketAddr, peer: &SocketAddr) -> bool {
+    let peer = peer.ip().to_canonical();
+    is_local(&peer) || peer == local.ip().to_canonical()
+}
+
+/// Addresses of this host that other hosts can connect to.
+///
+/// Loopback and IPv6 link-local addresses are left out, the latter need a scope id
+/// that does not survive being sent to another host. IPv4 addresses come first.
+pub fn get_local_ips() -> RMIResult<Vec<IpAddr>> {
+    let mut ips: Vec<IpAddr> = get_local_ifs()?
         .into_iter()
-        .filter(|iface| !iface.is_loopback())
         .map(|iface| iface.ip())
+        .filter(|ip| match ip {
+            IpAddr::V4(_) => true,
+            IpAddr::V6(v6) => !v6.is_unicast_link_local(),
+        })
         .collect();
+    ips.sort_by_key(|ip| ip.is_ipv6());
     Ok(ips)
 }
 #[allow(dead_code)]
-pub fn get_local_ifs() -> Result<Vec<Interface>, ()> {
+pub fn get_local_ifs() -> RMIResult<Vec<Interface>> {
     let ifs = if_addrs::get_if_addrs()
         .map_err(|err| {
-            eprintln!("Error getting ips: {err}");
-            ()
+            warn!(error = %err, "could not get ips");
+            RMIError::IoError(err.to_string())
         })?
         .into_iter()
         .filter(|iface| !iface.is_loopback())
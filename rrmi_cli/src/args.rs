@@ -0,0 +1,196 @@
+use std::fmt::Display;
+
+use rrmi::{RMIError, RmiUrl};
+
+pub const USAGE: &str = "\
+Usage: rrmi [--registry <host[:port]>] [--psk <key>] <command>
+
+Commands:
+  list [host[:port] | <url>]              names bound in the registry, under the path of url
+  describe <name>                         type, address and methods of a name
+  call <name> <method> [json-args]        calls a method, prints what it returned
+  watch [prefix | <url>]                  prints names under prefix as they are (un)bound
+  ping [host[:port]]                      checks the registry answers
+  bench <name> <method> [json-args] [-n <calls>]
+                                          round trip times of a method
+
+The registry defaults to $RRMI_REGISTRY or localhost:1099, names can also be given as URLs
+that say which registry holds them: rrmi://host[:port]/path/name. Arguments are a JSON array
+in parameter order, an object by parameter name or a single value.";
+
+pub const DEFAULT_PORT: u16 = 1099;
+const DEFAULT_BENCH_CALLS: usize = 1000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    List {
+        prefix: String,
+    },
+    Describe {
+        name: String,
+    },
+    Call {
+        name: String,
+        method: String,
+        args: serde_json::Value,
+    },
+    Watch {
+        prefix: String,
+    },
+    Ping,
+    Bench {
+        name: String,
+        method: String,
+        args: serde_json::Value,
+        calls: usize,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Args {
+    pub host: String,
+    pub port: u16,
+    pub psk: Option<String>,
+    pub command: Command,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArgsError(pub String);
+
+impl Display for ArgsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+fn error(message: impl Into<String>) -> ArgsError {
+    ArgsError(message.into())
+}
+
+/// Splits `host[:port]`, IPv6 addresses go in brackets when followed by a port: `[::1]:1099`.
+pub fn parse_registry(registry: &str) -> Result<(String, u16), ArgsError> {
+    let (host, port) = match registry.rsplit_once(':') {
+        // a bare IPv6 address has colons but no port
+        Some((host, port)) if !host.contains(':') || host.ends_with(']') => (host, Some(port)),
+        _ => (registry, None),
+    };
+    let port = match port {
+        Some(port) => port
+            .parse()
+            .map_err(|_| error(format!("invalid port in {registry}")))?,
+        None => DEFAULT_PORT,
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    if host.is_empty() {
+        return Err(error(format!("no host in {registry}")));
+    }
+    Ok((host.to_string(), port))
+}
+
+fn is_url(arg: &str) -> bool {
+    arg.starts_with("rrmi:") || arg.starts_with("//")
+}
+
+/// `name`, or the name in it when it is a URL, which then says where the registry is.
+fn locate(name: String, located: &mut Option<(String, u16)>) -> Result<String, ArgsError> {
+    if !is_url(&name) {
+        return Ok(name);
+    }
+    let url: RmiUrl = name.parse().map_err(|e: RMIError| error(e.to_string()))?;
+    *located = Some((url.host, url.port));
+    Ok(url.name)
+}
+
+fn parse_json(args: Option<String>) -> Result<serde_json::Value, ArgsError> {
+    match args {
+        None => Ok(serde_json::Value::Null),
+        Some(args) => {
+            serde_json::from_str(&args).map_err(|e| error(format!("arguments are not JSON: {e}")))
+        }
+    }
+}
+
+/// Parses the arguments after the program name, `registry` being `$RRMI_REGISTRY`.
+pub fn parse(args: Vec<String>, registry: Option<String>) -> Result<Args, ArgsError> {
+    let mut registry = registry;
+    let mut psk = None;
+    let mut calls = None;
+    let mut positional = vec![];
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = |option: &str| {
+            args.next()
+                .ok_or_else(|| error(format!("{option} needs a value")))
+        };
+        match arg.as_str() {
+            "-r" | "--registry" => registry = Some(value(&arg)?),
+            "--psk" => psk = Some(value(&arg)?),
+            "-n" | "--calls" => {
+                let count = value(&arg)?;
+                let count = count
+                    .parse()
+                    .map_err(|_| error(format!("invalid number of calls: {count}")))?;
+                calls = Some(count);
+            }
+            "-h" | "--help" => return Err(error(USAGE)),
+            _ => positional.push(arg),
+        }
+    }
+    let mut positional = positional.into_iter();
+    let mut located = None;
+    let command = positional.next().ok_or_else(|| error(USAGE))?;
+    let required = |positional: &mut std::vec::IntoIter<String>, what: &str| {
+        positional
+            .next()
+            .ok_or_else(|| error(format!("{command} needs a {what}")))
+    };
+    let command = match command.as_str() {
+        "list" | "ping" => {
+            let mut prefix = String::new();
+            match positional.next() {
+                Some(url) if is_url(&url) => prefix = locate(url, &mut located)?,
+                Some(at) => registry = Some(at),
+                None => (),
+            }
+            match command.as_str() {
+                "list" => Command::List { prefix },
+                _ => Command::Ping,
+            }
+        }
+        "watch" => Command::Watch {
+            prefix: match positional.next() {
+                Some(prefix) => locate(prefix, &mut located)?,
+                None => String::new(),
+            },
+        },
+        "describe" => Command::Describe {
+            name: locate(required(&mut positional, "name")?, &mut located)?,
+        },
+        "call" => Command::Call {
+            name: locate(required(&mut positional, "name")?, &mut located)?,
+            method: required(&mut positional, "method")?,
+            args: parse_json(positional.next())?,
+        },
+        "bench" => Command::Bench {
+            name: locate(required(&mut positional, "name")?, &mut located)?,
+            method: required(&mut positional, "method")?,
+            args: parse_json(positional.next())?,
+            calls: calls.unwrap_or(DEFAULT_BENCH_CALLS),
+        },
+        other => return Err(error(format!("unknown command {other}\n\n{USAGE}"))),
+    };
+    if let Some(extra) = positional.next() {
+        return Err(error(format!("unexpected argument {extra}")));
+    }
+    let (host, port) = match (located, registry) {
+        (Some(located), _) => located,
+        (None, Some(registry)) => parse_registry(&registry)?,
+        (None, None) => ("localhost".to_string(), DEFAULT_PORT),
+    };
+    Ok(Args {
+        host,
+        port,
+        psk,
+        command,
+    })
+}
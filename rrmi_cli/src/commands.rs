@@ -0,0 +1,119 @@
+use std::time::{Duration, Instant, SystemTime};
+
+use rrmi::remote::ObjectInfo;
+use rrmi::{PreSharedKey, RMIResult, get_registry, get_registry_psk};
+
+use crate::args::{Args, Command};
+
+pub fn run(args: Args) -> RMIResult<()> {
+    let registry = match &args.psk {
+        Some(key) => get_registry_psk(&args.host, args.port, PreSharedKey::new(key))?,
+        None => get_registry(&args.host, args.port)?,
+    };
+    match args.command {
+        Command::List { prefix } => {
+            for name in registry.list_under(&prefix)? {
+                println!("{name}");
+            }
+        }
+        Command::Watch { prefix } => {
+            let (events, received) = std::sync::mpsc::channel();
+            let _watch = registry.watch(&prefix, move |event| drop(events.send(event)))?;
+            for event in received {
+                println!("{event}");
+            }
+        }
+        Command::Ping => {
+            let start = Instant::now();
+            registry.ping()?;
+            println!(
+                "registry at {}:{} answered in {:?}",
+                args.host,
+                args.port,
+                start.elapsed()
+            );
+        }
+        Command::Describe { name } => print!("{}", describe(&registry.describe(&name)?)),
+        Command::Call { name, method, args } => {
+            let stub = registry.lookup_dynamic(&name)?;
+            let result = stub.call_json(&method, args)?;
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&result).unwrap_or_else(|_| result.to_string())
+            );
+        }
+        Command::Bench {
+            name,
+            method,
+            args,
+            calls,
+        } => {
+            let stub = registry.lookup_dynamic(&name)?;
+            let mut rtts = Vec::with_capacity(calls);
+            let start = Instant::now();
+            for _ in 0..calls {
+                let call = Instant::now();
+                stub.call_json(&method, args.clone())?;
+                rtts.push(call.elapsed());
+            }
+            print!(
+                "{}",
+                bench_report(&format!("{name}.{method}"), rtts, start.elapsed())
+            );
+        }
+    }
+    Ok(())
+}
+
+/// What `describe` prints.
+pub fn describe(info: &ObjectInfo) -> String {
+    let unknown = "unknown (bound by reference)";
+    let mut text = format!("{} (id {})\n", info.name, info.id);
+    text += &format!(
+        "  type:        {}\n",
+        info.type_name.as_deref().unwrap_or(unknown)
+    );
+    let addr = info
+        .addr
+        .as_ref()
+        .map_or("not looked up yet".to_string(), ToString::to_string);
+    text += &format!("  address:     {addr}\n");
+    text += &format!(
+        "  fingerprint: {}\n",
+        info.fingerprint.as_deref().unwrap_or(unknown)
+    );
+    let age = SystemTime::now()
+        .duration_since(info.bound_at)
+        .unwrap_or_default();
+    text += &format!("  bound:       {}s ago\n", age.as_secs());
+    if !info.methods.is_empty() {
+        text += "  methods:\n";
+    }
+    for method in &info.methods {
+        text += &format!("    {method}");
+        if let Some(role) = &method.require {
+            text += &format!(" [requires {role}]");
+        }
+        text += "\n";
+    }
+    text
+}
+
+/// What `bench` prints for `rtts`, the round trip of every call, made in `total`.
+pub fn bench_report(what: &str, mut rtts: Vec<Duration>, total: Duration) -> String {
+    if rtts.is_empty() {
+        return format!("{what}: no calls made\n");
+    }
+    rtts.sort();
+    let percentile = |p: f64| rtts[((rtts.len() - 1) as f64 * p).round() as usize];
+    let mean = rtts.iter().sum::<Duration>() / rtts.len() as u32;
+    format!(
+        "{what}: {} calls in {total:?}, {:.0} calls/s\n  min {:?}  mean {mean:?}  p50 {:?}  p99 {:?}  max {:?}\n",
+        rtts.len(),
+        rtts.len() as f64 / total.as_secs_f64(),
+        rtts[0],
+        percentile(0.5),
+        percentile(0.99),
+        rtts[rtts.len() - 1],
+    )
+}
@@ -0,0 +1,21 @@
+//! Inspects and calls the objects of a running registry without writing Rust against their
+//! generated stubs.
+//!
+//! Usage: `rrmi [--registry <host[:port]>] <list|describe|call|ping|bench> ...`, see `--help`.
+use std::process::exit;
+
+mod args;
+mod commands;
+mod tests;
+
+fn main() {
+    let args = std::env::args().skip(1).collect();
+    let args = args::parse(args, std::env::var("RRMI_REGISTRY").ok()).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        exit(2);
+    });
+    if let Err(e) = commands::run(args) {
+        eprintln!("{e}");
+        exit(1);
+    }
+}
@@ -0,0 +1,152 @@
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests {
+    use crate::args::{Args, Command, parse, parse_registry};
+    use crate::commands::{bench_report, describe, run};
+    use rrmi::create_registry;
+    use rrmi::remote::RemoteObject;
+    use rrmi_macros::remote_object;
+    use serde_json::json;
+    use std::time::Duration;
+
+    static CLI_PORT: u16 = 10985;
+
+    struct Greeter;
+
+    #[remote_object]
+    impl Greeter {
+        #[remote]
+        fn greet(&self, name: &str, times: u32) -> String {
+            format!("hello {name}").repeat(times as usize)
+        }
+    }
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn registries() {
+        assert_eq!(parse_registry("node1"), Ok(("node1".to_string(), 1099)));
+        assert_eq!(
+            parse_registry("node1:2000"),
+            Ok(("node1".to_string(), 2000))
+        );
+        assert_eq!(parse_registry("::1"), Ok(("::1".to_string(), 1099)));
+        assert_eq!(parse_registry("[::1]:2000"), Ok(("::1".to_string(), 2000)));
+        assert!(parse_registry("node1:port").is_err());
+        assert!(parse_registry(":2000").is_err());
+    }
+
+    #[test]
+    fn commands() {
+        assert_eq!(
+            parse(args("list node1:2000"), None),
+            Ok(Args {
+                host: "node1".to_string(),
+                port: 2000,
+                psk: None,
+                command: Command::List {
+                    prefix: String::new()
+                },
+            })
+        );
+        // URLs say where the registry is, over the option
+        let listed = parse(args("-r node2 list rrmi://node1:2000/jobs"), None);
+        assert_eq!(
+            listed.map(|a| (a.host, a.port, a.command)),
+            Ok((
+                "node1".to_string(),
+                2000,
+                Command::List {
+                    prefix: "jobs".to_string()
+                }
+            ))
+        );
+        let described = parse(args("describe //[::1]/jobs/counter"), None).expect("valid url");
+        assert_eq!((described.host.as_str(), described.port), ("::1", 1099));
+        assert_eq!(
+            described.command,
+            Command::Describe {
+                name: "jobs/counter".to_string()
+            }
+        );
+        assert!(parse(args("describe rrmi://node1/jobs//counter"), None).is_err());
+        let watched = parse(args("watch jobs"), None).map(|a| a.command);
+        assert_eq!(
+            watched,
+            Ok(Command::Watch {
+                prefix: "jobs".to_string()
+            })
+        );
+        let call = parse(
+            vec![
+                "call".to_string(),
+                "greeter".to_string(),
+                "greet".to_string(),
+                r#"{"name": "bob", "times": 2}"#.to_string(),
+            ],
+            Some("node2".to_string()),
+        )
+        .expect("valid call");
+        assert_eq!(call.host, "node2");
+        assert_eq!(
+            call.command,
+            Command::Call {
+                name: "greeter".to_string(),
+                method: "greet".to_string(),
+                args: json!({"name": "bob", "times": 2}),
+            }
+        );
+        // the option wins over the environment
+        let bench = parse(
+            args("-r node3 bench greeter greet -n 10"),
+            Some("node2".to_string()),
+        );
+        let bench = bench.expect("valid bench");
+        assert_eq!(bench.host, "node3");
+        assert!(matches!(bench.command, Command::Bench { calls: 10, .. }));
+        assert!(parse(args("describe"), None).is_err());
+        assert!(parse(args("call greeter greet {oops"), None).is_err());
+        assert!(parse(args("ping node1 node2"), None).is_err());
+        assert!(parse(args("fly"), None).is_err());
+        assert!(parse(args("bench greeter greet -n many"), None).is_err());
+    }
+
+    #[test]
+    fn against_a_registry() {
+        let reg = create_registry(CLI_PORT);
+        reg.bind("greeter", Greeter);
+        let registry = format!("localhost:{CLI_PORT}");
+        for line in [
+            "list",
+            "ping",
+            "describe greeter",
+            "call greeter greet [\"bob\",2]",
+            "bench greeter greet [\"bob\",1] -n 5",
+        ] {
+            let parsed = parse(args(line), Some(registry.clone())).expect("valid");
+            assert_eq!(run(parsed), Ok(()), "{line}");
+        }
+        let parsed = parse(args("call greeter wave"), Some(registry.clone())).expect("valid");
+        assert!(run(parsed).is_err());
+
+        let text = describe(&reg.describe("greeter").expect("greeter is in"));
+        assert!(text.starts_with("greeter (id 1)\n  type:        Greeter\n"));
+        assert!(text.contains("    fn greet(name: &str, times: u32) -> String\n"));
+    }
+
+    #[test]
+    fn bench_percentiles() {
+        let rtts = (1..=100).map(Duration::from_millis).collect();
+        let report = bench_report("x.y", rtts, Duration::from_secs(1));
+        assert_eq!(
+            report,
+            "x.y: 100 calls in 1s, 100 calls/s\n  min 1ms  mean 50.5ms  p50 51ms  p99 99ms  max 100ms\n"
+        );
+        assert_eq!(
+            bench_report("x.y", vec![], Duration::ZERO),
+            "x.y: no calls made\n"
+        );
+    }
+}
@@ -3,27 +3,64 @@ use syn::Ident;
 
 use crate::{
     RemoteObjectInfo, Span, TokenStream2,
-    utils::{already_rmi_result, fix_ref_to_type, fix_ref_when_called, is_str_ref},
+    utils::{already_rmi_result, fix_ref_to_type, fix_ref_when_called, is_str_ref, type_string},
 };
 
+/// Enters a span for the rest of a generated method when built with `tracing`. It goes
+/// through rrmi's re-export, so the crate using `#[remote_object]` needs neither `tracing`
+/// nor a feature of its own.
+fn enter_span(struct_name: &Ident, method: &str, fields: TokenStream2) -> TokenStream2 {
+    if cfg!(feature = "tracing") {
+        quote! {
+            let _span = ::rrmi::__tracing::info_span!(
+                #method,
+                object = stringify!(#struct_name),
+                #fields
+            )
+            .entered();
+        }
+    } else {
+        quote! {}
+    }
+}
+
 pub fn gen_remote_obj(remote_obj: &RemoteObjectInfo) -> TokenStream2 {
     let struct_name = &remote_obj.struct_name.0;
-    #[cfg(not(feature = "tracing"))]
-    let instrument = quote! {};
-    #[cfg(feature = "tracing")]
-    let instrument = quote! {
-        #[allow(unexpected_cfgs)]
-        #[cfg_attr(feature = "tracing", ::tracing::instrument)]
-    };
+    let span = enter_span(struct_name, "run", quote! {});
+    let signatures = remote_obj.methods.iter().map(|m| {
+        let name = m.name.to_string();
+        let params = m.params.0.iter().map(|param| {
+            let (param_name, param_type) = &param.0;
+            let param_name = param_name.to_string();
+            let param_type = type_string(param_type);
+            quote! {(#param_name.to_string(), #param_type.to_string())}
+        });
+        let returns = type_string(&m.get_ret());
+        let require = match &m.require {
+            Some(role) => quote! { Some(#role.to_string()) },
+            None => quote! { None },
+        };
+        quote! {
+            ::rrmi::remote::MethodSignature{
+                name: #name.to_string(),
+                params: vec![#(#params),*],
+                returns: #returns.to_string(),
+                require: #require,
+            }
+        }
+    });
     quote! {
         impl RemoteObject for #struct_name{
-            #instrument
-            fn run(&self, stream: &mut ::rrmi::TcpStream) -> ::rrmi::RMIResult<()> {
+            fn run(&self, stream: &mut dyn ::rrmi::Connection) -> ::rrmi::RMIResult<()> {
+                #span
                 self.handle_connection_gen(stream)
         }
             fn name(&self) -> &'static str{
                 stringify!(#struct_name)
             }
+            fn methods(&self) -> Vec<::rrmi::remote::MethodSignature>{
+                vec![#(#signatures),*]
+            }
     }
     }
 }
@@ -62,16 +99,22 @@ pub fn gen_stub(remote_obj: &RemoteObjectInfo) -> TokenStream2 {
         let param_names = params.iter().map(|p| fix_ref_when_called(&p.0));
 
         let fn_contents = quote! {
-            use ::rrmi::Transport;
-            let transport_client = &self.transport_client;
+            let call = ::rrmi::remote::Call::new(
+                stringify!(#struct_name),
+                stringify!(#method_name),
+                self.take_headers(),
+                0,
+            );
             let req = #req_name::#camel{
                 #(#param_names),*
             };
-            let resp : #res_name = transport_client.send(req)?;
-            match resp{
-                #pattern => #expr,
-                _ => Err(::rrmi::RMIError::TransportError("Wrong response".to_string())),
-            }
+            ::rrmi::remote::invoke(&self.transport_client, &self.interceptors, call, req, |resp: #res_name| {
+                match resp{
+                    #pattern => #expr,
+                    #res_name::RmiError(e) => Err(e),
+                    _ => Err(::rrmi::RMIError::TransportError("Wrong response".to_string())),
+                }
+            })
         };
 
         let fn_call = if params.is_empty() {
@@ -89,23 +132,54 @@ pub fn gen_stub(remote_obj: &RemoteObjectInfo) -> TokenStream2 {
     let stub_struct = quote! {
         pub struct #stub_name{
             // remote: ::rrmi::RemoteRef,
-            transport_client: ::rrmi::TcpClient,
+            transport_client: ::rrmi::Client,
             stub_name: String,
+            headers: ::rrmi::Headers,
+            call_headers: ::std::cell::RefCell<::rrmi::Headers>,
+            interceptors: ::rrmi::remote::Interceptors,
+        }
+        impl ::rrmi::RemoteStub for #stub_name{
+            fn connect(stub: ::rrmi::Stub) -> ::rrmi::RMIResult<Self>{
+                let transport_client = stub.connect()?;
+                Ok(#stub_name{
+                    transport_client,
+                    stub_name: "#stub_name".into(),
+                    headers: ::rrmi::Headers::new(),
+                    call_headers: ::std::cell::RefCell::new(::rrmi::Headers::new()),
+                    interceptors: ::rrmi::remote::Interceptors::default(),
+                })
+            }
         }
         impl From<::rrmi::Stub> for #stub_name{
             fn from(stub: ::rrmi::Stub) -> Self{
-                let remote = stub.remote;
-                let transport_client = ::rrmi::TcpClient::new(remote.addr);
-                #stub_name{transport_client, stub_name: "#stub_name".into()}
+                <Self as ::rrmi::RemoteStub>::connect(stub).expect("Could not connect to server")
+            }
+        }
+        impl #stub_name{
+            /// Sends `value` as header `key` with every call from this stub.
+            pub fn set_header(&mut self, key: &str, value: &str){
+                self.headers.insert(key.to_string(), value.to_string());
+            }
+            /// Sends `headers` with the next call only, over the ones set with `set_header`.
+            pub fn call_headers(&self, headers: ::rrmi::Headers) -> &Self{
+                self.call_headers.borrow_mut().extend(headers);
+                self
+            }
+            /// Adds `interceptor` at the end of the chain every call from this stub goes through.
+            pub fn add_interceptor(&mut self, interceptor: impl ::rrmi::remote::Interceptor + 'static){
+                self.interceptors.push(interceptor);
+            }
+            fn take_headers(&self) -> ::rrmi::Headers{
+                let mut headers = self.headers.clone();
+                headers.extend(self.call_headers.take());
+                headers
             }
         }
     };
-    #[cfg(not(feature = "tracing"))]
-    let debug = quote! {};
-    #[cfg(feature = "tracing")]
-    let debug = quote! {
-        #[allow(unexpected_cfgs)]
-        #[cfg_attr(feature = "tracing", derive(Debug))]
+    let debug = if cfg!(feature = "tracing") {
+        quote! {#[derive(::std::fmt::Debug)]}
+    } else {
+        quote! {}
     };
     quote! {
         #debug
@@ -117,29 +191,40 @@ pub fn gen_stub(remote_obj: &RemoteObjectInfo) -> TokenStream2 {
 }
 
 pub fn gen_handle_connection(remote_obj: &RemoteObjectInfo) -> TokenStream2 {
+    let struct_name = &remote_obj.struct_name.0;
     let (req_name, res_name) = remote_obj.get_enum_names();
-    #[cfg(not(feature = "tracing"))]
-    let instrument = quote! {};
-    #[cfg(feature = "tracing")]
-    let instrument = quote! {
-        #[allow(unexpected_cfgs)]
-        #[cfg_attr(feature = "tracing", ::tracing::instrument)]
-    };
+    let span = enter_span(struct_name, "handle_connection_gen", quote! {});
     quote! {
-        #instrument
-        fn handle_connection_gen(&self, stream: &mut ::rrmi::TcpStream) -> ::rrmi::RMIResult<()> {
+        fn handle_connection_gen(&self, stream: &mut dyn ::rrmi::Connection) -> ::rrmi::RMIResult<()> {
+            #span
             let request_bytes = ::rrmi::receive_data(stream);
-            let request: #req_name = ::rrmi::unmarshal(&request_bytes)?;
+            let request: ::rrmi::Envelope<#req_name> = ::rrmi::unmarshal(&request_bytes)?;
+            let mut call = ::rrmi::remote::Call::new(
+                stringify!(#struct_name),
+                request.body.method_name(),
+                request.headers,
+                request_bytes.len(),
+            );
+            let started = ::rrmi::remote::before_call(&mut call);
 
-            let response: #res_name = self.handle_request_gen(request);
+            let response: #res_name = match started.refused() {
+                Some(e) => #res_name::RmiError(e),
+                None => self.handle_request_gen(request.body),
+            };
+            let error = match &response {
+                #res_name::RmiError(e) => Some(e.clone()),
+                _ => None,
+            };
 
             let response_bytes = ::rrmi::marshal(&response)?;
+            started.finish(&call, response_bytes.len(), error);
             ::rrmi::send_data(response_bytes, stream)
     }
     }
 }
 
 pub fn gen_handle_request(remote_obj: &RemoteObjectInfo) -> TokenStream2 {
+    let struct_name = &remote_obj.struct_name.0;
     let (req_name, res_name) = remote_obj.get_enum_names();
     let match_arms = remote_obj.methods.iter().map(|m| {
         let method_name = &m.name;
@@ -165,16 +250,21 @@ pub fn gen_handle_request(remote_obj: &RemoteObjectInfo) -> TokenStream2 {
         };
         quote! { #pattern => #res_name::#camel(#call)}
     });
-    #[cfg(not(feature = "tracing"))]
-    let instrument = quote! {};
-    #[cfg(feature = "tracing")]
-    let instrument = quote! {
-        #[allow(unexpected_cfgs)]
-        #[cfg_attr(feature = "tracing", ::tracing::instrument)]
-    };
+    let span = enter_span(
+        struct_name,
+        "handle_request_gen",
+        quote! { method = req.method_name() },
+    );
     quote! {
-        #instrument
         fn handle_request_gen(&self, req: #req_name) -> #res_name{
+            #span
+            if let Err(e) = ::rrmi::remote::authorize(
+                stringify!(#struct_name),
+                req.method_name(),
+                req.required_role(),
+            ) {
+                return #res_name::RmiError(e);
+            }
             match req{
                 #(#match_arms),*
             }
@@ -213,6 +303,19 @@ pub fn gen_enums(remote_obj: &RemoteObjectInfo) -> TokenStream2 {
         // }
         quote! { #enum_variant(#ret)}
     });
+    let method_names = remote_obj.methods.iter().map(|m| {
+        let camel = m.get_name_camel();
+        let name = m.name.to_string();
+        quote! { #req_name::#camel{..} => #name }
+    });
+    let required_roles = remote_obj.methods.iter().map(|m| {
+        let camel = m.get_name_camel();
+        let role = match &m.require {
+            Some(role) => quote! { Some(#role) },
+            None => quote! { None },
+        };
+        quote! { #req_name::#camel{..} => #role }
+    });
     let derive_debug = if cfg!(feature = "tracing") {
         quote! {#[derive(::std::fmt::Debug)]}
     } else {
@@ -229,7 +332,23 @@ pub fn gen_enums(remote_obj: &RemoteObjectInfo) -> TokenStream2 {
         #[derive(serde::Serialize,serde::Deserialize)]
         #derive_debug
         pub enum #res_name{
-            #(#res_variants),*
+            #(#res_variants,)*
+            /// the call was refused before reaching the method
+            RmiError(::rrmi::RMIError),
+        }
+
+        impl #req_name{
+            pub fn method_name(&self) -> &'static str{
+                match self{
+                    #(#method_names),*
+                }
+            }
+            /// the role set with `#[remote(require = "role")]`
+            pub fn required_role(&self) -> Option<&'static str>{
+                match self{
+                    #(#required_roles),*
+                }
+            }
         }
     };
 
@@ -2,7 +2,7 @@ use proc_macro2::Span;
 use quote::quote;
 use std::fmt::Debug;
 use syn::{
-    FnArg, Ident, ImplItem, ImplItemFn, ItemImpl, Meta, Pat, ReturnType, Token, Type,
+    FnArg, Ident, ImplItem, ImplItemFn, ItemImpl, LitStr, Meta, Pat, ReturnType, Token, Type,
     parse::{Parse, ParseStream},
     punctuated::Punctuated,
 };
@@ -34,18 +34,14 @@ impl Parse for RemoteObjectInfo {
             )
         })?;
         // eprintln!("{struct_name:?}");
-        let methods: Vec<RemoteMethodInfo> = impl_block
-            .items
-            .iter_mut()
-            .filter_map(|x| {
-                match x {
-                    ImplItem::Fn(method) => RemoteMethodInfo::try_from(&mut *method)
-                        .map_err(|e| syn::Error::new_spanned(method, format!("Problem parsing function: {e:?}"))),
-                    _ => Err(syn::Error::new_spanned(x, "Could not parse as a function")),
-                }
-                .ok()
-            })
-            .collect();
+        let mut methods = vec![];
+        for item in impl_block.items.iter_mut() {
+            if let ImplItem::Fn(method) = item
+                && let Some(method) = RemoteMethodInfo::parse(method)?
+            {
+                methods.push(method);
+            }
+        }
         let original = impl_block.clone(); // clone after cleaning the methods
         Ok(Self {
             struct_name,
@@ -119,12 +115,14 @@ pub struct RemoteMethodInfo {
     pub name: Ident,
     pub params: ParametersInfo,
     pub ret: ReturnType,
+    /// role from `#[remote(require = "role")]`
+    pub require: Option<String>,
 }
 
 impl RemoteMethodInfo {
     pub fn get_name_camel(&self) -> Ident {
         let name = &self.name;
-        Ident::new(&camel_case(&name.to_string()), name.span().clone())
+        Ident::new(&camel_case(name.to_string()), name.span())
     }
 
     pub fn get_ret(&self) -> Type {
@@ -133,28 +131,42 @@ impl RemoteMethodInfo {
             ReturnType::Type(_, ty) => *ty.clone(),
         }
     }
-}
 
-impl TryFrom<&mut ImplItemFn> for RemoteMethodInfo {
-    type Error = ();
-    fn try_from(method: &mut ImplItemFn) -> Result<Self, ()> {
-        let is_remote = method
+    /// Reads a method marked `#[remote]`, `None` for the others.
+    fn parse(method: &mut ImplItemFn) -> syn::Result<Option<Self>> {
+        let Some(remote) = method
             .attrs
             .iter()
-            .any(|attr| matches!(&attr.meta, Meta::Path(path) if path.is_ident("remote")));
-        if !is_remote {
-            return Err(());
+            .find(|attr| attr.path().is_ident("remote"))
+        else {
+            return Ok(None);
+        };
+        let mut require = None;
+        if let Meta::List(_) = &remote.meta {
+            // #[remote(require = "role")]
+            remote.parse_nested_meta(|meta| {
+                if meta.path.is_ident("require") {
+                    let role: LitStr = meta.value()?.parse()?;
+                    require = Some(role.value());
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `require = \"role\"`"))
+                }
+            })?;
         }
         // DISCARD #[remote]
-        method
-            .attrs
-            .retain(|a| !matches!(&a.meta, Meta::Path(path) if path.is_ident("remote")));
+        method.attrs.retain(|a| !a.path().is_ident("remote"));
         let name = method.sig.ident.clone();
         // let s = &method.sig.ident.to_string();
         // eprintln!("Found remote method: {s}");
         let params = ParametersInfo::from(&method.sig.inputs);
         let ret = method.sig.output.clone();
-        Ok(Self { name, params, ret })
+        Ok(Some(Self {
+            name,
+            params,
+            ret,
+            require,
+        }))
     }
 }
 
@@ -15,27 +15,44 @@ pub fn camel_case(s: impl AsRef<str>) -> String {
         .collect()
 }
 
+/// How a type is written in the source, without the spaces `quote` puts between tokens.
+pub fn type_string(ty: &Type) -> String {
+    quote!(#ty)
+        .to_string()
+        .replace(" :: ", "::")
+        .replace(":: ", "::")
+        .replace(" < ", "<")
+        .replace("< ", "<")
+        .replace(" <", "<")
+        .replace(" >", ">")
+        .replace(" ,", ",")
+        .replace("& ", "&")
+        .replace("( ", "(")
+        .replace(" )", ")")
+        .replace("[ ", "[")
+        .replace(" ]", "]")
+        .replace(" ;", ";")
+}
+
 pub fn fix_ref_to_type(ty: &Type) -> Type {
     // check if the type is &str
-    if let Type::Reference(r) = ty {
-        if let Type::Path(p) = r.elem.as_ref() {
-            if p.path.is_ident("str") {
-                // replace &str with String
-                return syn::parse_quote!(String);
-            }
-        }
+    if let Type::Reference(r) = ty
+        && let Type::Path(p) = r.elem.as_ref()
+        && p.path.is_ident("str")
+    {
+        // replace &str with String
+        return syn::parse_quote!(String);
     }
     ty.clone()
 }
 
 pub fn fix_ref_when_called(param: &(Ident, Type)) -> TokenStream2 {
     let (ident, ty) = param;
-    if let Type::Reference(r) = ty {
-        if let Type::Path(p) = r.elem.as_ref() {
-            if p.path.is_ident("str") {
-                return quote! {#ident: #ident.to_string()};
-            }
-        }
+    if let Type::Reference(r) = ty
+        && let Type::Path(p) = r.elem.as_ref()
+        && p.path.is_ident("str")
+    {
+        return quote! {#ident: #ident.to_string()};
     }
     quote! {#ident}
 }
@@ -43,21 +60,20 @@ pub fn fix_ref_when_called(param: &(Ident, Type)) -> TokenStream2 {
 #[allow(unused)]
 pub fn already_rmi_result(ty: &Type) -> bool {
     // take the type and check if is already ::foo::bar::RMIResult<T>
-    if let Type::Path(tp) = ty {
-        if let Some(last) = tp.path.segments.last() {
-            if last.ident == "RMIResult" {
-                return true;
-            }
-        }
+    if let Type::Path(tp) = ty
+        && let Some(last) = tp.path.segments.last()
+        && last.ident == "RMIResult"
+    {
+        return true;
     }
     false
 }
 
 pub fn is_str_ref(ty: &Type) -> bool {
-    if let Type::Reference(r) = ty {
-        if let Type::Path(p) = r.elem.as_ref() {
-            return p.path.is_ident("str");
-        }
+    if let Type::Reference(r) = ty
+        && let Type::Path(p) = r.elem.as_ref()
+    {
+        return p.path.is_ident("str");
     }
     false
 }
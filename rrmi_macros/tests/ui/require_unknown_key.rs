@@ -0,0 +1,13 @@
+use rrmi_macros::remote_object;
+
+struct Vault;
+
+#[remote_object]
+impl Vault {
+    #[remote(role = "admin")]
+    fn open(&self) -> bool {
+        true
+    }
+}
+
+fn main() {}
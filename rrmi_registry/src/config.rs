@@ -0,0 +1,221 @@
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde::Deserialize;
+
+use rrmi::remote::registry::RegistryStub;
+use rrmi::remote::{Principal, REGISTRY_WRITE_ROLE, Registry};
+use rrmi::utils::resolve_addrs;
+use rrmi::{
+    PreSharedKey, RMIError, RMIResult, RemoteRef, RmiUrl, try_create_registry,
+    try_create_registry_psk,
+};
+
+pub const DEFAULT_PORT: u16 = 1099;
+
+/// What the daemon reads from `--config`, every field is optional.
+///
+/// ```toml
+/// port = 1099
+/// advertise = "10.0.0.5"
+/// journal = "/var/lib/rrmi/bindings"
+///
+/// [auth]
+/// psk_file = "/etc/rrmi/key"
+/// remote_binds = true
+/// writers = ["node1"]
+///
+/// [tls]
+/// cert = "/etc/rrmi/registry.pem"
+/// key = "/etc/rrmi/registry.key"
+/// client_ca = "/etc/rrmi/ca.pem"
+///
+/// [[peers]]
+/// prefix = "node3"
+/// registry = "rrmi://node3:1099"
+///
+/// [replication]
+/// replicas = ["rrmi://node1", "rrmi://node2", "rrmi://node4"]
+/// me = 0
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub port: Option<u16>,
+    /// the address lookups point clients at, see `Registry::set_advertised_ip`
+    pub advertise: Option<IpAddr>,
+    /// remote binds are kept there across restarts, see `Registry::use_journal`
+    pub journal: Option<PathBuf>,
+    #[serde(default)]
+    pub auth: Auth,
+    pub tls: Option<Tls>,
+    #[serde(default)]
+    pub peers: Vec<Peer>,
+    pub replication: Option<Replication>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Auth {
+    /// clients have to know this key, see `create_registry_psk`
+    pub psk: Option<String>,
+    pub psk_file: Option<PathBuf>,
+    /// every client that got in may bind and unbind, not only the ones on this host
+    #[serde(default)]
+    pub remote_binds: bool,
+    /// certificate common names that may bind and unbind from other hosts over mutual TLS
+    #[serde(default)]
+    pub writers: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Tls {
+    pub cert: PathBuf,
+    pub key: PathBuf,
+    /// asks clients for a certificate signed by this CA, their common name is their identity
+    pub client_ca: Option<PathBuf>,
+}
+
+/// A registry lookups of names not bound here are forwarded to, see `Registry::add_peer`.
+/// It is reached with the pre-shared key of this registry, if there is one.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Peer {
+    /// names under it are forwarded without it, every unknown name is when empty
+    #[serde(default)]
+    pub prefix: String,
+    /// `rrmi://host[:port]`
+    pub registry: String,
+}
+
+/// The registries this one is a replica with, see `Registry::use_replicas`. Replicas on other
+/// hosts have to be allowed to write, like binders, and are reached with the pre-shared key
+/// of this registry, if there is one.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Replication {
+    /// `rrmi://host[:port]` of every replica this one included, in the same order on all
+    pub replicas: Vec<String>,
+    /// which of them this one is
+    pub me: usize,
+}
+
+/// A stub for the registry at `url`, not pinged: it may well start after us.
+fn registry_stub(url: &str, psk: Option<&PreSharedKey>) -> RMIResult<RegistryStub> {
+    let url: RmiUrl = url.parse()?;
+    let addr = resolve_addrs(&url.host, url.port)?[0];
+    let remote = RemoteRef::new(addr, 0);
+    Ok(match psk {
+        Some(key) => RegistryStub::with_psk(remote, key.clone()),
+        None => RegistryStub::new(remote),
+    })
+}
+
+impl Config {
+    pub fn read(path: impl AsRef<Path>) -> RMIResult<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| RMIError::IoError(format!("{}: {e}", path.display())))?;
+        Self::parse(&text).map_err(|e| RMIError::BadArguments(format!("{}: {e}", path.display())))
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        toml::from_str(text).map_err(|e| e.to_string())
+    }
+
+    fn psk(&self) -> RMIResult<Option<PreSharedKey>> {
+        match (&self.auth.psk, &self.auth.psk_file) {
+            (Some(_), Some(_)) => Err(RMIError::BadArguments(
+                "set either psk or psk_file, not both".to_string(),
+            )),
+            (Some(key), None) => Ok(Some(PreSharedKey::new(key))),
+            (None, Some(path)) => PreSharedKey::from_file(path).map(Some),
+            (None, None) => Ok(None),
+        }
+    }
+
+    /// Whether `caller` may bind and unbind names from another host.
+    pub fn may_write(&self, caller: &Principal) -> bool {
+        self.auth.remote_binds
+            || caller
+                .name
+                .as_ref()
+                .is_some_and(|name| self.auth.writers.contains(name))
+    }
+
+    /// Starts the registry this config describes.
+    pub fn start(self) -> RMIResult<Arc<Registry>> {
+        let port = self.port.unwrap_or(DEFAULT_PORT);
+        let psk = self.psk()?;
+        let peers = self
+            .peers
+            .iter()
+            .map(|peer| {
+                Ok((
+                    peer.prefix.as_str(),
+                    registry_stub(&peer.registry, psk.as_ref())?,
+                ))
+            })
+            .collect::<RMIResult<Vec<_>>>()?;
+        let replicas = match &self.replication {
+            Some(replication) => Some((
+                replication
+                    .replicas
+                    .iter()
+                    .map(|url| registry_stub(url, psk.as_ref()))
+                    .collect::<RMIResult<Vec<_>>>()?,
+                replication.me,
+            )),
+            None => None,
+        };
+        let registry = match (&self.tls, psk) {
+            (Some(_), Some(_)) => {
+                return Err(RMIError::BadArguments(
+                    "a registry uses either TLS or a pre-shared key".to_string(),
+                ));
+            }
+            (Some(tls), None) => start_tls(port, tls)?,
+            (None, Some(key)) => try_create_registry_psk(port, key)?,
+            (None, None) => try_create_registry(port)?,
+        };
+        if let Some(ip) = self.advertise {
+            registry.set_advertised_ip(ip);
+        }
+        if let Some(journal) = &self.journal {
+            registry.use_journal(journal)?;
+        }
+        for (prefix, peer) in peers {
+            registry.add_peer(prefix, peer);
+        }
+        if let Some((replicas, me)) = replicas {
+            registry.use_replicas(replicas, me)?;
+        }
+        // the daemon has no objects of its own, roles only guard bindings
+        registry.set_authorizer(
+            move |caller: &Principal, _: &str, _: &str, role: Option<&str>| match role {
+                None => true,
+                Some(REGISTRY_WRITE_ROLE) => self.may_write(caller),
+                Some(_) => false,
+            },
+        );
+        Ok(registry)
+    }
+}
+
+#[cfg(feature = "tls")]
+fn start_tls(port: u16, tls: &Tls) -> RMIResult<Arc<Registry>> {
+    let config = match &tls.client_ca {
+        Some(ca) => rrmi::TlsServerConfig::from_pem_mutual(&tls.cert, &tls.key, ca)?,
+        None => rrmi::TlsServerConfig::from_pem(&tls.cert, &tls.key)?,
+    };
+    rrmi::try_create_registry_tls(port, config)
+}
+
+#[cfg(not(feature = "tls"))]
+fn start_tls(_port: u16, _tls: &Tls) -> RMIResult<Arc<Registry>> {
+    Err(RMIError::Tls(
+        "rrmi-registry was built without the tls feature".to_string(),
+    ))
+}
@@ -0,0 +1,79 @@
+//! A registry that lives on its own, for objects of other processes to bind to remotely.
+//!
+//! Usage: `rrmi-registry [--config <file.toml>] [--port <port>] [--advertise <ip>]`
+//!
+//! Logs go to stderr, filtered with `RUST_LOG` (`info` by default). Stops on SIGTERM or SIGINT,
+//! giving the calls in progress a few seconds to finish.
+use std::process::exit;
+use std::time::Duration;
+
+use signal_hook::consts::{SIGINT, SIGTERM};
+use signal_hook::iterator::Signals;
+use tracing_subscriber::EnvFilter;
+
+use crate::config::Config;
+
+mod config;
+mod tests;
+
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+const USAGE: &str =
+    "Usage: rrmi-registry [--config <file.toml>] [--port <port>] [--advertise <ip>]";
+
+/// The config file, if any, with the command line on top.
+fn config(args: Vec<String>) -> Result<Config, String> {
+    let mut config = Config::default();
+    let mut port = None;
+    let mut advertise = None;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{arg} needs a value"));
+        match arg.as_str() {
+            "-c" | "--config" => config = Config::read(value()?).map_err(|e| e.to_string())?,
+            "-p" | "--port" => {
+                let value = value()?;
+                port = Some(value.parse().map_err(|_| format!("invalid port {value}"))?);
+            }
+            "--advertise" => {
+                let value = value()?;
+                advertise = Some(value.parse().map_err(|_| format!("invalid ip {value}"))?);
+            }
+            _ => return Err(USAGE.to_string()),
+        }
+    }
+    config.port = port.or(config.port);
+    config.advertise = advertise.or(config.advertise);
+    Ok(config)
+}
+
+fn main() {
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
+        )
+        .with_writer(std::io::stderr)
+        .init();
+    let config = config(std::env::args().skip(1).collect()).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        exit(2);
+    });
+    let mut signals = Signals::new([SIGTERM, SIGINT]).unwrap_or_else(|e| {
+        eprintln!("Could not listen for signals: {e}");
+        exit(1);
+    });
+    let registry = config.start().unwrap_or_else(|e| {
+        eprintln!("{e}");
+        exit(1);
+    });
+    if let Some(signal) = signals.forever().next() {
+        let names = registry.list().unwrap_or_default();
+        tracing::info!(signal, bound = names.len(), "shutting down");
+        let report = registry.shutdown(SHUTDOWN_TIMEOUT);
+        for call in &report.aborted {
+            tracing::warn!(%call, "call aborted");
+        }
+        if !report.is_clean() {
+            exit(1);
+        }
+    }
+}
@@ -0,0 +1,168 @@
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests {
+    use crate::config;
+    use crate::config::{Auth, Config, Peer, Replication, Tls};
+    use rrmi::remote::{MockRemoteObject, MockRemoteObjectStub, Principal};
+    use rrmi::{PreSharedKey, RemoteRef, Skeleton, get_registry, get_registry_psk};
+    use std::net::{IpAddr, SocketAddr};
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    static DAEMON_PORT: u16 = 10984;
+    static DAEMON_PSK_PORT: u16 = 10983;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn config_file() {
+        let text = r#"
+            port = 2099
+            advertise = "10.0.0.5"
+            journal = "/var/lib/rrmi/bindings"
+
+            [auth]
+            psk_file = "/etc/rrmi/key"
+            writers = ["node1"]
+
+            [tls]
+            cert = "registry.pem"
+            key = "registry.key"
+
+            [[peers]]
+            prefix = "node3"
+            registry = "rrmi://node3:1099"
+
+            [[peers]]
+            registry = "//10.0.0.1"
+
+            [replication]
+            replicas = ["rrmi://node1", "rrmi://node2:2099"]
+            me = 1
+        "#;
+        assert_eq!(
+            Config::parse(text),
+            Ok(Config {
+                port: Some(2099),
+                advertise: Some(IpAddr::from([10, 0, 0, 5])),
+                journal: Some(PathBuf::from("/var/lib/rrmi/bindings")),
+                auth: Auth {
+                    psk: None,
+                    psk_file: Some(PathBuf::from("/etc/rrmi/key")),
+                    remote_binds: false,
+                    writers: vec!["node1".to_string()],
+                },
+                tls: Some(Tls {
+                    cert: PathBuf::from("registry.pem"),
+                    key: PathBuf::from("registry.key"),
+                    client_ca: None,
+                }),
+                peers: vec![
+                    Peer {
+                        prefix: "node3".to_string(),
+                        registry: "rrmi://node3:1099".to_string(),
+                    },
+                    Peer {
+                        prefix: String::new(),
+                        registry: "//10.0.0.1".to_string(),
+                    },
+                ],
+                replication: Some(Replication {
+                    replicas: vec!["rrmi://node1".to_string(), "rrmi://node2:2099".to_string()],
+                    me: 1,
+                }),
+            })
+        );
+        assert_eq!(Config::parse(""), Ok(Config::default()));
+        assert!(Config::parse("prot = 2099").is_err());
+        assert!(Config::parse("advertise = \"not an ip\"").is_err());
+    }
+
+    #[test]
+    fn command_line() {
+        let parsed = config(args("--port 3000 --advertise ::1")).expect("valid");
+        assert_eq!(parsed.port, Some(3000));
+        assert_eq!(parsed.advertise, Some("::1".parse().unwrap()));
+        assert!(config(args("--port")).is_err());
+        assert!(config(args("--port many")).is_err());
+        assert!(config(args("--config /no/such/file.toml")).is_err());
+        assert!(config(args("serve")).is_err());
+    }
+
+    #[test]
+    fn writers() {
+        let caller = |name: Option<&str>| Principal {
+            peer_addr: Some(SocketAddr::from(([192, 0, 2, 1], 40000))),
+            local: false,
+            name: name.map(str::to_string),
+        };
+        let config = Config::parse("[auth]\nwriters = [\"node1\"]").expect("valid");
+        assert!(config.may_write(&caller(Some("node1"))));
+        assert!(!config.may_write(&caller(Some("node2"))));
+        assert!(!config.may_write(&caller(None)));
+        let open = Config::parse("[auth]\nremote_binds = true").expect("valid");
+        assert!(open.may_write(&caller(None)));
+    }
+
+    #[test]
+    fn conflicting_auth() {
+        let both = "[auth]\npsk = \"a\"\npsk_file = \"b\"";
+        assert!(Config::parse(both).expect("valid toml").start().is_err());
+        let psk_and_tls = "[auth]\npsk = \"a\"\n[tls]\ncert = \"c\"\nkey = \"k\"";
+        assert!(
+            Config::parse(psk_and_tls)
+                .expect("valid toml")
+                .start()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn busy_port() {
+        let taken = std::net::TcpListener::bind(("::", 0)).expect("any port is free");
+        let port = taken.local_addr().expect("listening").port();
+        let config = Config {
+            port: Some(port),
+            ..Config::default()
+        };
+        assert!(config.start().is_err());
+    }
+
+    #[test]
+    fn serves_remote_binds() {
+        let config = Config {
+            port: Some(DAEMON_PORT),
+            advertise: Some(IpAddr::from([127, 0, 0, 1])),
+            ..Config::default()
+        };
+        let _registry = config.start().expect("port is free");
+        let exported = Skeleton::new(Arc::new(MockRemoteObject::silent()));
+        let port = exported.export().expect("exported object listens");
+        let remote = RemoteRef::new(SocketAddr::from(([127, 0, 0, 1], port)), 0);
+
+        let rmt_reg = get_registry("localhost", DAEMON_PORT).expect("daemon is listening");
+        rmt_reg
+            .bind("mock", remote)
+            .expect("callers on this host may bind");
+        let stub: MockRemoteObjectStub = rmt_reg.lookup("mock").expect("mock is in").into();
+        assert_eq!(stub.run("daemon", vec![4; 2]), Ok(vec![4; 2]));
+    }
+
+    #[test]
+    fn pre_shared_key() {
+        let config = Config {
+            port: Some(DAEMON_PSK_PORT),
+            auth: Auth {
+                psk: Some("open sesame".to_string()),
+                ..Auth::default()
+            },
+            ..Config::default()
+        };
+        let _registry = config.start().expect("port is free");
+        let key = PreSharedKey::new("open sesame");
+        let rmt_reg = get_registry_psk("localhost", DAEMON_PSK_PORT, key).expect("key is right");
+        assert_eq!(rmt_reg.list(), Ok(vec![]));
+    }
+}